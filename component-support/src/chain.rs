@@ -0,0 +1,64 @@
+//! Reading contracts over the JSON-RPC endpoint of a chain from the WAVS config
+
+use crate::bindings::host::get_eth_chain_config;
+use alloy_primitives::Address;
+use alloy_provider::{
+    network::{Ethereum, TransactionBuilder},
+    Provider, RootProvider,
+};
+use alloy_rpc_types_eth::{BlockId, BlockNumberOrTag, TransactionRequest};
+use alloy_sol_types::SolCall;
+use anyhow::{anyhow, Result};
+use std::str::FromStr;
+use wavs_wasi_chain::ethereum::new_eth_provider;
+
+/// A provider for the chain's `http_endpoint`
+pub fn provider(chain_name: &str) -> Result<RootProvider<Ethereum>> {
+    let endpoint = get_eth_chain_config(chain_name)
+        .ok_or_else(|| anyhow!("unknown chain {}", chain_name))?
+        .http_endpoint
+        .ok_or_else(|| anyhow!("chain {} has no http_endpoint", chain_name))?;
+    Ok(new_eth_provider::<Ethereum>(endpoint))
+}
+
+/// `eth_call` on the latest block, which may differ by a block or two between operators
+pub async fn call<C: SolCall>(
+    provider: &RootProvider<Ethereum>,
+    to: Address,
+    call: C,
+) -> Result<C::Return> {
+    call_at(provider, to, call, BlockNumberOrTag::Latest.into()).await
+}
+
+/// `eth_call` on a given block
+pub async fn call_at<C: SolCall>(
+    provider: &RootProvider<Ethereum>,
+    to: Address,
+    call: C,
+    block: BlockId,
+) -> Result<C::Return> {
+    let tx = TransactionRequest::default().with_to(to).with_input(call.abi_encode());
+    let output = provider.call(&tx).block(block).await?;
+    Ok(C::abi_decode_returns(&output, true)?)
+}
+
+/// What happens to a price that fails a check against the chain
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CheckAction {
+    /// Fail the run, also when the chain can't be read
+    Refuse,
+    /// Publish it with the check marked as flagged
+    Flag,
+}
+
+impl FromStr for CheckAction {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "refuse" => Ok(CheckAction::Refuse),
+            "flag" => Ok(CheckAction::Flag),
+            _ => Err(()),
+        }
+    }
+}
@@ -0,0 +1,20 @@
+/// Reads a service config `kv` entry, which the host exposes as an environment variable
+pub fn env_var<T: std::str::FromStr>(key: &str) -> Option<T> {
+    std::env::var(key).ok()?.parse().ok()
+}
+
+/// Reads a secret from the host environment.
+/// Only variables listed in the service config `host_envs` are visible, and they must be
+/// prefixed with `WAVS_ENV_`. Secrets never belong in `kv`, which is public.
+pub fn host_secret(key: &str) -> Option<String> {
+    std::env::var(key).ok().filter(|value| !value.is_empty())
+}
+
+/// Every `host_envs` value, which are all treated as secrets when logging.
+/// Values this short would mostly redact unrelated text.
+pub fn host_secrets() -> Vec<String> {
+    std::env::vars()
+        .filter(|(key, value)| key.starts_with("WAVS_ENV_") && value.len() >= 6)
+        .map(|(_, value)| value)
+        .collect()
+}
@@ -0,0 +1,193 @@
+//! Retries around upstream HTTP calls.
+//!
+//! Every attempt costs fuel, so the number of attempts and the backoff are capped no matter
+//! what the service config asks for. Waiting on a timer doesn't burn fuel, but the run has to
+//! finish in time, so all attempts of a request together are bounded by a deadline.
+
+use crate::{
+    config::env_var,
+    log::{debug, info},
+};
+use std::fmt;
+use wstd::{
+    future::FutureExt,
+    http::{Client, Request, StatusCode},
+    io::{empty, AsyncRead, Empty},
+    task::sleep,
+    time::{Duration, Instant},
+};
+
+/// Upper bound on attempts per request, whatever the config says
+pub const MAX_ATTEMPTS: u32 = 5;
+/// Upper bound on a single backoff (including a server's `Retry-After`), in milliseconds
+pub const MAX_BACKOFF_MS: u64 = 5_000;
+/// Upper bound on the time spent on a request, all attempts and backoffs included,
+/// in milliseconds
+pub const MAX_DEADLINE_MS: u64 = 30_000;
+
+/// Read from the service config `kv`, falling back to the defaults
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first one
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled after each further attempt
+    pub backoff_ms: u64,
+    /// Each attempt is abandoned after this long
+    pub timeout_ms: u64,
+    /// The request is abandoned this long after the first attempt started, and no retry is
+    /// made that would have to wait past it
+    pub deadline_ms: u64,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self { max_attempts: 3, backoff_ms: 250, timeout_ms: 5_000, deadline_ms: 10_000 }
+    }
+}
+
+impl RetryPolicy {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            max_attempts: env_var("http_max_attempts")
+                .unwrap_or(default.max_attempts)
+                .clamp(1, MAX_ATTEMPTS),
+            backoff_ms: env_var("http_backoff_ms").unwrap_or(default.backoff_ms),
+            timeout_ms: env_var("http_timeout_ms").unwrap_or(default.timeout_ms),
+            deadline_ms: env_var("http_deadline_ms")
+                .unwrap_or(default.deadline_ms)
+                .min(MAX_DEADLINE_MS),
+        }
+    }
+
+    /// Wait before retrying after `attempt` (1-based) failed
+    fn backoff(&self, attempt: u32, error: &FetchError) -> Duration {
+        let ms = match error {
+            FetchError::RateLimited { retry_after: Some(secs) } => secs.saturating_mul(1000),
+            _ => self.backoff_ms.saturating_mul(1 << (attempt - 1).min(16)),
+        };
+        Duration::from_millis(ms.min(MAX_BACKOFF_MS))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum FetchError {
+    /// HTTP 429, with the `Retry-After` delay in seconds if the server sent one
+    RateLimited {
+        retry_after: Option<u64>,
+    },
+    Status(StatusCode),
+    Timeout,
+    Transport(String),
+}
+
+impl FetchError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            FetchError::RateLimited { retry_after } => {
+                retry_after.map_or(true, |secs| secs.saturating_mul(1000) <= MAX_BACKOFF_MS)
+            }
+            FetchError::Status(status) => status.is_server_error(),
+            FetchError::Timeout | FetchError::Transport(_) => true,
+        }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::RateLimited { retry_after: Some(secs) } => {
+                write!(f, "rate limited (retry after {}s)", secs)
+            }
+            FetchError::RateLimited { retry_after: None } => write!(f, "rate limited"),
+            FetchError::Status(status) => write!(f, "HTTP {}", status),
+            FetchError::Timeout => write!(f, "timed out"),
+            FetchError::Transport(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// Sends `req` until it succeeds, fails with a non-retryable error, or runs out of attempts or
+/// time. Returns the body of the first successful response, or the last error.
+pub async fn fetch_with_retry(
+    req: Request<Empty>,
+    policy: &RetryPolicy,
+) -> Result<Vec<u8>, FetchError> {
+    let first_start = Instant::now();
+    let remaining_ms =
+        || policy.deadline_ms.saturating_sub(first_start.elapsed().as_millis() as u64);
+
+    let mut attempt = 1;
+    loop {
+        let start = Instant::now();
+        let timeout_ms = policy.timeout_ms.min(remaining_ms());
+        let res = fetch_once(clone_request(&req), timeout_ms).await;
+        let elapsed_ms = start.elapsed().as_millis();
+
+        let error = match res {
+            Ok(body) => {
+                debug!("{}: {} bytes in {}ms", req.uri(), body.len(), elapsed_ms);
+                return Ok(body);
+            }
+            Err(e) => {
+                debug!("{}: {} after {}ms", req.uri(), e, elapsed_ms);
+                e
+            }
+        };
+
+        if attempt >= policy.max_attempts || !error.is_retryable() {
+            return Err(error);
+        }
+
+        // a retry that can't start before the deadline isn't worth waiting for
+        let backoff = policy.backoff(attempt, &error);
+        if backoff.as_millis() as u64 >= remaining_ms() {
+            info!("{} failed ({}), no time left for attempt {}", req.uri(), error, attempt + 1);
+            return Err(error);
+        }
+
+        info!("{} failed ({}), attempt {}/{}", req.uri(), error, attempt, policy.max_attempts);
+        sleep(backoff).await;
+        attempt += 1;
+    }
+}
+
+async fn fetch_once(req: Request<Empty>, timeout_ms: u64) -> Result<Vec<u8>, FetchError> {
+    async {
+        let mut res =
+            Client::new().send(req).await.map_err(|e| FetchError::Transport(e.to_string()))?;
+
+        match res.status() {
+            StatusCode::TOO_MANY_REQUESTS => {
+                let retry_after = res
+                    .headers()
+                    .get("retry-after")
+                    .and_then(|value| value.to_str().ok()?.trim().parse().ok());
+                return Err(FetchError::RateLimited { retry_after });
+            }
+            status if !status.is_success() => return Err(FetchError::Status(status)),
+            _ => {}
+        }
+
+        let mut body = Vec::new();
+        res.body_mut()
+            .read_to_end(&mut body)
+            .await
+            .map_err(|e| FetchError::Transport(e.to_string()))?;
+        Ok(body)
+    }
+    .timeout(Duration::from_millis(timeout_ms))
+    .await
+    .map_err(|_| FetchError::Timeout)?
+}
+
+/// Requests are consumed when sent, so each attempt sends a copy
+fn clone_request(req: &Request<Empty>) -> Request<Empty> {
+    let mut clone = Request::new(empty());
+    *clone.method_mut() = req.method().clone();
+    *clone.uri_mut() = req.uri().clone();
+    *clone.headers_mut() = req.headers().clone();
+    clone
+}
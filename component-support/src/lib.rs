@@ -0,0 +1,95 @@
+//! What every component in this repo shares: the WAVS bindings, reading triggers, publishing
+//! results and the helpers for config, logging, HTTP and contract calls.
+//!
+//! A component implements [`Oracle`] and hands its trigger to [`run`]:
+//!
+//! ```ignore
+//! struct Component;
+//! export!(Component with_types_in component_support::bindings);
+//!
+//! impl Guest for Component {
+//!     fn run(action: TriggerAction) -> Result<Vec<u8>, String> {
+//!         component_support::run::<Component>(action)
+//!     }
+//! }
+//! ```
+
+pub mod chain;
+pub mod config;
+pub mod fetch;
+pub mod log;
+pub mod time;
+pub mod trigger;
+
+#[allow(warnings, clippy::all)]
+pub mod bindings {
+    wit_bindgen::generate!({
+        world: "layer-trigger-world",
+        path: "wit",
+        pub_export_macro: true,
+        default_bindings_module: "component_support::bindings",
+    });
+}
+
+use bindings::TriggerAction;
+use log::{info, warn};
+use shared::{
+    abi::{AssetPrice, ResultEncoding},
+    error::OracleError,
+};
+use std::future::Future;
+use trigger::{
+    decode_trigger_event, encode_cosmos_output, encode_trigger_output, Destination, TriggerEvent,
+};
+use wstd::runtime::block_on;
+
+/// What a component answers a trigger with
+pub trait Oracle {
+    /// The result for a decoded trigger, which [`run`] publishes
+    fn get_output(event: &TriggerEvent) -> impl Future<Output = Result<Output, OracleError>>;
+}
+
+/// An encoded result and how it's encoded, which `SimpleSubmit` records so that its getters
+/// only decode a result the way it was written
+pub struct Output {
+    pub data: Vec<u8>,
+    pub encoding: ResultEncoding,
+    /// The prices `SimpleSubmit` keeps as each asset's latest, for price feed results
+    pub prices: Vec<AssetPrice>,
+}
+
+impl Output {
+    pub fn new(data: Vec<u8>, encoding: ResultEncoding) -> Self {
+        Self { data, encoding, prices: Vec::new() }
+    }
+
+    pub fn with_prices(mut self, prices: Vec<AssetPrice>) -> Self {
+        self.prices = prices;
+        self
+    }
+}
+
+/// Answers a trigger: the result goes back to the chain it came from, or to the CLI
+pub fn run<O: Oracle>(action: TriggerAction) -> Result<Vec<u8>, String> {
+    let event = decode_trigger_event(action.data).map_err(|e| e.to_string())?;
+    let trigger_id = event.trigger_id;
+
+    let res = match &event.error {
+        Some(e) => Err(e.clone()),
+        None => block_on(O::get_output(&event)),
+    };
+    if let Err(e) = &res {
+        warn!("trigger {} failed: {}", trigger_id, e);
+    }
+
+    let output = match event.dest {
+        // failures are published too, so the requester doesn't wait for a result that never comes
+        Destination::Ethereum => Ok(encode_trigger_output(&event, res)),
+        Destination::Cosmos => Ok(encode_cosmos_output(trigger_id, res.map(|output| output.data))),
+        Destination::CliOutput => res.map(|output| output.data).map_err(|e| e.to_string()),
+    };
+    if let Ok(output) = &output {
+        info!("publishing {} bytes for trigger {}", output.len(), trigger_id);
+    }
+    output
+}
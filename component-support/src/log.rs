@@ -0,0 +1,55 @@
+//! Logging through the host's `log` import, which operators can filter by level.
+//!
+//! Messages are formatted like `println!`. Values of `host_envs` variables (API keys) are
+//! replaced with `[REDACTED]` first, since upstream errors can echo them back.
+
+use crate::{bindings::host::LogLevel, config::host_secrets};
+use std::{fmt, sync::LazyLock};
+
+static SECRETS: LazyLock<Vec<String>> = LazyLock::new(host_secrets);
+
+pub fn log(level: LogLevel, args: fmt::Arguments) {
+    let message = redact(&args.to_string());
+
+    // the import only exists inside a WAVS host, native builds (e.g. `cargo test`) print instead
+    #[cfg(target_arch = "wasm32")]
+    crate::bindings::host::log(level, &message);
+    #[cfg(not(target_arch = "wasm32"))]
+    eprintln!("[{:?}] {}", level, message);
+}
+
+fn redact(message: &str) -> String {
+    SECRETS
+        .iter()
+        .fold(message.to_string(), |message, secret| message.replace(secret, "[REDACTED]"))
+}
+
+// exported from the crate root under hidden names, and re-exported here under the short ones
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_error {
+    ($($arg:tt)*) => { $crate::log::log($crate::bindings::host::LogLevel::Error, format_args!($($arg)*)) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_warn {
+    ($($arg:tt)*) => { $crate::log::log($crate::bindings::host::LogLevel::Warn, format_args!($($arg)*)) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_info {
+    ($($arg:tt)*) => { $crate::log::log($crate::bindings::host::LogLevel::Info, format_args!($($arg)*)) };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __log_debug {
+    ($($arg:tt)*) => { $crate::log::log($crate::bindings::host::LogLevel::Debug, format_args!($($arg)*)) };
+}
+
+pub use crate::{
+    __log_debug as debug, __log_error as error, __log_info as info, __log_warn as warn,
+};
@@ -0,0 +1,355 @@
+use crate::{
+    bindings::{
+        host::{get_cosmos_chain_config, get_eth_chain_config},
+        wavs::worker::layer_types::{
+            CosmosEvent, TriggerData, TriggerDataCosmosContractEvent, TriggerDataEthContractEvent,
+        },
+    },
+    chain,
+    fetch::{fetch_with_retry, RetryPolicy},
+    log::warn,
+    time, Output,
+};
+use alloy_primitives::{Address, B256};
+use alloy_provider::{network::Ethereum, Provider};
+use alloy_rpc_types_eth::{BlockId, BlockNumberOrTag, BlockTransactionsKind, Filter, Log};
+use alloy_sol_types::{SolEvent, SolValue};
+use anyhow::{anyhow, Result};
+use shared::{
+    abi::{request_hash, DataWithId, ErrorCode, NewTrigger, ResultEncoding, TriggerInfo},
+    cosmos::{CosmosTriggerResult, NEW_TRIGGER_EVENT, TRIGGER_ID_ATTRIBUTE},
+    error::OracleError,
+    request::TriggerRequest,
+};
+use wavs_wasi_chain::{decode_event_log_data, ethereum::new_eth_provider, http::http_request_get};
+
+pub enum Destination {
+    Ethereum,
+    Cosmos,
+    CliOutput,
+}
+
+/// The request is decoded separately, so that a bad request can still be answered
+/// with an error for its trigger
+pub struct TriggerEvent {
+    pub trigger_id: u64,
+    pub request: RequestPayload,
+    pub dest: Destination,
+    /// See [`request_hash`], zero unless the trigger is a `NewTrigger` log
+    pub request_hash: B256,
+    /// Set if the trigger can only be answered with this error, whatever the request
+    pub error: Option<OracleError>,
+    block_height: Option<u64>,
+    log: Option<TriggerLog>,
+    /// The chain a Cosmos event came from
+    cosmos_chain: Option<String>,
+}
+
+pub enum RequestPayload {
+    /// An encoded request, from a `NewTrigger` log or the CLI
+    Bytes(Vec<u8>),
+    /// The attributes of a Cosmos event, see [`TriggerRequest::from_attributes`]
+    Attributes(Vec<(String, String)>),
+}
+
+impl RequestPayload {
+    pub fn decode<R: TriggerRequest>(&self) -> Result<R, R::Error> {
+        match self {
+            RequestPayload::Bytes(data) => R::decode(data),
+            RequestPayload::Attributes(attributes) => R::from_attributes(
+                attributes.iter().map(|(key, value)| (key.as_str(), value.as_str())),
+            ),
+        }
+    }
+}
+
+/// The `NewTrigger` log, as far as the host tells us about it
+struct TriggerLog {
+    chain_name: String,
+    address: Address,
+    creator: Address,
+    block_height: u64,
+    data: Vec<u8>,
+}
+
+/// Where a request was made on-chain, so results can be ordered against other chain events
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TriggerAnchor {
+    pub block_height: Option<u64>,
+    pub tx_hash: Option<B256>,
+}
+
+/// Exactly which `NewTrigger` log a request came from, so it can be told apart from any other
+#[derive(Debug, Clone, PartialEq)]
+pub struct TriggerOrigin {
+    pub chain_name: String,
+    pub chain_id: u64,
+    pub contract: Address,
+    pub creator: Address,
+    pub block_height: u64,
+    pub block_hash: B256,
+    pub log_index: u64,
+    pub tx_hash: B256,
+}
+
+pub fn decode_trigger_event(trigger_data: TriggerData) -> Result<TriggerEvent> {
+    match trigger_data {
+        TriggerData::EthContractEvent(TriggerDataEthContractEvent {
+            log,
+            contract_address,
+            chain_name,
+            block_height,
+        }) => {
+            let data = log.data.clone();
+            let event: NewTrigger = decode_event_log_data!(log)?;
+            let trigger_info = TriggerInfo::abi_decode(&event._0, false)?;
+            let address = Address::from_slice(&contract_address.raw_bytes);
+            // a result can't be bound to the request without the chain ID, so only the error is
+            // published, for the requester to see rather than wait
+            let chain_id =
+                get_eth_chain_config(&chain_name).and_then(|config| config.chain_id.parse().ok());
+            let error = chain_id.is_none().then(|| {
+                OracleError::BadInput(format!("No chain ID for {} in the WAVS config", chain_name))
+            });
+            Ok(TriggerEvent {
+                trigger_id: trigger_info.triggerId,
+                request: RequestPayload::Bytes(trigger_info.data.to_vec()),
+                dest: Destination::Ethereum,
+                request_hash: chain_id
+                    .map(|chain_id| request_hash(chain_id, address, &trigger_info))
+                    .unwrap_or_default(),
+                error,
+                block_height: Some(block_height),
+                log: Some(TriggerLog {
+                    chain_name,
+                    address,
+                    creator: trigger_info.creator,
+                    block_height,
+                    data,
+                }),
+                cosmos_chain: None,
+            })
+        }
+        TriggerData::CosmosContractEvent(TriggerDataCosmosContractEvent {
+            event,
+            chain_name,
+            block_height,
+            ..
+        }) => Ok(TriggerEvent {
+            trigger_id: cosmos_trigger_id(&event)?,
+            request: RequestPayload::Attributes(event.attributes),
+            dest: Destination::Cosmos,
+            request_hash: B256::ZERO,
+            error: None,
+            block_height: Some(block_height),
+            log: None,
+            cosmos_chain: Some(chain_name),
+        }),
+        TriggerData::Raw(data) => Ok(TriggerEvent {
+            trigger_id: 0,
+            request: RequestPayload::Bytes(data),
+            dest: Destination::CliOutput,
+            request_hash: B256::ZERO,
+            error: None,
+            block_height: None,
+            log: None,
+            cosmos_chain: None,
+        }),
+    }
+}
+
+/// Without a trigger ID there's nobody to answer, so unlike a bad request this fails the run
+fn cosmos_trigger_id(event: &CosmosEvent) -> Result<u64> {
+    if event.ty != NEW_TRIGGER_EVENT {
+        return Err(anyhow!("Unexpected Cosmos event type {}", event.ty));
+    }
+
+    let (_, trigger_id) = event
+        .attributes
+        .iter()
+        .find(|(key, _)| key == TRIGGER_ID_ATTRIBUTE)
+        .ok_or_else(|| anyhow!("Missing {} attribute", TRIGGER_ID_ATTRIBUTE))?;
+    trigger_id
+        .trim()
+        .parse()
+        .map_err(|_| anyhow!("Invalid {}: {}", TRIGGER_ID_ATTRIBUTE, trigger_id))
+}
+
+impl TriggerEvent {
+    pub async fn anchor(&self) -> TriggerAnchor {
+        let tx_hash = match &self.log {
+            Some(log) => find_tx_hash(log).await,
+            None => None,
+        };
+        TriggerAnchor { block_height: self.block_height, tx_hash }
+    }
+
+    /// The chain and block of the `NewTrigger` log, `None` for other triggers. Reading the chain
+    /// at this block rather than the latest one gives every operator the same state.
+    pub fn log_block(&self) -> Option<(&str, u64)> {
+        self.log.as_ref().map(|log| (log.chain_name.as_str(), log.block_height))
+    }
+
+    /// The block of `chain` to read so that every operator reads the same state: the trigger's
+    /// block for a `NewTrigger` log on that chain, the latest one for a CLI run. Any other chain
+    /// has no block that all operators agree on.
+    pub fn read_block(&self, chain: &str) -> Result<BlockId, OracleError> {
+        match (&self.dest, self.log_block()) {
+            (Destination::CliOutput, _) => Ok(BlockNumberOrTag::Latest.into()),
+            (_, Some((trigger_chain, block))) if trigger_chain == chain => {
+                Ok(BlockNumberOrTag::Number(block).into())
+            }
+            _ => Err(OracleError::BadInput(format!(
+                "{} can only be read for triggers from it, to read it at the trigger block",
+                chain
+            ))),
+        }
+    }
+
+    /// When the trigger's block was made, in unix seconds. Unlike the wall clock it's the same for
+    /// every operator, so results that depend on the time go by it. A CLI run has no block and
+    /// gets the current time.
+    pub async fn block_time(&self) -> Result<u64, OracleError> {
+        match (&self.log, &self.cosmos_chain, self.block_height) {
+            (Some(log), _, _) => eth_block_time(&log.chain_name, log.block_height).await,
+            (None, Some(chain_name), Some(height)) => cosmos_block_time(chain_name, height).await,
+            _ => Ok(time::now()),
+        }
+    }
+
+    /// `None` if the request didn't come from a `NewTrigger` log. Unlike [`Self::anchor`] a
+    /// failed lookup is an error, for results that can't do without it.
+    pub async fn origin(&self) -> Result<Option<TriggerOrigin>, OracleError> {
+        let Some(log) = &self.log else {
+            return Ok(None);
+        };
+
+        let config = get_eth_chain_config(&log.chain_name)
+            .ok_or_else(|| OracleError::BadInput(format!("Unknown chain {}", log.chain_name)))?;
+        let chain_id = config.chain_id.parse().map_err(|_| {
+            OracleError::BadInput(format!("Chain {} has no numeric chain ID", log.chain_name))
+        })?;
+        let endpoint = config.http_endpoint.ok_or_else(|| {
+            OracleError::BadInput(format!("No RPC endpoint for chain {}", log.chain_name))
+        })?;
+
+        let unavailable =
+            || OracleError::UpstreamUnavailable(format!("No response from {}", log.chain_name));
+        let found = find_log(log, endpoint)
+            .await
+            .map_err(|e| {
+                warn!("couldn't look up trigger {}: {}", self.trigger_id, e);
+                unavailable()
+            })?
+            // the endpoint is behind the node that saw the log
+            .ok_or_else(unavailable)?;
+
+        Ok(Some(TriggerOrigin {
+            chain_name: log.chain_name.clone(),
+            chain_id,
+            contract: log.address,
+            creator: log.creator,
+            block_height: log.block_height,
+            block_hash: found.block_hash.ok_or_else(unavailable)?,
+            log_index: found.log_index.ok_or_else(unavailable)?,
+            tx_hash: found.transaction_hash.ok_or_else(unavailable)?,
+        }))
+    }
+}
+
+async fn eth_block_time(chain_name: &str, height: u64) -> Result<u64, OracleError> {
+    let provider = chain::provider(chain_name)
+        .map_err(|_| OracleError::BadInput(format!("No RPC endpoint for chain {}", chain_name)))?;
+    let unavailable =
+        || OracleError::UpstreamUnavailable(format!("No response from {}", chain_name));
+
+    let block = provider
+        .get_block_by_number(BlockNumberOrTag::Number(height), BlockTransactionsKind::Hashes)
+        .await
+        .map_err(|e| {
+            warn!("couldn't read block {} of {}: {}", height, chain_name, e);
+            unavailable()
+        })?
+        // the endpoint is behind the node that saw the trigger
+        .ok_or_else(unavailable)?;
+    Ok(block.header.timestamp)
+}
+
+/// From the CometBFT RPC `block` endpoint, whose header time is RFC 3339
+async fn cosmos_block_time(chain_name: &str, height: u64) -> Result<u64, OracleError> {
+    let endpoint =
+        get_cosmos_chain_config(chain_name).and_then(|config| config.rpc_endpoint).ok_or_else(
+            || OracleError::BadInput(format!("No RPC endpoint for chain {}", chain_name)),
+        )?;
+    let unavailable =
+        || OracleError::UpstreamUnavailable(format!("No response from {}", chain_name));
+
+    let url = format!("{}/block?height={}", endpoint.trim_end_matches('/'), height);
+    let req = http_request_get(&url).map_err(|_| {
+        OracleError::BadInput(format!("Invalid RPC endpoint for chain {}", chain_name))
+    })?;
+    let body = fetch_with_retry(req, &RetryPolicy::from_env()).await.map_err(|e| {
+        warn!("couldn't read block {} of {}: {}", height, chain_name, e);
+        unavailable()
+    })?;
+
+    // e.g. {"result":{"block":{"header":{"height":"1234","time":"2025-02-04T17:46:41.612Z"}}}}
+    serde_json::from_slice::<serde_json::Value>(&body)
+        .ok()
+        .and_then(|json| json.pointer("/result/block/header/time")?.as_str().map(str::to_owned))
+        .and_then(|time| time::parse_rfc3339(&time))
+        .ok_or_else(|| {
+            OracleError::ParseFailure(format!("No time for block {} of {}", height, chain_name))
+        })
+}
+
+/// `None` if the chain has no HTTP endpoint or the lookup fails
+async fn find_tx_hash(log: &TriggerLog) -> Option<B256> {
+    let endpoint = get_eth_chain_config(&log.chain_name)?.http_endpoint?;
+    match find_log(log, endpoint).await {
+        Ok(found) => found.and_then(|found| found.transaction_hash),
+        Err(e) => {
+            warn!("couldn't look up the trigger transaction: {}", e);
+            None
+        }
+    }
+}
+
+/// The host only passes the log's topics and data, so the rest is found by looking for the
+/// same log in its block
+async fn find_log(log: &TriggerLog, endpoint: String) -> Result<Option<Log>> {
+    let provider = new_eth_provider::<Ethereum>(endpoint);
+
+    let filter = Filter::new()
+        .address(log.address)
+        .select(log.block_height)
+        .event_signature(NewTrigger::SIGNATURE_HASH);
+
+    Ok(provider
+        .get_logs(&filter)
+        .await?
+        .into_iter()
+        .find(|found| found.data().data.as_ref() == log.data.as_slice()))
+}
+
+/// A failure is published as its message, with its code and no encoding
+pub fn encode_trigger_output(event: &TriggerEvent, result: Result<Output, OracleError>) -> Vec<u8> {
+    let (data, error, encoding, prices) = match result {
+        Ok(output) => (output.data, ErrorCode::None, output.encoding, output.prices),
+        Err(e) => (e.message().as_bytes().to_vec(), e.code(), ResultEncoding::None, Vec::new()),
+    };
+    DataWithId {
+        triggerId: event.trigger_id,
+        data: data.into(),
+        error,
+        requestHash: event.request_hash,
+        encoding,
+        prices,
+    }
+    .abi_encode()
+}
+
+/// JSON for the submitting CosmWasm contract, see [`CosmosTriggerResult`]
+pub fn encode_cosmos_output(trigger_id: u64, result: Result<Vec<u8>, OracleError>) -> Vec<u8> {
+    CosmosTriggerResult::new(trigger_id, result).encode()
+}
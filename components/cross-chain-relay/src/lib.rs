@@ -0,0 +1,78 @@
+use alloy_sol_types::SolValue;
+use component_support::{
+    bindings::{export, host::get_eth_chain_config, Guest, TriggerAction},
+    config::env_var,
+    log::{debug, info},
+    trigger::TriggerEvent,
+    Oracle, Output,
+};
+use shared::{
+    abi::{RelayMessage, ResultEncoding},
+    error::OracleError,
+    relay::RelayRequest,
+};
+
+struct Component;
+export!(Component with_types_in component_support::bindings);
+
+impl Guest for Component {
+    fn run(action: TriggerAction) -> std::result::Result<Vec<u8>, String> {
+        component_support::run::<Component>(action)
+    }
+}
+
+impl Oracle for Component {
+    async fn get_output(event: &TriggerEvent) -> Result<Output, OracleError> {
+        let req: RelayRequest = event.request.decode()?;
+        info!("request: {:?}", req);
+
+        let destination = destination(req.destination, env_var("destination"))?;
+        let destination_chain_id = get_eth_chain_config(&destination)
+            .and_then(|config| config.chain_id.parse::<u64>().ok())
+            .ok_or_else(|| OracleError::BadInput(format!("Unknown EVM chain {}", destination)))?;
+
+        // the log index is what tells two triggers in one block apart, so there's no relaying
+        // without it
+        let origin = event.origin().await?.ok_or_else(|| {
+            OracleError::BadInput("Only NewTrigger logs can be relayed".to_string())
+        })?;
+        if origin.chain_id == destination_chain_id {
+            return Err(OracleError::BadInput(format!(
+                "{} is the chain the trigger came from",
+                destination
+            )));
+        }
+        debug!(
+            "relaying log {} of block {} on {} to {}",
+            origin.log_index, origin.block_height, origin.chain_name, destination
+        );
+
+        let message = RelayMessage {
+            sourceChainId: origin.chain_id,
+            sourceBlock: origin.block_height,
+            sourceBlockHash: origin.block_hash,
+            logIndex: origin.log_index,
+            sourceTrigger: origin.contract,
+            sourceTxHash: origin.tx_hash,
+            creator: origin.creator,
+            destinationChainId: destination_chain_id,
+            payload: req.payload,
+        };
+        Ok(Output::new(message.abi_encode(), ResultEncoding::RelayMessage))
+    }
+}
+
+/// A destination in the service config is fixed, a request may leave it out or repeat it
+fn destination(
+    requested: Option<String>,
+    configured: Option<String>,
+) -> Result<String, OracleError> {
+    match (requested, configured) {
+        (Some(requested), Some(configured)) if requested != configured => {
+            Err(OracleError::BadInput("`destination` is fixed by the service config".to_string()))
+        }
+        (requested, configured) => configured.or(requested).ok_or_else(|| {
+            OracleError::BadInput("No destination in the request or the service config".to_string())
+        }),
+    }
+}
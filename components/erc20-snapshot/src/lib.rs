@@ -0,0 +1,41 @@
+mod snapshot;
+use alloy_sol_types::SolValue;
+use component_support::{
+    bindings::{export, Guest, TriggerAction},
+    config::env_var,
+    log::{debug, info},
+    trigger::TriggerEvent,
+    Oracle, Output,
+};
+use shared::{abi::ResultEncoding, erc20_snapshot::SnapshotRequest, error::OracleError};
+
+struct Component;
+export!(Component with_types_in component_support::bindings);
+
+impl Guest for Component {
+    fn run(action: TriggerAction) -> std::result::Result<Vec<u8>, String> {
+        component_support::run::<Component>(action)
+    }
+}
+
+impl Oracle for Component {
+    async fn get_output(event: &TriggerEvent) -> Result<Output, OracleError> {
+        let req: SnapshotRequest = event.request.decode()?;
+        info!("request: {:?}", req);
+
+        let chain = req.chain.clone().or_else(|| env_var("chain")).ok_or_else(|| {
+            OracleError::BadInput("No chain in the request or the service config".to_string())
+        })?;
+
+        let block = snapshot::block_id(&chain, &req, event)?;
+        let snapshot = snapshot::read(&chain, &req, block).await?;
+        debug!(
+            "total supply {} and {} balances at block {}",
+            snapshot.totalSupply,
+            snapshot.balances.len(),
+            snapshot.blockNumber
+        );
+
+        Ok(Output::new(snapshot.abi_encode(), ResultEncoding::TokenSnapshot))
+    }
+}
@@ -0,0 +1,121 @@
+//! Reading the balances, all at the one block the request resolves to
+
+use alloy_primitives::{Address, U256};
+use alloy_provider::{network::Ethereum, Provider, RootProvider};
+use alloy_rpc_types_eth::{BlockId, BlockNumberOrTag, BlockTransactionsKind};
+use alloy_sol_types::SolCall;
+use alloy_transport::TransportError;
+use component_support::{
+    chain::{self, call_at},
+    log::warn,
+    trigger::TriggerEvent,
+};
+use futures::future::join_all;
+use shared::{
+    abi::TokenSnapshot,
+    erc20_snapshot::{BlockTag, SnapshotRequest},
+    error::OracleError,
+};
+
+/// Reads the snapshot at `block`, see [`block_id`]
+pub async fn read(
+    chain_name: &str,
+    req: &SnapshotRequest,
+    block: BlockId,
+) -> Result<TokenSnapshot, OracleError> {
+    let provider = chain::provider(chain_name)
+        .map_err(|_| OracleError::BadInput(format!("No RPC endpoint for chain {}", chain_name)))?;
+    let unavailable = |e: TransportError| {
+        warn!("{} RPC failed: {}", chain_name, e);
+        OracleError::UpstreamUnavailable(format!("No response from {}", chain_name))
+    };
+
+    let block = provider
+        .get_block(block, BlockTransactionsKind::Hashes)
+        .await
+        .map_err(unavailable)?
+        .ok_or_else(|| match req.block {
+            Some(tag) => OracleError::BadInput(format!("No block {} on {}", tag, chain_name)),
+            // the endpoint is behind the node that saw the trigger
+            None => OracleError::UpstreamUnavailable(format!("No response from {}", chain_name)),
+        })?;
+    let at = BlockId::number(block.header.number);
+
+    let token = Token { provider: &provider, chain_name, address: req.token, at };
+    let total_supply = token.call(solidity::totalSupplyCall {}).await?._0;
+    let balances = join_all(
+        req.holders.iter().map(|holder| token.call(solidity::balanceOfCall { account: *holder })),
+    )
+    .await
+    .into_iter()
+    .map(|balance| balance.map(|balance| balance._0))
+    .collect::<Result<Vec<_>, _>>()?;
+
+    let held_total = balances
+        .iter()
+        .try_fold(U256::ZERO, |total, balance| total.checked_add(*balance))
+        .ok_or_else(|| OracleError::OutOfRange("Balances overflow uint256".to_string()))?;
+
+    Ok(TokenSnapshot {
+        token: req.token,
+        blockNumber: block.header.number,
+        blockHash: block.header.hash,
+        totalSupply: total_supply,
+        holders: req.holders.clone(),
+        balances,
+        heldTotal: held_total,
+    })
+}
+
+/// The block the request names, or else the trigger's one, which every operator reads the same
+pub fn block_id(
+    chain_name: &str,
+    req: &SnapshotRequest,
+    event: &TriggerEvent,
+) -> Result<BlockId, OracleError> {
+    Ok(match req.block {
+        Some(BlockTag::Latest) => BlockNumberOrTag::Latest.into(),
+        Some(BlockTag::Safe) => BlockNumberOrTag::Safe.into(),
+        Some(BlockTag::Finalized) => BlockNumberOrTag::Finalized.into(),
+        Some(BlockTag::Number(number)) => BlockNumberOrTag::Number(number).into(),
+        None => event.read_block(chain_name)?,
+    })
+}
+
+struct Token<'a> {
+    provider: &'a RootProvider<Ethereum>,
+    chain_name: &'a str,
+    address: Address,
+    at: BlockId,
+}
+
+impl Token<'_> {
+    /// A revert or a return that doesn't decode means the address isn't an ERC-20 token
+    /// (at that block), anything else that the endpoint couldn't be reached
+    async fn call<C: SolCall>(&self, call: C) -> Result<C::Return, OracleError> {
+        call_at(self.provider, self.address, call, self.at).await.map_err(|e| {
+            warn!("{} on {} failed: {}", C::SIGNATURE, self.address, e);
+            match e.downcast_ref::<TransportError>() {
+                Some(e) if !e.is_error_resp() => OracleError::UpstreamUnavailable(format!(
+                    "No response from {}",
+                    self.chain_name
+                )),
+                _ => OracleError::BadInput(format!(
+                    "{} doesn't answer {} on {}",
+                    self.address,
+                    C::SIGNATURE,
+                    self.chain_name
+                )),
+            }
+        })
+    }
+}
+
+mod solidity {
+    use alloy_sol_macro::sol;
+
+    sol! {
+        function totalSupply() external view returns (uint256);
+        function balanceOf(address account) external view returns (uint256);
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{assets::AssetInfo, config::env_var, sources::SourceQuote};
+use shared::{
+    error::OracleError,
+    price_feed::{CircuitBreakerCheck, MarketData, Ohlc, ReferenceCheck},
+    request::PriceRequestOptions,
+};
+
+/// Controls how quotes from several sources are combined.
+/// Read from the service config `kv`, falling back to the defaults.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AggregationConfig {
+    /// Quotes further than this from the median (in basis points) are dropped
+    pub max_deviation_bps: u32,
+    /// Minimum number of quotes that must survive outlier rejection
+    pub min_sources: usize,
+    /// Quotes older than this many seconds are dropped, 0 keeps them all
+    pub max_quote_age: u64,
+}
+
+impl Default for AggregationConfig {
+    fn default() -> Self {
+        Self { max_deviation_bps: 200, min_sources: 1, max_quote_age: 600 }
+    }
+}
+
+impl AggregationConfig {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            max_deviation_bps: env_var("max_deviation_bps").unwrap_or(default.max_deviation_bps),
+            min_sources: env_var("min_sources").unwrap_or(default.min_sources),
+            max_quote_age: env_var("max_quote_age").unwrap_or(default.max_quote_age),
+        }
+    }
+
+    pub fn with_overrides(self, options: &PriceRequestOptions) -> Self {
+        Self {
+            max_deviation_bps: options.max_deviation_bps.unwrap_or(self.max_deviation_bps),
+            min_sources: options.min_sources.unwrap_or(self.min_sources),
+            max_quote_age: self.max_quote_age,
+        }
+    }
+}
+
+/// The price published for one asset, before it's encoded
+#[derive(Debug, Clone, PartialEq)]
+pub struct AggregatedPrice {
+    pub asset_id: u64,
+    pub symbol: String,
+    pub price: f64,
+    /// unix seconds
+    pub timestamp: u64,
+    pub sources: Vec<String>,
+    /// Only for OHLC requests
+    pub ohlc: Option<Ohlc>,
+    /// Only for TWAP and OHLC requests: the first point in the window of any kept source
+    pub started_at: Option<u64>,
+    /// Everything the kept sources reported, the request's selection is made when encoding
+    pub market: MarketData,
+    /// Set after aggregation, see [`crate::reference`]
+    pub reference: Option<ReferenceCheck>,
+    /// Set after quantization, see [`crate::circuit_breaker`]
+    pub circuit_breaker: Option<CircuitBreakerCheck>,
+}
+
+/// Takes the median of all fresh quotes, drops the ones that deviate too far from it,
+/// and publishes the median of the survivors along with their source names.
+/// For OHLC quotes the deviation is judged on the close, and each of the four is a median.
+/// Quote ages are measured from `now`, the trigger's block time, so every operator drops the
+/// same quotes however late it runs.
+pub fn aggregate(
+    asset: &AssetInfo,
+    mut quotes: Vec<SourceQuote>,
+    config: &AggregationConfig,
+    now: u64,
+) -> Result<AggregatedPrice, OracleError> {
+    let min_sources = config.min_sources.max(1);
+    if quotes.is_empty() {
+        return Err(OracleError::UpstreamUnavailable(
+            "No price source returned a quote".to_string(),
+        ));
+    }
+
+    quotes.retain(|q| q.price.is_finite() && q.price > 0.0);
+    if quotes.is_empty() {
+        return Err(OracleError::OutOfRange(
+            "No price source returned a positive price".to_string(),
+        ));
+    }
+
+    if config.max_quote_age > 0 {
+        quotes.retain(|q| now.saturating_sub(q.timestamp) <= config.max_quote_age);
+        if quotes.is_empty() {
+            return Err(OracleError::StaleData(format!(
+                "Every quote is older than {} seconds",
+                config.max_quote_age
+            )));
+        }
+    }
+
+    if quotes.len() < min_sources {
+        return Err(OracleError::UpstreamUnavailable(format!(
+            "Only {} source(s) returned a usable quote, need {}",
+            quotes.len(),
+            min_sources
+        )));
+    }
+
+    let first_median = median(quotes.iter().map(|q| q.price).collect());
+    let max_deviation = first_median * config.max_deviation_bps as f64 / 10_000.0;
+    quotes.retain(|q| (q.price - first_median).abs() <= max_deviation);
+
+    if quotes.len() < min_sources {
+        return Err(OracleError::OutOfRange(format!(
+            "Only {} source(s) within {} bps of the median, need {}",
+            quotes.len(),
+            config.max_deviation_bps,
+            min_sources
+        )));
+    }
+
+    quotes.sort_by(|a, b| a.source.cmp(b.source));
+
+    Ok(AggregatedPrice {
+        asset_id: asset.cmc_id,
+        symbol: match asset.symbol.as_ref() {
+            "" => quotes[0].symbol.clone(),
+            symbol => symbol.to_string(),
+        },
+        timestamp: quotes.iter().map(|q| q.timestamp).max().unwrap_or_default(),
+        price: median(quotes.iter().map(|q| q.price).collect()),
+        sources: quotes.iter().map(|q| q.source.to_string()).collect(),
+        ohlc: median_ohlc(quotes.iter().map(|q| q.ohlc).collect()),
+        started_at: quotes.iter().filter_map(|q| q.started_at).min(),
+        market: median_market(&quotes),
+        reference: None,
+        circuit_breaker: None,
+    })
+}
+
+/// Each field is the median of the quotes that have it
+fn median_market(quotes: &[SourceQuote]) -> MarketData {
+    let field = |get: fn(&MarketData) -> Option<f64>| {
+        let values: Vec<f64> =
+            quotes.iter().filter_map(|q| get(&q.market)).filter(|v| v.is_finite()).collect();
+        (!values.is_empty()).then(|| median(values))
+    };
+    MarketData {
+        market_cap: field(|market| market.market_cap),
+        volume_24h: field(|market| market.volume_24h),
+        percent_change_24h: field(|market| market.percent_change_24h),
+        circulating_supply: field(|market| market.circulating_supply),
+        total_supply: field(|market| market.total_supply),
+        rank: field(|market| market.rank.map(f64::from)).map(|rank| rank.round() as u32),
+    }
+}
+
+/// `None` unless every quote has an OHLC
+fn median_ohlc(ohlcs: Option<Vec<Ohlc>>) -> Option<Ohlc> {
+    let ohlcs = ohlcs.filter(|ohlcs| !ohlcs.is_empty())?;
+    let field = |get: fn(&Ohlc) -> f64| median(ohlcs.iter().map(get).collect());
+    Some(Ohlc {
+        open: field(|ohlc| ohlc.open),
+        high: field(|ohlc| ohlc.high),
+        low: field(|ohlc| ohlc.low),
+        close: field(|ohlc| ohlc.close),
+    })
+}
+
+fn median(mut values: Vec<f64>) -> f64 {
+    values.sort_by(f64::total_cmp);
+    let mid = values.len() / 2;
+    if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    }
+}
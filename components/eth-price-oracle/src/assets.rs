@@ -0,0 +1,102 @@
+use serde::Deserialize;
+use shared::request::Asset;
+use std::{borrow::Cow, sync::LazyLock};
+
+/// How a CoinMarketCap asset is identified on the other upstreams.
+/// A `None` means the asset is not listed there and that source is skipped.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct AssetInfo {
+    pub cmc_id: u64,
+    pub symbol: Cow<'static, str>,
+    #[serde(default)]
+    pub coingecko_id: Option<Cow<'static, str>>,
+    #[serde(default)]
+    pub binance_base: Option<Cow<'static, str>>,
+    #[serde(default)]
+    pub kraken_base: Option<Cow<'static, str>>,
+}
+
+const fn asset(
+    cmc_id: u64,
+    symbol: &'static str,
+    coingecko_id: &'static str,
+    binance_base: Option<&'static str>,
+    kraken_base: Option<&'static str>,
+) -> AssetInfo {
+    const fn borrowed(s: Option<&'static str>) -> Option<Cow<'static, str>> {
+        match s {
+            Some(s) => Some(Cow::Borrowed(s)),
+            None => None,
+        }
+    }
+
+    AssetInfo {
+        cmc_id,
+        symbol: Cow::Borrowed(symbol),
+        coingecko_id: Some(Cow::Borrowed(coingecko_id)),
+        binance_base: borrowed(binance_base),
+        kraken_base: borrowed(kraken_base),
+    }
+}
+
+pub const KNOWN_ASSETS: &[AssetInfo] = &[
+    asset(1, "BTC", "bitcoin", Some("BTC"), Some("XBT")),
+    asset(1027, "ETH", "ethereum", Some("ETH"), Some("ETH")),
+    asset(825, "USDT", "tether", None, Some("USDT")),
+    asset(1839, "BNB", "binancecoin", Some("BNB"), None),
+    asset(5426, "SOL", "solana", Some("SOL"), Some("SOL")),
+    asset(52, "XRP", "ripple", Some("XRP"), Some("XRP")),
+    asset(3408, "USDC", "usd-coin", Some("USDC"), Some("USDC")),
+    asset(74, "DOGE", "dogecoin", Some("DOGE"), Some("XDG")),
+    asset(2010, "ADA", "cardano", Some("ADA"), Some("ADA")),
+    asset(3794, "ATOM", "cosmos", Some("ATOM"), Some("ATOM")),
+];
+
+/// [`KNOWN_ASSETS`] plus the service config `assets` entry, a JSON array of [`AssetInfo`].
+/// Configured assets take precedence, so they can also fix a mapping without recompiling.
+/// An invalid entry fails every lookup, rather than pricing with the built-in mappings only.
+static ASSETS: LazyLock<Result<Vec<AssetInfo>, String>> = LazyLock::new(|| {
+    let mut assets: Vec<AssetInfo> = match std::env::var("assets") {
+        Ok(json) => serde_json::from_str(&json)
+            .map_err(|e| format!("Invalid `assets` service config: {}", e))?,
+        Err(_) => Vec::new(),
+    };
+
+    assets.extend(KNOWN_ASSETS.iter().cloned());
+    Ok(assets)
+});
+
+/// An asset priced in a quote currency, e.g. ETH/USD
+#[derive(Debug, Clone, PartialEq)]
+pub struct PricePair {
+    pub base: AssetInfo,
+    /// Uppercase currency code, e.g. `USD`
+    pub quote: String,
+}
+
+impl AssetInfo {
+    pub fn resolve(asset: &Asset) -> Result<Self, String> {
+        let assets = ASSETS.as_deref().map_err(Clone::clone)?;
+        match asset {
+            Asset::Id(id) => Ok(Self::by_cmc_id(assets, *id)),
+            Asset::Symbol(symbol) => Self::by_symbol(assets, symbol)
+                .ok_or_else(|| format!("Unknown asset symbol: {}", symbol)),
+        }
+    }
+
+    /// Only known or configured assets can be looked up by symbol
+    fn by_symbol(assets: &[AssetInfo], symbol: &str) -> Option<Self> {
+        assets.iter().find(|asset| asset.symbol.eq_ignore_ascii_case(symbol)).cloned()
+    }
+
+    /// Looks up a CoinMarketCap ID. Unknown IDs can still be priced, but only by CoinMarketCap.
+    fn by_cmc_id(assets: &[AssetInfo], cmc_id: u64) -> Self {
+        assets.iter().find(|asset| asset.cmc_id == cmc_id).cloned().unwrap_or(Self {
+            cmc_id,
+            symbol: Cow::Borrowed(""),
+            coingecko_id: None,
+            binance_base: None,
+            kraken_base: None,
+        })
+    }
+}
@@ -0,0 +1,161 @@
+//! Optional circuit breaker against the prices already published to the submit contract.
+//! A price that jumps too far from the latest one for the same asset is refused or flagged,
+//! so one corrupt upstream tick can't trip liquidations downstream.
+
+use crate::{
+    aggregate::AggregatedPrice,
+    chain::{self, call_at, CheckAction},
+    config::env_var,
+    log::warn,
+};
+use alloy_primitives::Address;
+use alloy_rpc_types_eth::BlockNumberOrTag;
+use anyhow::Result;
+use futures::future::join_all;
+use shared::{
+    abi::{asset_key, from_fixed_point, to_fixed_point, AssetPrice, ASSET_PRICE_DECIMALS},
+    error::OracleError,
+    price_feed::CircuitBreakerCheck,
+    request::PriceMode,
+};
+
+/// Read from the service config `kv`. Off unless the contract is set.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CircuitBreaker {
+    /// The `SimpleSubmit` results are published to, on the chain the triggers come from
+    pub contract: Address,
+    pub max_change_percent: f64,
+    pub action: CheckAction,
+}
+
+impl CircuitBreaker {
+    pub fn from_env() -> Option<Self> {
+        Some(Self {
+            contract: env_var("circuit_breaker_contract")?,
+            max_change_percent: env_var("circuit_breaker_max_change_percent").unwrap_or(10.0),
+            action: env_var("circuit_breaker_action").unwrap_or(CheckAction::Refuse),
+        })
+    }
+
+    /// Compares each price with the latest one `SimpleSubmit` holds for the same asset, quote
+    /// and mode, as of the trigger's block so that every operator compares with the same one.
+    /// Assets without one (e.g. the first result) pass unchecked.
+    pub async fn apply(
+        &self,
+        chain: &str,
+        block: u64,
+        quote: &str,
+        mode: PriceMode,
+        prices: &mut [AggregatedPrice],
+    ) -> Result<(), OracleError> {
+        let latest = match self.latest_prices(chain, block, quote, mode, prices).await {
+            Ok(latest) => latest,
+            Err(e) => {
+                warn!("couldn't read the latest prices: {}", e);
+                // raw RPC errors differ between operators, so they stay out of the message
+                return match self.action {
+                    CheckAction::Refuse => Err(OracleError::UpstreamUnavailable(
+                        "No response from the submit contract".to_string(),
+                    )),
+                    CheckAction::Flag => Ok(()),
+                };
+            }
+        };
+
+        for (price, last) in prices.iter_mut().zip(latest) {
+            let Some(last) = last else {
+                continue;
+            };
+
+            let change_percent =
+                ((price.price - last.price) / last.price * 10_000.0).round() / 100.0;
+            let flagged = change_percent.abs() > self.max_change_percent;
+            if flagged && self.action == CheckAction::Refuse {
+                return Err(OracleError::OutOfRange(format!(
+                    "{}: {}% from the result for trigger {}, max {}%",
+                    price.symbol, change_percent, last.trigger_id, self.max_change_percent
+                )));
+            }
+
+            price.circuit_breaker = Some(CircuitBreakerCheck {
+                trigger_id: last.trigger_id,
+                price: last.price,
+                change_percent,
+                flagged,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// One `getLatestPrice` call per asset, `None` for an asset without a price yet
+    async fn latest_prices(
+        &self,
+        chain: &str,
+        block: u64,
+        quote: &str,
+        mode: PriceMode,
+        prices: &[AggregatedPrice],
+    ) -> Result<Vec<Option<LatestPrice>>> {
+        let provider = chain::provider(chain)?;
+
+        let results = join_all(prices.iter().map(|price| {
+            let call = solidity::getLatestPriceCall { key: asset_key(price.asset_id, quote, mode) };
+            call_at(&provider, self.contract, call, BlockNumberOrTag::Number(block).into())
+        }))
+        .await;
+
+        results
+            .into_iter()
+            .map(|res| {
+                let latest = res?;
+                Ok((latest.triggerId != 0 && !latest.price.is_zero()).then(|| LatestPrice {
+                    trigger_id: latest.triggerId,
+                    price: from_fixed_point(latest.price, ASSET_PRICE_DECIMALS),
+                }))
+            })
+            .collect()
+    }
+}
+
+/// An asset's price in the newest result published for it
+#[derive(Debug, Clone, PartialEq)]
+struct LatestPrice {
+    trigger_id: u64,
+    price: f64,
+}
+
+/// What `SimpleSubmit` keeps as the assets' latest prices once the result is published.
+/// A price the circuit breaker or the reference check flagged is left out, so the next result
+/// is still checked against the last one that wasn't.
+pub fn published_prices(
+    prices: &[AggregatedPrice],
+    quote: &str,
+    mode: PriceMode,
+) -> Result<Vec<AssetPrice>, OracleError> {
+    prices
+        .iter()
+        .filter(|price| {
+            !price.circuit_breaker.as_ref().is_some_and(|check| check.flagged)
+                && !price.reference.as_ref().is_some_and(|check| check.flagged)
+        })
+        .map(|price| {
+            let fixed = to_fixed_point(price.price, ASSET_PRICE_DECIMALS).ok_or_else(|| {
+                OracleError::OutOfRange(format!(
+                    "{}: {} doesn't fit in a fixed-point price with {} decimals",
+                    price.symbol, price.price, ASSET_PRICE_DECIMALS
+                ))
+            })?;
+            Ok(AssetPrice { assetKey: asset_key(price.asset_id, quote, mode), price: fixed })
+        })
+        .collect()
+}
+
+mod solidity {
+    use alloy_sol_macro::sol;
+
+    sol! {
+        // SimpleSubmit.getLatestPrice, with the static ITypes.LatestPrice returned as its fields
+        function getLatestPrice(bytes32 key) external view returns (uint64 triggerId, uint256 price);
+    }
+}
@@ -1,124 +1,234 @@
-mod trigger;
-use shared::price_feed::PriceFeedData;
-use trigger::{decode_trigger_event, encode_trigger_output, Destination};
-use wavs_wasi_chain::http::{fetch_json, http_request_get};
-pub mod bindings;
-use crate::bindings::{export, Guest, TriggerAction};
-use serde::{Deserialize, Serialize};
-use wstd::{http::HeaderValue, runtime::block_on};
+mod aggregate;
+mod assets;
+mod circuit_breaker;
+mod output;
+mod quantize;
+mod range;
+mod reference;
+mod routes;
+mod sources;
+use aggregate::{aggregate, AggregatedPrice, AggregationConfig};
+use assets::{AssetInfo, PricePair};
+use circuit_breaker::{published_prices, CircuitBreaker};
+use component_support::{
+    bindings::{export, Guest, TriggerAction},
+    chain, config, fetch,
+    fetch::{FetchError, RetryPolicy},
+    log,
+    log::{debug, info, warn},
+    time, trigger,
+    trigger::{TriggerAnchor, TriggerEvent},
+    Oracle, Output,
+};
+use futures::future::join_all;
+use quantize::QuantizeConfig;
+use range::Query;
+use reference::ReferenceConfig;
+use routes::select_route;
+use shared::{
+    error::OracleError,
+    request::{Asset, PriceRequest},
+};
+use sources::{PriceSource, SourceQuote};
 
 struct Component;
-export!(Component with_types_in bindings);
+export!(Component with_types_in component_support::bindings);
 
 impl Guest for Component {
     fn run(action: TriggerAction) -> std::result::Result<Vec<u8>, String> {
-        let (trigger_id, req, dest) =
-            decode_trigger_event(action.data).map_err(|e| e.to_string())?;
-
-        // Convert bytes to string and parse first char as u64
-        let input = std::str::from_utf8(&req).map_err(|e| e.to_string())?;
-        println!("input id: {}", input);
-
-        let id = input.chars().next().ok_or("Empty input")?;
-        let id = id.to_digit(16).ok_or("Invalid hex digit")? as u64;
-
-        let res = block_on(async move {
-            let resp_data = get_price_feed(id).await;
-            println!("resp_data: {:?}", resp_data);
-
-            match resp_data {
-                Ok(resp) => {
-                    let output: Vec<u8> = serde_json::to_vec(&resp).unwrap();
-                    Ok(output)
-                }
-                Err(e) => Err(e),
-            }
-        });
-
-        match res {
-            Ok(data) => match dest {
-                Destination::Ethereum => Ok(encode_trigger_output(trigger_id, &data)),
-                Destination::CliOutput => Ok(data),
-            },
-            Err(e) => Err(e),
-        }
+        component_support::run::<Component>(action)
     }
 }
 
-async fn get_price_feed(id: u64) -> Result<PriceFeedData, String> {
-    let url = format!(
-        "https://api.coinmarketcap.com/data-api/v3/cryptocurrency/detail?id={}&range=1h",
-        id
-    );
-
-    let current_time = std::time::SystemTime::now().elapsed().unwrap().as_secs();
-
-    let mut req = http_request_get(&url).map_err(|e| e.to_string())?;
-    req.headers_mut().insert("Accept", HeaderValue::from_static("application/json"));
-    req.headers_mut().insert("Content-Type", HeaderValue::from_static("application/json"));
-    req.headers_mut()
-        .insert("User-Agent", HeaderValue::from_static("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/132.0.0.0 Safari/537.36"));
-    req.headers_mut().insert(
-        "Cookie",
-        HeaderValue::from_str(&format!("myrandom_cookie={}", current_time)).unwrap(),
-    );
-
-    let json: Root = fetch_json(req).await.map_err(|e| e.to_string())?;
-
-    Ok(PriceFeedData {
-        symbol: json.data.symbol,
-        price: json.data.statistics.price,
-        timestamp: json.status.timestamp,
-    })
+impl Oracle for Component {
+    async fn get_output(event: &TriggerEvent) -> Result<Output, OracleError> {
+        let anchor = event.anchor().await;
+        get_output(event, &anchor).await
+    }
 }
 
+async fn get_output(event: &TriggerEvent, anchor: &TriggerAnchor) -> Result<Output, OracleError> {
+    let req: PriceRequest = event.request.decode()?;
+    let route = select_route(&req.kind())?;
+    let req = route.prepare(req)?;
+    info!("{} request: {:?}", route.kind(), req);
+
+    let mut prices = get_price_feeds(event, &req).await?;
+
+    // the latest prices are read at the trigger's block, which only NewTrigger logs have
+    if let (Some(breaker), Some((chain, block)), true) =
+        (CircuitBreaker::from_env(), event.log_block(), route.publishes_prices())
+    {
+        breaker.apply(chain, block, &req.quote, req.options.mode, &mut prices).await?;
+    }
+    debug!("prices: {:?}", prices);
 
-/// -----
-/// https://transform.tools/json-to-rust-serde
-/// Generated from https://api.coinmarketcap.com/data-api/v3/cryptocurrency/detail?id=1&range=1h
-/// -----
-///
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Root {
-    pub data: Data,
-    pub status: Status,
+    let published = match route.publishes_prices() {
+        true => published_prices(&prices, &req.quote, req.options.mode)?,
+        false => Vec::new(),
+    };
+    Ok(route.encode(prices, &req, event.trigger_id, anchor)?.with_prices(published))
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Data {
-    pub id: f64,
-    pub name: String,
-    pub symbol: String,
-    pub statistics: Statistics,
-    pub description: String,
-    pub category: String,
-    pub slug: String,
+async fn get_price_feeds(
+    event: &TriggerEvent,
+    req: &PriceRequest,
+) -> Result<Vec<AggregatedPrice>, OracleError> {
+    let source_names = if req.options.sources.is_empty() {
+        sources::configured_source_names("sources")
+    } else {
+        req.options.sources.clone()
+    };
+    let sources =
+        sources::select_sources(&source_names).map_err(|e| OracleError::BadInput(e.to_string()))?;
+
+    // fallbacks already among the primary sources would only be asked twice
+    let fallback_names: Vec<String> = sources::configured_source_names("fallback_sources")
+        .into_iter()
+        .filter(|name| !sources.iter().any(|source| source.name() == name))
+        .collect();
+    let fallback = match fallback_names.is_empty() {
+        true => Vec::new(),
+        false => sources::select_sources(&fallback_names)
+            .map_err(|e| OracleError::BadInput(e.to_string()))?,
+    };
+    let sources = SourceSet { primary: sources, fallback };
+
+    let config = AggregationConfig::from_env().with_overrides(&req.options);
+    let quantize = QuantizeConfig::from_env().with_overrides(&req.options);
+    let policy = RetryPolicy::from_env();
+    let reference = ReferenceConfig::from_env()?;
+    // ranges end and quotes age at the trigger's block time, unless neither applies
+    let block_time = match req.options.mode.is_spot() && config.max_quote_age == 0 {
+        true => 0,
+        false => event.block_time().await?,
+    };
+    let query = Query::from_options(&req.options, block_time);
+
+    let mut prices = join_all(req.assets.as_slice().iter().map(|asset| {
+        get_price_feed(asset, &req.quote, &sources, &query, &config, &policy, block_time)
+    }))
+    .await
+    .into_iter()
+    .collect::<Result<Vec<_>, _>>()?;
+
+    prices.iter_mut().for_each(|price| quantize.apply(price));
+
+    // the reference bounds the price that's published, not the one before quantization
+    let checks =
+        join_all(prices.iter().map(|price| reference::check(price, &req.quote, &reference, event)))
+            .await;
+    for ((price, check), asset) in prices.iter_mut().zip(checks).zip(req.assets.as_slice()) {
+        price.reference =
+            check.map_err(|e| e.context(asset))?.map(|check| quantize.apply_reference(check));
+    }
+
+    Ok(prices)
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Statistics {
-    pub price: f64,
-    #[serde(rename = "totalSupply")]
-    pub total_supply: f64,
+/// The fallback sources are only queried for an asset when the primary ones return
+/// fewer than `min_sources` quotes
+struct SourceSet {
+    primary: Vec<Box<dyn PriceSource>>,
+    fallback: Vec<Box<dyn PriceSource>>,
+}
+
+async fn get_price_feed(
+    asset: &Asset,
+    quote: &str,
+    sources: &SourceSet,
+    query: &Query,
+    config: &AggregationConfig,
+    policy: &RetryPolicy,
+    block_time: u64,
+) -> Result<AggregatedPrice, OracleError> {
+    let pair = PricePair {
+        base: AssetInfo::resolve(asset).map_err(OracleError::BadInput)?,
+        quote: quote.to_string(),
+    };
+
+    let mut failures = Failures::default();
+    let mut quotes = failures
+        .split_results(asset, sources::fetch_quotes(&sources.primary, &pair, query, policy).await);
+
+    if quotes.len() < config.min_sources.max(1) && !sources.fallback.is_empty() {
+        info!("only {} quote(s) for {}, trying fallback sources", quotes.len(), asset);
+        quotes.extend(failures.split_results(
+            asset,
+            sources::fetch_quotes(&sources.fallback, &pair, query, policy).await,
+        ));
+    }
+
+    if quotes.is_empty() {
+        return Err(failures.into_error(&pair).context(asset));
+    }
+
+    aggregate(&pair.base, quotes, config, block_time).map_err(|e| e.context(asset))
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct CoinBitesVideo {
-    pub id: String,
-    pub category: String,
-    #[serde(rename = "videoUrl")]
-    pub video_url: String,
-    pub title: String,
-    pub description: String,
-    #[serde(rename = "previewImage")]
-    pub preview_image: String,
+/// Names of the sources that failed for an asset, by kind of failure
+#[derive(Default)]
+struct Failures {
+    unavailable: Vec<&'static str>,
+    /// Reported separately since they usually call for a config change
+    /// (an API key, fewer triggers) rather than a retry
+    rate_limited: Vec<&'static str>,
+    unparseable: Vec<&'static str>,
 }
 
-#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
-pub struct Status {
-    pub timestamp: String,
-    pub error_code: String,
-    pub error_message: String,
-    pub elapsed: String,
-    pub credit_count: f64,
+impl Failures {
+    /// Logs and records failed sources, returning the quotes of the others
+    fn split_results(
+        &mut self,
+        asset: &Asset,
+        results: Vec<(&'static str, anyhow::Result<SourceQuote>)>,
+    ) -> Vec<SourceQuote> {
+        let mut quotes = Vec::new();
+
+        for (source, res) in results {
+            match res {
+                Ok(quote) => quotes.push(quote),
+                Err(e) => match e.downcast_ref::<FetchError>() {
+                    Some(FetchError::RateLimited { .. }) => {
+                        warn!("{} rate limited for {}: {}", source, asset, e);
+                        self.rate_limited.push(source);
+                    }
+                    Some(_) => {
+                        warn!("{} unavailable for {}: {}", source, asset, e);
+                        self.unavailable.push(source);
+                    }
+                    None => {
+                        warn!("{} returned an unusable response for {}: {}", source, asset, e);
+                        self.unparseable.push(source);
+                    }
+                },
+            }
+        }
+
+        quotes
+    }
+
+    /// Why no quote at all could be had. Only source names go into the message,
+    /// since raw upstream errors differ between operators.
+    fn into_error(self, pair: &PricePair) -> OracleError {
+        if !self.rate_limited.is_empty() {
+            let mut message = format!("Rate limited by {}", self.rate_limited.join(", "));
+            if !self.unavailable.is_empty() {
+                message += &format!(", no response from {}", self.unavailable.join(", "));
+            }
+            OracleError::UpstreamUnavailable(message)
+        } else if !self.unavailable.is_empty() {
+            OracleError::UpstreamUnavailable(format!(
+                "No response from {}",
+                self.unavailable.join(", ")
+            ))
+        } else if !self.unparseable.is_empty() {
+            OracleError::ParseFailure(format!(
+                "Unusable response from {}",
+                self.unparseable.join(", ")
+            ))
+        } else {
+            OracleError::BadInput(format!("No selected price source lists it in {}", pair.quote))
+        }
+    }
 }
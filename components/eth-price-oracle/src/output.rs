@@ -0,0 +1,137 @@
+use crate::{aggregate::AggregatedPrice, trigger::TriggerAnchor};
+use alloy_primitives::{I256, U256};
+use shared::{
+    abi::{feed_key, round_id, to_fixed_point, PriceFeed, RoundData, DEFAULT_DECIMALS},
+    codec::encode_abi,
+    error::OracleError,
+    price_feed::{AssetMetadata, MetadataOutput, Ohlc, PriceFeedData, PriceFeedOutput},
+    request::{AssetSelection, OutputEncoding, PriceMode, PriceRequestOptions, DEFAULT_WINDOW},
+};
+
+/// Encodes the prices (one per requested asset, in request order) as the request asked for
+pub fn encode_output(
+    prices: Vec<AggregatedPrice>,
+    assets: &AssetSelection,
+    quote: &str,
+    options: &PriceRequestOptions,
+    trigger_id: u64,
+    anchor: &TriggerAnchor,
+) -> Result<Vec<u8>, OracleError> {
+    if let Some(codec) = options.encoding.feed_codec() {
+        let mut feeds = prices.into_iter().map(|price| feed_data(price, options, anchor));
+        let output = match assets {
+            AssetSelection::Single(_) => PriceFeedOutput::Single(Box::new(feeds.next().unwrap())),
+            AssetSelection::Batch(_) => PriceFeedOutput::Batch(feeds.collect()),
+        };
+        // the feeds hold nothing these can't encode, the transaction hash is a B256 already.
+        // A failure would be a result that doesn't fit the encoding, like too large an ABI price.
+        return codec.encode(&output).map_err(|e| OracleError::OutOfRange(e.to_string()));
+    }
+
+    match options.encoding {
+        OutputEncoding::Abi => {
+            let decimals = options.decimals.unwrap_or(DEFAULT_DECIMALS);
+            let feeds = prices
+                .into_iter()
+                .map(|price| {
+                    let fixed_point = |value: f64| fixed_point(&price, value, decimals);
+                    let ohlc =
+                        price.ohlc.unwrap_or(Ohlc { open: 0.0, high: 0.0, low: 0.0, close: 0.0 });
+                    Ok(PriceFeed {
+                        assetId: price.asset_id,
+                        price: fixed_point(price.price)?,
+                        decimals,
+                        timestamp: price.timestamp,
+                        blockHeight: anchor.block_height.unwrap_or_default(),
+                        txHash: anchor.tx_hash.unwrap_or_default(),
+                        mode: options.mode.into(),
+                        window: window(options).unwrap_or_default(),
+                        open: fixed_point(ohlc.open)?,
+                        high: fixed_point(ohlc.high)?,
+                        low: fixed_point(ohlc.low)?,
+                    })
+                })
+                .collect::<Result<Vec<_>, OracleError>>()?;
+            Ok(encode_abi(feeds, assets))
+        }
+        // Chainlink, the only other encoding without a feed codec
+        _ => {
+            let decimals = options.decimals.unwrap_or(DEFAULT_DECIMALS);
+            let round_id = round_id(trigger_id);
+            let rounds = prices
+                .iter()
+                .map(|price| {
+                    Ok(RoundData {
+                        roundId: round_id,
+                        answer: I256::from_raw(fixed_point(price, price.price, decimals)?),
+                        startedAt: U256::from(price.started_at.unwrap_or(price.timestamp)),
+                        updatedAt: U256::from(price.timestamp),
+                        answeredInRound: round_id,
+                        feedKey: feed_key(price.asset_id, quote, options.mode, decimals),
+                    })
+                })
+                .collect::<Result<Vec<_>, OracleError>>()?;
+            Ok(encode_abi(rounds, assets))
+        }
+    }
+}
+
+/// Encodes the market data of the prices as JSON, leaving the prices out
+pub fn encode_metadata(
+    prices: Vec<AggregatedPrice>,
+    assets: &AssetSelection,
+    options: &PriceRequestOptions,
+    anchor: &TriggerAnchor,
+) -> Vec<u8> {
+    let mut metadata = prices.into_iter().map(|price| AssetMetadata {
+        symbol: price.symbol,
+        timestamp: price.timestamp,
+        sources: price.sources,
+        block_height: anchor.block_height,
+        tx_hash: anchor.tx_hash.map(|hash| hash.to_string()),
+        market: price.market.select(&options.fields),
+    });
+    let output = match assets {
+        AssetSelection::Single(_) => MetadataOutput::Single(Box::new(metadata.next().unwrap())),
+        AssetSelection::Batch(_) => MetadataOutput::Batch(metadata.collect()),
+    };
+    serde_json::to_vec(&output).unwrap()
+}
+
+fn fixed_point(price: &AggregatedPrice, value: f64, decimals: u8) -> Result<U256, OracleError> {
+    to_fixed_point(value, decimals).ok_or_else(|| {
+        OracleError::OutOfRange(format!(
+            "{}: {} doesn't fit in a fixed-point price with {} decimals",
+            price.symbol, value, decimals
+        ))
+    })
+}
+
+fn feed_data(
+    price: AggregatedPrice,
+    options: &PriceRequestOptions,
+    anchor: &TriggerAnchor,
+) -> PriceFeedData {
+    PriceFeedData {
+        symbol: price.symbol,
+        timestamp: price.timestamp,
+        price: price.price,
+        sources: price.sources,
+        block_height: anchor.block_height,
+        tx_hash: anchor.tx_hash.map(|hash| hash.to_string()),
+        mode: options.mode,
+        window: window(options),
+        ohlc: price.ohlc,
+        market: price.market.select(&options.fields),
+        reference: price.reference,
+        circuit_breaker: price.circuit_breaker,
+    }
+}
+
+/// Seconds of history behind the price, `None` for spot prices
+fn window(options: &PriceRequestOptions) -> Option<u64> {
+    match options.mode {
+        PriceMode::Spot => None,
+        _ => Some(options.window.unwrap_or(DEFAULT_WINDOW)),
+    }
+}
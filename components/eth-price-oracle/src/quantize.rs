@@ -0,0 +1,93 @@
+//! Operators fetch at slightly different moments, so raw prices and timestamps almost never
+//! match byte-for-byte. Rounding both to a coarser grid lets the signed payloads agree.
+
+use crate::{aggregate::AggregatedPrice, config::env_var};
+use shared::{price_feed::ReferenceCheck, request::PriceRequestOptions};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QuantizeConfig {
+    /// Significant digits kept in the price
+    pub significant_digits: u32,
+    /// Timestamps are rounded down to a multiple of this many seconds
+    pub timestamp_window: u64,
+}
+
+impl Default for QuantizeConfig {
+    fn default() -> Self {
+        Self { significant_digits: 6, timestamp_window: 60 }
+    }
+}
+
+impl QuantizeConfig {
+    pub fn from_env() -> Self {
+        let default = Self::default();
+        Self {
+            significant_digits: env_var("significant_digits").unwrap_or(default.significant_digits),
+            timestamp_window: env_var("timestamp_window").unwrap_or(default.timestamp_window),
+        }
+    }
+
+    pub fn with_overrides(self, options: &PriceRequestOptions) -> Self {
+        Self {
+            significant_digits: options.significant_digits.unwrap_or(self.significant_digits),
+            timestamp_window: options.timestamp_window.unwrap_or(self.timestamp_window),
+        }
+    }
+
+    pub fn apply(&self, price: &mut AggregatedPrice) {
+        price.price = round_significant(price.price, self.significant_digits);
+        if let Some(ohlc) = &mut price.ohlc {
+            for value in [&mut ohlc.open, &mut ohlc.high, &mut ohlc.low, &mut ohlc.close] {
+                *value = round_significant(*value, self.significant_digits);
+            }
+        }
+        let market = &mut price.market;
+        for value in [
+            &mut market.market_cap,
+            &mut market.volume_24h,
+            &mut market.percent_change_24h,
+            &mut market.circulating_supply,
+            &mut market.total_supply,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *value = round_significant(*value, self.significant_digits);
+        }
+        if self.timestamp_window > 0 {
+            price.timestamp -= price.timestamp % self.timestamp_window;
+            if let Some(started_at) = &mut price.started_at {
+                *started_at -= *started_at % self.timestamp_window;
+            }
+        }
+        price.sources.sort();
+    }
+
+    /// The reference is read after the price is quantized, and its price rounded the same way
+    pub fn apply_reference(&self, reference: ReferenceCheck) -> ReferenceCheck {
+        ReferenceCheck {
+            price: round_significant(reference.price, self.significant_digits),
+            ..reference
+        }
+    }
+}
+
+/// Rounds to `digits` significant digits (half away from zero).
+///
+/// Scaling by an exact power of ten and dividing back means the result is the double closest
+/// to the rounded decimal, so it serializes to the same short string on every operator.
+pub fn round_significant(value: f64, digits: u32) -> f64 {
+    if value == 0.0 || !value.is_finite() || digits == 0 {
+        return value;
+    }
+
+    let magnitude = value.abs().log10().floor() as i32;
+    let exp = digits as i32 - 1 - magnitude;
+    if exp >= 0 {
+        let factor = 10f64.powi(exp);
+        (value * factor).round() / factor
+    } else {
+        let factor = 10f64.powi(-exp);
+        (value / factor).round() * factor
+    }
+}
@@ -0,0 +1,145 @@
+//! Time-weighted averages and OHLC over a window of upstream price history.
+//!
+//! Every source is reduced to a single [`SourceQuote`] first, so the usual median and outlier
+//! rejection across sources applies to TWAPs and closes like it does to spot prices.
+
+use crate::sources::SourceQuote;
+use anyhow::{anyhow, Result};
+use shared::{
+    price_feed::{MarketData, Ohlc},
+    request::{PriceMode, PriceRequestOptions, DEFAULT_WINDOW},
+};
+
+/// Sources report history at most by the minute, so the window ends on a minute boundary
+const WINDOW_ALIGNMENT: u64 = 60;
+
+/// The span of history a range query covers, in unix seconds
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Window {
+    pub start: u64,
+    pub end: u64,
+}
+
+impl Window {
+    /// The `length` seconds before the minute of `time`
+    pub fn ending_at(time: u64, length: u64) -> Self {
+        let end = time - time % WINDOW_ALIGNMENT;
+        Self { start: end.saturating_sub(length), end }
+    }
+
+    pub fn length(&self) -> u64 {
+        self.end - self.start
+    }
+
+    /// Candle size that keeps a window under a few hundred candles, in minutes
+    pub fn candle_minutes(&self) -> u64 {
+        match self.length() {
+            0..=21_600 => 1,
+            21_601..=86_400 => 5,
+            _ => 30,
+        }
+    }
+}
+
+/// What to ask every source for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Query {
+    Spot,
+    Range { mode: PriceMode, window: Window },
+}
+
+impl Query {
+    /// The window ends at `time`, the trigger's block time, so every operator and every asset
+    /// of a batch covers the same history
+    pub fn from_options(options: &PriceRequestOptions, time: u64) -> Self {
+        match options.mode {
+            PriceMode::Spot => Query::Spot,
+            mode => Query::Range {
+                mode,
+                window: Window::ending_at(time, options.window.unwrap_or(DEFAULT_WINDOW)),
+            },
+        }
+    }
+}
+
+/// One upstream data point. Sources that only report prices (not candles) set all four
+/// prices to the same value.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Candle {
+    /// When `close` was the price, i.e. the end of the candle, in unix seconds
+    pub timestamp: u64,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+impl Candle {
+    pub fn point(timestamp: u64, price: f64) -> Self {
+        Self { timestamp, open: price, high: price, low: price, close: price }
+    }
+}
+
+/// Reduces a source's candles in `window` to one quote: the TWAP, or the close with the OHLC.
+/// Candles ending after the window are left out, operators that ask later would see more of them.
+pub fn summarize(
+    source: &'static str,
+    symbol: &str,
+    mut candles: Vec<Candle>,
+    mode: PriceMode,
+    window: &Window,
+) -> Result<SourceQuote> {
+    candles.retain(|candle| candle.timestamp <= window.end && candle.close.is_finite());
+    candles.sort_by_key(|candle| candle.timestamp);
+
+    // the price when the window starts, if the source goes back that far
+    let opening = candles.iter().rev().find(|candle| candle.timestamp <= window.start).copied();
+    candles.retain(|candle| candle.timestamp >= window.start);
+
+    // one point can't show how the price moved
+    if candles.len() < 2 {
+        return Err(anyhow!("only {} price point(s) in the window", candles.len()));
+    }
+
+    let last = candles[candles.len() - 1];
+    let (price, ohlc) = match mode {
+        PriceMode::Twap => (twap(opening, &candles, window), None),
+        _ => {
+            let ohlc = Ohlc {
+                open: candles[0].open,
+                high: candles.iter().map(|candle| candle.high).fold(f64::MIN, f64::max),
+                low: candles.iter().map(|candle| candle.low).fold(f64::MAX, f64::min),
+                close: last.close,
+            };
+            (ohlc.close, Some(ohlc))
+        }
+    };
+
+    Ok(SourceQuote {
+        source,
+        symbol: symbol.to_string(),
+        price,
+        timestamp: last.timestamp,
+        ohlc,
+        started_at: Some(candles[0].timestamp),
+        market: MarketData::default(),
+    })
+}
+
+/// Each close holds until the next candle, the last one until the end of the window. Before the
+/// first candle the price is the `opening` close, or else the first candle's open.
+fn twap(opening: Option<Candle>, candles: &[Candle], window: &Window) -> f64 {
+    if window.length() == 0 {
+        return candles[candles.len() - 1].close;
+    }
+
+    let first = candles[0];
+    let before = opening.map_or(first.open, |opening| opening.close);
+    let weighted: f64 = candles
+        .iter()
+        .zip(candles.iter().skip(1).map(|next| next.timestamp).chain([window.end]))
+        .map(|(candle, until)| candle.close * (until - candle.timestamp) as f64)
+        .sum::<f64>()
+        + before * (first.timestamp - window.start) as f64;
+    weighted / window.length() as f64
+}
@@ -0,0 +1,262 @@
+//! Optional check of published prices against an on-chain reference, read with `eth_call`
+//! over the chain's JSON-RPC endpoint at the trigger's block. The median already outvotes a
+//! single bad upstream, the reference bounds how far the published price can move even if
+//! several agree.
+
+use crate::{
+    aggregate::AggregatedPrice,
+    chain::{self, call_at, CheckAction},
+    config::env_var,
+    log::warn,
+    trigger::TriggerEvent,
+};
+use alloy_primitives::Address;
+use alloy_rpc_types_eth::BlockId;
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use shared::{error::OracleError, price_feed::ReferenceCheck};
+
+/// An on-chain price for one pair, configured in the service config `references` entry
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReferenceFeed {
+    pub symbol: String,
+    #[serde(default = "default_quote")]
+    pub quote: String,
+    /// Name of the chain in the WAVS config, which must have an `http_endpoint`
+    pub chain: String,
+    #[serde(flatten)]
+    pub source: ReferenceSource,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ReferenceSource {
+    /// An `AggregatorV3Interface`, read with `latestRoundData`. Without `decimals`, the
+    /// aggregator is asked for them too.
+    Chainlink {
+        address: Address,
+        #[serde(default)]
+        decimals: Option<u8>,
+    },
+    /// The time-weighted average tick of a pool over `twap_seconds`, read with `observe`
+    UniswapV3 {
+        address: Address,
+        /// Whether the asset is the pool's `token0`, the other token being the quote
+        #[serde(default = "default_base_is_token0")]
+        base_is_token0: bool,
+        #[serde(default = "default_twap_seconds")]
+        twap_seconds: u32,
+    },
+}
+
+impl ReferenceSource {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ReferenceSource::Chainlink { .. } => "chainlink",
+            ReferenceSource::UniswapV3 { .. } => "uniswap_v3",
+        }
+    }
+}
+
+fn default_quote() -> String {
+    "USD".to_string()
+}
+
+fn default_base_is_token0() -> bool {
+    true
+}
+
+fn default_twap_seconds() -> u32 {
+    1800
+}
+
+/// Read from the service config `kv`, falling back to the defaults.
+/// Unlike the aggregation settings these can't be overridden per request,
+/// so a requester can't turn the check off.
+#[derive(Debug, Clone)]
+pub struct ReferenceConfig {
+    /// The `references` entry, a JSON array of [`ReferenceFeed`]
+    pub feeds: Vec<ReferenceFeed>,
+    pub max_deviation_bps: u32,
+    /// How many seconds a Chainlink answer may be older than the price, 0 for any age
+    pub max_age: u64,
+    pub action: CheckAction,
+}
+
+impl Default for ReferenceConfig {
+    fn default() -> Self {
+        Self {
+            feeds: Vec::new(),
+            max_deviation_bps: 300,
+            max_age: 3600,
+            action: CheckAction::Refuse,
+        }
+    }
+}
+
+impl ReferenceConfig {
+    /// An invalid `references` entry fails the run, rather than prices going out unchecked
+    pub fn from_env() -> Result<Self, OracleError> {
+        let default = Self::default();
+        let feeds = match std::env::var("references") {
+            Ok(json) => serde_json::from_str(&json).map_err(|e| {
+                OracleError::BadInput(format!("Invalid `references` service config: {}", e))
+            })?,
+            Err(_) => default.feeds,
+        };
+        Ok(Self {
+            feeds,
+            max_deviation_bps: env_var("reference_max_deviation_bps")
+                .unwrap_or(default.max_deviation_bps),
+            max_age: env_var("reference_max_age").unwrap_or(default.max_age),
+            action: env_var("reference_action").unwrap_or(default.action),
+        })
+    }
+}
+
+/// A reference price, and when it was last updated if the reference says
+struct Reading {
+    price: f64,
+    updated_at: Option<u64>,
+}
+
+/// Compares `price`, as it will be published, with the reference configured for it in `quote`,
+/// if there is one. The reference is read at the trigger's block, so it must be on the chain
+/// the trigger came from.
+pub async fn check(
+    price: &AggregatedPrice,
+    quote: &str,
+    config: &ReferenceConfig,
+    event: &TriggerEvent,
+) -> Result<Option<ReferenceCheck>, OracleError> {
+    let Some(feed) = config.feeds.iter().find(|feed| {
+        feed.symbol.eq_ignore_ascii_case(&price.symbol) && feed.quote.eq_ignore_ascii_case(quote)
+    }) else {
+        return Ok(None);
+    };
+    let source = feed.source.name();
+    let block = event.read_block(&feed.chain)?;
+
+    let reference = match read(feed, block).await {
+        Ok(reference) => reference,
+        Err(e) => {
+            warn!("couldn't read the {} reference for {}: {}", source, price.symbol, e);
+            // raw RPC errors differ between operators, so only the source goes into the message
+            return match config.action {
+                CheckAction::Refuse => Err(OracleError::UpstreamUnavailable(format!(
+                    "No response from the {} reference",
+                    source
+                ))),
+                CheckAction::Flag => Ok(None),
+            };
+        }
+    };
+
+    // a stale answer can't vouch for the price, however close it is
+    let age = reference.updated_at.map(|updated_at| price.timestamp.saturating_sub(updated_at));
+    let stale = match age {
+        Some(age) => config.max_age > 0 && age > config.max_age,
+        None => false,
+    };
+    if stale && config.action == CheckAction::Refuse {
+        return Err(OracleError::StaleData(format!(
+            "the {} reference is {}s older than the price, max {}s",
+            source,
+            age.unwrap_or_default(),
+            config.max_age
+        )));
+    }
+
+    let deviation_bps =
+        ((price.price - reference.price).abs() / reference.price * 10_000.0).round() as u32;
+    let flagged = stale || deviation_bps > config.max_deviation_bps;
+    if flagged && config.action == CheckAction::Refuse {
+        return Err(OracleError::OutOfRange(format!(
+            "{} bps from the {} reference, max {}",
+            deviation_bps, source, config.max_deviation_bps
+        )));
+    }
+
+    Ok(Some(ReferenceCheck {
+        source: source.to_string(),
+        price: reference.price,
+        deviation_bps,
+        flagged,
+    }))
+}
+
+async fn read(feed: &ReferenceFeed, block: BlockId) -> Result<Reading> {
+    let provider = chain::provider(&feed.chain)?;
+
+    let reading = match &feed.source {
+        ReferenceSource::Chainlink { address, decimals } => {
+            let round =
+                call_at(&provider, *address, solidity::latestRoundDataCall {}, block).await?;
+            let decimals = match decimals {
+                Some(decimals) => *decimals,
+                None => call_at(&provider, *address, solidity::decimalsCall {}, block).await?._0,
+            };
+            if round.answer.is_negative() {
+                return Err(anyhow!("negative answer"));
+            }
+            Reading {
+                price: f64::from(round.answer.into_raw()) / 10f64.powi(decimals as i32),
+                updated_at: Some(round.updatedAt.saturating_to()),
+            }
+        }
+        ReferenceSource::UniswapV3 { address, base_is_token0, twap_seconds } => {
+            let observed = call_at(
+                &provider,
+                *address,
+                solidity::observeCall { secondsAgos: vec![*twap_seconds, 0] },
+                block,
+            )
+            .await?;
+            let [start, end] = observed.tickCumulatives[..] else {
+                return Err(anyhow!("expected 2 observations"));
+            };
+            let tick = (i64::try_from(end)? - i64::try_from(start)?) as f64 / *twap_seconds as f64;
+
+            let token0 = call_at(&provider, *address, solidity::token0Call {}, block).await?._0;
+            let token1 = call_at(&provider, *address, solidity::token1Call {}, block).await?._0;
+            let decimals0 = call_at(&provider, token0, solidity::decimalsCall {}, block).await?._0;
+            let decimals1 = call_at(&provider, token1, solidity::decimalsCall {}, block).await?._0;
+
+            // token1 per token0, in whole tokens
+            let price = 1.0001f64.powf(tick) * 10f64.powi(decimals0 as i32 - decimals1 as i32);
+            Reading { price: if *base_is_token0 { price } else { 1.0 / price }, updated_at: None }
+        }
+    };
+
+    if !reading.price.is_finite() || reading.price <= 0.0 {
+        return Err(anyhow!("unusable reference price {}", reading.price));
+    }
+    Ok(reading)
+}
+
+mod solidity {
+    use alloy_sol_macro::sol;
+
+    sol! {
+        // Chainlink's AggregatorV3Interface and ERC-20 metadata share `decimals()`
+        function decimals() external view returns (uint8);
+
+        function latestRoundData()
+            external
+            view
+            returns (
+                uint80 roundId,
+                int256 answer,
+                uint256 startedAt,
+                uint256 updatedAt,
+                uint80 answeredInRound
+            );
+
+        function token0() external view returns (address);
+        function token1() external view returns (address);
+        function observe(uint32[] calldata secondsAgos)
+            external
+            view
+            returns (int56[] memory tickCumulatives, uint160[] memory secondsPerLiquidityCumulativeX128s);
+    }
+}
@@ -0,0 +1,161 @@
+//! Every request arrives as the same `NewTrigger` event, so the request's `kind` tag decides what
+//! is asked for. All kinds share [`PriceRequest`], and each checks it and encodes its own output.
+
+use crate::{
+    aggregate::AggregatedPrice,
+    output::{encode_metadata, encode_output},
+    trigger::TriggerAnchor,
+    Output,
+};
+use shared::{
+    abi::ResultEncoding,
+    error::OracleError,
+    request::{AssetSelection, MarketField, OutputEncoding, PriceMode, PriceRequest},
+};
+
+/// A kind of request the oracle answers.
+/// Adding a new kind means implementing this and registering it in [`all_routes`].
+pub trait Route {
+    /// The `kind` tag, lowercase and unique
+    fn kind(&self) -> &'static str;
+
+    /// Checks that the request fits this kind, filling in what the kind implies
+    fn prepare(&self, req: PriceRequest) -> Result<PriceRequest, OracleError> {
+        Ok(req)
+    }
+
+    /// Encodes the prices (one per requested asset, in request order)
+    fn encode(
+        &self,
+        prices: Vec<AggregatedPrice>,
+        req: &PriceRequest,
+        trigger_id: u64,
+        anchor: &TriggerAnchor,
+    ) -> Result<Output, OracleError> {
+        let data = encode_output(prices, &req.assets, &req.quote, &req.options, trigger_id, anchor)?;
+        Ok(Output::new(data, req.options.encoding.result_encoding(&req.assets)))
+    }
+
+    /// Whether the circuit breaker compares the result with earlier ones
+    fn publishes_prices(&self) -> bool {
+        true
+    }
+}
+
+/// Every kind of request, the first answering untagged ones
+pub fn all_routes() -> Vec<Box<dyn Route>> {
+    vec![Box::new(Price), Box::new(Spot), Box::new(Batch), Box::new(Twap), Box::new(Metadata)]
+}
+
+/// The route for a request's kind
+pub fn select_route(kind: &str) -> Result<Box<dyn Route>, OracleError> {
+    all_routes()
+        .into_iter()
+        .find(|route| route.kind() == kind)
+        .ok_or_else(|| OracleError::UnsupportedKind(format!("Unknown request kind {}", kind)))
+}
+
+/// Whatever the options ask for, as before there were kinds
+struct Price;
+
+impl Route for Price {
+    fn kind(&self) -> &'static str {
+        "price"
+    }
+}
+
+/// The latest price of a single asset
+struct Spot;
+
+impl Route for Spot {
+    fn kind(&self) -> &'static str {
+        "spot"
+    }
+
+    fn prepare(&self, req: PriceRequest) -> Result<PriceRequest, OracleError> {
+        if let AssetSelection::Batch(_) = req.assets {
+            return Err(bad_input("spot", "a single `asset`"));
+        }
+        if !req.options.mode.is_spot() {
+            return Err(bad_input("spot", "no `mode`"));
+        }
+        Ok(req)
+    }
+}
+
+/// Prices of several assets, published as a list even for one
+struct Batch;
+
+impl Route for Batch {
+    fn kind(&self) -> &'static str {
+        "batch"
+    }
+
+    fn prepare(&self, mut req: PriceRequest) -> Result<PriceRequest, OracleError> {
+        if let AssetSelection::Single(asset) = req.assets {
+            req.assets = AssetSelection::Batch(vec![asset]);
+        }
+        Ok(req)
+    }
+}
+
+/// Time-weighted average prices over the request's `window`
+struct Twap;
+
+impl Route for Twap {
+    fn kind(&self) -> &'static str {
+        "twap"
+    }
+
+    fn prepare(&self, mut req: PriceRequest) -> Result<PriceRequest, OracleError> {
+        match req.options.mode {
+            PriceMode::Spot | PriceMode::Twap => req.options.mode = PriceMode::Twap,
+            PriceMode::Ohlc => return Err(bad_input("twap", "`mode` twap or none")),
+        }
+        Ok(req)
+    }
+}
+
+/// Market data without the price, all fields unless the request selects some
+struct Metadata;
+
+impl Route for Metadata {
+    fn kind(&self) -> &'static str {
+        "metadata"
+    }
+
+    fn prepare(&self, mut req: PriceRequest) -> Result<PriceRequest, OracleError> {
+        // sources only report market data with the latest price
+        if !req.options.mode.is_spot() {
+            return Err(bad_input("metadata", "no `mode`"));
+        }
+        if req.options.encoding != OutputEncoding::Json {
+            return Err(bad_input("metadata", "the `json` encoding"));
+        }
+        if req.options.fields.is_empty() {
+            req.options.fields = MarketField::ALL.to_vec();
+        }
+        Ok(req)
+    }
+
+    fn encode(
+        &self,
+        prices: Vec<AggregatedPrice>,
+        req: &PriceRequest,
+        _trigger_id: u64,
+        anchor: &TriggerAnchor,
+    ) -> Result<Output, OracleError> {
+        Ok(Output::new(
+            encode_metadata(prices, &req.assets, &req.options, anchor),
+            ResultEncoding::Json,
+        ))
+    }
+
+    fn publishes_prices(&self) -> bool {
+        false
+    }
+}
+
+fn bad_input(kind: &str, expected: &str) -> OracleError {
+    OracleError::BadInput(format!("`{}` requests take {}", kind, expected))
+}
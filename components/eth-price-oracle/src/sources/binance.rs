@@ -0,0 +1,120 @@
+use super::{PriceSource, SourceQuote};
+use crate::{
+    assets::PricePair,
+    config::env_var,
+    range::{Candle, Window},
+};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use shared::price_feed::MarketData;
+use wavs_wasi_chain::http::http_request_get;
+use wstd::{http::Request, io::Empty};
+
+pub struct Binance {
+    pub url: String,
+}
+
+impl Binance {
+    pub fn from_env() -> Self {
+        Self {
+            url: env_var("binance_url").unwrap_or_else(|| "https://api.binance.com".to_string()),
+        }
+    }
+}
+
+impl PriceSource for Binance {
+    fn name(&self) -> &'static str {
+        "binance"
+    }
+
+    fn request(&self, pair: &PricePair) -> Result<Option<Request<Empty>>> {
+        let Some(symbol) = symbol(pair) else {
+            return Ok(None);
+        };
+
+        // the MINI ticker is the cheapest one that says when the last trade happened
+        let url = format!("{}/api/v3/ticker/24hr?symbol={}&type=MINI", self.url, symbol);
+
+        Ok(Some(http_request_get(&url)?))
+    }
+
+    fn parse(&self, pair: &PricePair, body: &[u8]) -> Result<SourceQuote> {
+        let json: BinanceTicker = serde_json::from_slice(body)?;
+
+        Ok(SourceQuote {
+            source: self.name(),
+            symbol: pair.base.symbol.to_string(),
+            price: json.last_price.parse()?,
+            timestamp: json.close_time / 1000,
+            ohlc: None,
+            started_at: None,
+            market: MarketData::default(),
+        })
+    }
+
+    fn range_request(&self, pair: &PricePair, window: &Window) -> Result<Option<Request<Empty>>> {
+        let Some(symbol) = symbol(pair) else {
+            return Ok(None);
+        };
+
+        let url = format!(
+            "{}/api/v3/klines?symbol={}&interval={}m&startTime={}&endTime={}&limit=1000",
+            self.url,
+            symbol,
+            window.candle_minutes(),
+            window.start * 1000,
+            window.end * 1000
+        );
+
+        Ok(Some(http_request_get(&url)?))
+    }
+
+    fn parse_range(&self, _pair: &PricePair, _window: &Window, body: &[u8]) -> Result<Vec<Candle>> {
+        // each kline is [open time, open, high, low, close, volume, close time, ...],
+        // with times in unix milliseconds and prices as strings
+        let klines: Vec<Vec<serde_json::Value>> = serde_json::from_slice(body)?;
+
+        klines
+            .iter()
+            .map(|kline| {
+                let price = |index: usize| -> Result<f64> {
+                    let value = kline.get(index).and_then(|v| v.as_str());
+                    Ok(value.ok_or_else(|| anyhow!("missing kline field {}", index))?.parse()?)
+                };
+                let close_time = kline
+                    .get(6)
+                    .and_then(|v| v.as_u64())
+                    .ok_or_else(|| anyhow!("missing kline close time"))?;
+
+                Ok(Candle {
+                    timestamp: (close_time + 1) / 1000,
+                    open: price(1)?,
+                    high: price(2)?,
+                    low: price(3)?,
+                    close: price(4)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// e.g. `BTCUSDT`, `None` if Binance doesn't list the base asset
+fn symbol(pair: &PricePair) -> Option<String> {
+    let base = pair.base.binance_base.as_ref()?;
+
+    // Binance has no USD books, USDT is the closest proxy and outliers are rejected anyway
+    let quote = match pair.quote.as_str() {
+        "USD" => "USDT",
+        quote => quote,
+    };
+
+    Some(format!("{}{}", base, quote))
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceTicker {
+    last_price: String,
+    /// unix milliseconds of the last trade in the window
+    close_time: u64,
+}
@@ -0,0 +1,109 @@
+use super::{PriceSource, SourceQuote};
+use crate::{
+    assets::PricePair,
+    config::env_var,
+    range::{Candle, Window},
+};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use shared::price_feed::MarketData;
+use std::collections::HashMap;
+use wavs_wasi_chain::http::http_request_get;
+use wstd::{http::Request, io::Empty};
+
+pub struct CoinGecko {
+    pub url: String,
+}
+
+impl CoinGecko {
+    pub fn from_env() -> Self {
+        Self {
+            url: env_var("coingecko_url")
+                .unwrap_or_else(|| "https://api.coingecko.com".to_string()),
+        }
+    }
+}
+
+impl PriceSource for CoinGecko {
+    fn name(&self) -> &'static str {
+        "coingecko"
+    }
+
+    fn request(&self, pair: &PricePair) -> Result<Option<Request<Empty>>> {
+        let Some(id) = &pair.base.coingecko_id else {
+            return Ok(None);
+        };
+
+        let url = format!(
+            "{}/api/v3/simple/price?ids={}&vs_currencies={}&include_last_updated_at=true&include_market_cap=true&include_24hr_vol=true&include_24hr_change=true",
+            self.url,
+            id,
+            pair.quote.to_ascii_lowercase()
+        );
+
+        Ok(Some(http_request_get(&url)?))
+    }
+
+    fn parse(&self, pair: &PricePair, body: &[u8]) -> Result<SourceQuote> {
+        let id = pair.base.coingecko_id.as_deref().unwrap_or_default();
+        let quote = pair.quote.to_ascii_lowercase();
+
+        // e.g. {"ethereum":{"usd":2712.3,"usd_market_cap":327011455337.1,"usd_24h_vol":27182637411.2,
+        //      "usd_24h_change":-1.94,"last_updated_at":1738688136}}
+        let mut json: HashMap<String, HashMap<String, f64>> = serde_json::from_slice(body)?;
+        let prices = json.remove(id).ok_or_else(|| anyhow!("no price for {}", id))?;
+        let price = prices.get(&quote).ok_or_else(|| anyhow!("no {} price for {}", quote, id))?;
+        let timestamp = prices
+            .get("last_updated_at")
+            .ok_or_else(|| anyhow!("no last_updated_at for {}", id))?;
+
+        Ok(SourceQuote {
+            source: self.name(),
+            symbol: pair.base.symbol.to_string(),
+            price: *price,
+            timestamp: *timestamp as u64,
+            ohlc: None,
+            started_at: None,
+            market: MarketData {
+                market_cap: prices.get(&format!("{}_market_cap", quote)).copied(),
+                volume_24h: prices.get(&format!("{}_24h_vol", quote)).copied(),
+                percent_change_24h: prices.get(&format!("{}_24h_change", quote)).copied(),
+                ..Default::default()
+            },
+        })
+    }
+
+    fn range_request(&self, pair: &PricePair, window: &Window) -> Result<Option<Request<Empty>>> {
+        let Some(id) = &pair.base.coingecko_id else {
+            return Ok(None);
+        };
+
+        // 5 minute points for a day, hourly ones beyond that
+        let url = format!(
+            "{}/api/v3/coins/{}/market_chart?vs_currency={}&days={}",
+            self.url,
+            id,
+            pair.quote.to_ascii_lowercase(),
+            window.length().div_ceil(86_400)
+        );
+
+        Ok(Some(http_request_get(&url)?))
+    }
+
+    fn parse_range(&self, _pair: &PricePair, _window: &Window, body: &[u8]) -> Result<Vec<Candle>> {
+        // e.g. {"prices":[[1739197842000,97241.0],...],"market_caps":[...],"total_volumes":[...]}
+        let json: MarketChart = serde_json::from_slice(body)?;
+
+        Ok(json
+            .prices
+            .into_iter()
+            .map(|(timestamp_ms, price)| Candle::point(timestamp_ms as u64 / 1000, price))
+            .collect())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct MarketChart {
+    /// unix milliseconds and price
+    prices: Vec<(f64, f64)>,
+}
@@ -0,0 +1,273 @@
+use super::{PriceSource, SourceQuote};
+use crate::{
+    assets::PricePair,
+    config::{env_var, host_secret},
+    range::{Candle, Window},
+    time,
+};
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use shared::price_feed::MarketData;
+use std::collections::HashMap;
+use wavs_wasi_chain::http::http_request_get;
+use wstd::{
+    http::{HeaderValue, Request},
+    io::Empty,
+};
+
+/// Without an API key this scrapes CoinMarketCap's public (website) data API, which isn't
+/// meant for programmatic use, so the request pretends to be a browser.
+/// With `WAVS_ENV_CMC_API_KEY` in the host environment the authenticated Pro API is used instead.
+pub struct CoinMarketCap {
+    pub url: String,
+    pub pro_url: String,
+    pub api_key: Option<String>,
+}
+
+impl CoinMarketCap {
+    pub fn from_env() -> Self {
+        Self {
+            url: env_var("coinmarketcap_url")
+                .unwrap_or_else(|| "https://api.coinmarketcap.com".to_string()),
+            pro_url: env_var("coinmarketcap_pro_url")
+                .unwrap_or_else(|| "https://pro-api.coinmarketcap.com".to_string()),
+            api_key: host_secret("WAVS_ENV_CMC_API_KEY"),
+        }
+    }
+}
+
+impl PriceSource for CoinMarketCap {
+    fn name(&self) -> &'static str {
+        "coinmarketcap"
+    }
+
+    fn request(&self, pair: &PricePair) -> Result<Option<Request<Empty>>> {
+        if let Some(api_key) = &self.api_key {
+            let url = format!(
+                "{}/v2/cryptocurrency/quotes/latest?id={}&convert={}",
+                self.pro_url, pair.base.cmc_id, pair.quote
+            );
+
+            let mut req = http_request_get(&url)?;
+            req.headers_mut().insert("Accept", HeaderValue::from_static("application/json"));
+            req.headers_mut().insert("X-CMC_PRO_API_KEY", HeaderValue::from_str(api_key)?);
+
+            return Ok(Some(req));
+        }
+
+        // the detail endpoint only reports USD prices
+        if pair.quote != "USD" {
+            return Ok(None);
+        }
+
+        let url = format!(
+            "{}/data-api/v3/cryptocurrency/detail?id={}&range=1h",
+            self.url, pair.base.cmc_id
+        );
+
+        let mut req = http_request_get(&url)?;
+        req.headers_mut().insert("Accept", HeaderValue::from_static("application/json"));
+        req.headers_mut().insert("Content-Type", HeaderValue::from_static("application/json"));
+        req.headers_mut()
+            .insert("User-Agent", HeaderValue::from_static("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/132.0.0.0 Safari/537.36"));
+        req.headers_mut()
+            .insert("Cookie", HeaderValue::from_str(&format!("myrandom_cookie={}", time::now()))?);
+
+        Ok(Some(req))
+    }
+
+    fn parse(&self, pair: &PricePair, body: &[u8]) -> Result<SourceQuote> {
+        if self.api_key.is_some() {
+            return parse_pro(self.name(), pair, body);
+        }
+
+        let json: Root = serde_json::from_slice(body)?;
+        let timestamp = time::parse_rfc3339(&json.status.timestamp)
+            .ok_or_else(|| anyhow!("bad timestamp {:?}", json.status.timestamp))?;
+
+        Ok(SourceQuote {
+            source: self.name(),
+            symbol: json.data.symbol,
+            price: json.data.statistics.price,
+            timestamp,
+            ohlc: None,
+            started_at: None,
+            market: MarketData {
+                market_cap: json.data.statistics.market_cap,
+                volume_24h: json.data.volume,
+                percent_change_24h: json.data.statistics.price_change_percentage_24h,
+                circulating_supply: json.data.statistics.circulating_supply,
+                total_supply: Some(json.data.statistics.total_supply),
+                rank: json.data.statistics.rank,
+            },
+        })
+    }
+
+    /// The website's chart data, there's no API key needed (or accepted) for it
+    fn range_request(&self, pair: &PricePair, window: &Window) -> Result<Option<Request<Empty>>> {
+        if pair.quote != "USD" {
+            return Ok(None);
+        }
+
+        // 5 minute points for a day, hourly ones for a week
+        let range = if window.length() <= 86_400 { "1D" } else { "7D" };
+        let url = format!(
+            "{}/data-api/v3/cryptocurrency/detail/chart?id={}&range={}",
+            self.url, pair.base.cmc_id, range
+        );
+
+        let mut req = http_request_get(&url)?;
+        req.headers_mut().insert("Accept", HeaderValue::from_static("application/json"));
+        req.headers_mut()
+            .insert("User-Agent", HeaderValue::from_static("Mozilla/5.0 (X11; Linux x86_64) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/132.0.0.0 Safari/537.36"));
+
+        Ok(Some(req))
+    }
+
+    fn parse_range(&self, _pair: &PricePair, _window: &Window, body: &[u8]) -> Result<Vec<Candle>> {
+        // e.g. {"data":{"points":{"1739197800":{"v":[97234.12,31875302816.6,1927352190442.3,...]}}}}
+        let json: ChartRoot = serde_json::from_slice(body)?;
+
+        json.data
+            .points
+            .into_iter()
+            .map(|(timestamp, point)| {
+                let price = point.v.first().ok_or_else(|| anyhow!("empty chart point"))?;
+                Ok(Candle::point(timestamp.parse()?, *price))
+            })
+            .collect()
+    }
+}
+
+fn parse_pro(source: &'static str, pair: &PricePair, body: &[u8]) -> Result<SourceQuote> {
+    let mut json: ProRoot = serde_json::from_slice(body)?;
+    if json.status.error_code != 0 {
+        return Err(anyhow!(json.status.error_message.unwrap_or_default()));
+    }
+
+    let mut data = json
+        .data
+        .remove(&pair.base.cmc_id.to_string())
+        .ok_or_else(|| anyhow!("no data for {}", pair.base.cmc_id))?;
+    let quote = data.quote.remove(&pair.quote).ok_or_else(|| anyhow!("no {} quote", pair.quote))?;
+    let timestamp = time::parse_rfc3339(&quote.last_updated)
+        .ok_or_else(|| anyhow!("bad last_updated {:?}", quote.last_updated))?;
+
+    Ok(SourceQuote {
+        source,
+        symbol: data.symbol,
+        price: quote.price,
+        timestamp,
+        ohlc: None,
+        started_at: None,
+        market: MarketData {
+            market_cap: quote.market_cap,
+            volume_24h: quote.volume_24h,
+            percent_change_24h: quote.percent_change_24h,
+            circulating_supply: data.circulating_supply,
+            total_supply: data.total_supply,
+            rank: data.cmc_rank,
+        },
+    })
+}
+
+/// -----
+/// https://transform.tools/json-to-rust-serde
+/// Generated from https://api.coinmarketcap.com/data-api/v3/cryptocurrency/detail?id=1&range=1h
+/// -----
+///
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Root {
+    pub data: Data,
+    pub status: Status,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Data {
+    pub id: f64,
+    pub name: String,
+    pub symbol: String,
+    pub statistics: Statistics,
+    /// 24h volume in USD
+    #[serde(default)]
+    pub volume: Option<f64>,
+    pub description: String,
+    pub category: String,
+    pub slug: String,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Statistics {
+    pub price: f64,
+    #[serde(rename = "totalSupply")]
+    pub total_supply: f64,
+    #[serde(rename = "marketCap", default)]
+    pub market_cap: Option<f64>,
+    #[serde(rename = "priceChangePercentage24h", default)]
+    pub price_change_percentage_24h: Option<f64>,
+    #[serde(rename = "circulatingSupply", default)]
+    pub circulating_supply: Option<f64>,
+    #[serde(default)]
+    pub rank: Option<u32>,
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Status {
+    pub timestamp: String,
+    pub error_code: String,
+    pub error_message: String,
+    pub elapsed: String,
+    pub credit_count: f64,
+}
+
+/// https://coinmarketcap.com/api/documentation/v1/#operation/getV2CryptocurrencyQuotesLatest
+/// (only the fields we use)
+#[derive(Debug, Deserialize)]
+struct ProRoot {
+    status: ProStatus,
+    #[serde(default)]
+    data: HashMap<String, ProData>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProStatus {
+    error_code: i64,
+    error_message: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProData {
+    symbol: String,
+    cmc_rank: Option<u32>,
+    circulating_supply: Option<f64>,
+    total_supply: Option<f64>,
+    quote: HashMap<String, ProQuote>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ProQuote {
+    price: f64,
+    last_updated: String,
+    market_cap: Option<f64>,
+    volume_24h: Option<f64>,
+    percent_change_24h: Option<f64>,
+}
+
+/// https://api.coinmarketcap.com/data-api/v3/cryptocurrency/detail/chart?id=1&range=1D
+/// (only the fields we use)
+#[derive(Debug, Deserialize)]
+struct ChartRoot {
+    data: ChartData,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChartData {
+    /// Keyed by unix seconds
+    points: HashMap<String, ChartPoint>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChartPoint {
+    /// The price in USD, then volume and market cap
+    v: Vec<f64>,
+}
@@ -0,0 +1,139 @@
+use super::{PriceSource, SourceQuote};
+use crate::{
+    assets::PricePair,
+    config::env_var,
+    range::{Candle, Window},
+};
+use anyhow::{anyhow, Result};
+use serde::Deserialize;
+use shared::price_feed::MarketData;
+use std::collections::HashMap;
+use wavs_wasi_chain::http::http_request_get;
+use wstd::{http::Request, io::Empty};
+
+pub struct Kraken {
+    pub url: String,
+}
+
+impl Kraken {
+    pub fn from_env() -> Self {
+        Self { url: env_var("kraken_url").unwrap_or_else(|| "https://api.kraken.com".to_string()) }
+    }
+}
+
+impl PriceSource for Kraken {
+    fn name(&self) -> &'static str {
+        "kraken"
+    }
+
+    fn request(&self, pair: &PricePair) -> Result<Option<Request<Empty>>> {
+        let Some(market) = market(pair) else {
+            return Ok(None);
+        };
+
+        // unlike the ticker, trades carry a timestamp
+        let url = format!("{}/0/public/Trades?pair={}&count=1", self.url, market);
+
+        Ok(Some(http_request_get(&url)?))
+    }
+
+    fn parse(&self, pair: &PricePair, body: &[u8]) -> Result<SourceQuote> {
+        // each trade is [price, volume, time (unix seconds), side, order type, misc, trade id]
+        let trades = serde_json::from_slice::<KrakenResponse>(body)?.rows()?;
+        let last = trades.last().ok_or_else(|| anyhow!("missing last trade"))?;
+        let price =
+            last.first().and_then(|v| v.as_str()).ok_or_else(|| anyhow!("missing price"))?;
+        let time = last.get(2).and_then(|v| v.as_f64()).ok_or_else(|| anyhow!("missing time"))?;
+
+        Ok(SourceQuote {
+            source: self.name(),
+            symbol: pair.base.symbol.to_string(),
+            price: price.parse()?,
+            timestamp: time as u64,
+            ohlc: None,
+            started_at: None,
+            market: MarketData::default(),
+        })
+    }
+
+    fn range_request(&self, pair: &PricePair, window: &Window) -> Result<Option<Request<Empty>>> {
+        let Some(market) = market(pair) else {
+            return Ok(None);
+        };
+
+        let url = format!(
+            "{}/0/public/OHLC?pair={}&interval={}&since={}",
+            self.url,
+            market,
+            window.candle_minutes(),
+            window.start
+        );
+
+        Ok(Some(http_request_get(&url)?))
+    }
+
+    fn parse_range(&self, _pair: &PricePair, window: &Window, body: &[u8]) -> Result<Vec<Candle>> {
+        // each candle is [start time (unix seconds), open, high, low, close, vwap, volume, count],
+        // with prices as strings
+        let candles = serde_json::from_slice::<KrakenResponse>(body)?.rows()?;
+        let interval = window.candle_minutes() * 60;
+
+        candles
+            .iter()
+            .map(|candle| {
+                let price = |index: usize| -> Result<f64> {
+                    let value = candle.get(index).and_then(|v| v.as_str());
+                    Ok(value.ok_or_else(|| anyhow!("missing candle field {}", index))?.parse()?)
+                };
+                let start = candle
+                    .first()
+                    .and_then(|v| v.as_u64())
+                    .ok_or_else(|| anyhow!("missing candle time"))?;
+
+                Ok(Candle {
+                    timestamp: start + interval,
+                    open: price(1)?,
+                    high: price(2)?,
+                    low: price(3)?,
+                    close: price(4)?,
+                })
+            })
+            .collect()
+    }
+}
+
+/// e.g. `XBTUSD`, `None` if Kraken doesn't list the base asset
+fn market(pair: &PricePair) -> Option<String> {
+    let base = pair.base.kraken_base.as_ref()?;
+
+    let quote = match pair.quote.as_str() {
+        "BTC" => "XBT",
+        quote => quote,
+    };
+
+    Some(format!("{}{}", base, quote))
+}
+
+#[derive(Debug, Deserialize)]
+struct KrakenResponse {
+    error: Vec<String>,
+    #[serde(default)]
+    result: HashMap<String, serde_json::Value>,
+}
+
+impl KrakenResponse {
+    /// Trades and candles are keyed by Kraken's internal pair name (e.g. XXBTZUSD),
+    /// next to a `last` cursor
+    fn rows(self) -> Result<Vec<Vec<serde_json::Value>>> {
+        if !self.error.is_empty() {
+            return Err(anyhow!(self.error.join(", ")));
+        }
+
+        let rows = self
+            .result
+            .into_iter()
+            .find_map(|(key, value)| (key != "last").then_some(value))
+            .ok_or_else(|| anyhow!("empty result"))?;
+        Ok(serde_json::from_value(rows)?)
+    }
+}
@@ -0,0 +1,149 @@
+mod binance;
+mod coingecko;
+mod coinmarketcap;
+mod kraken;
+
+use crate::{
+    assets::PricePair,
+    fetch::{fetch_with_retry, RetryPolicy},
+    range::{self, Candle, Query, Window},
+};
+use anyhow::{anyhow, Result};
+use futures::future::join_all;
+use shared::price_feed::{MarketData, Ohlc};
+use wstd::{http::Request, io::Empty};
+
+pub use binance::Binance;
+pub use coingecko::CoinGecko;
+pub use coinmarketcap::CoinMarketCap;
+pub use kraken::Kraken;
+
+/// A single price reported by one upstream, or its summary of a window for range queries
+#[derive(Debug, Clone, PartialEq)]
+pub struct SourceQuote {
+    pub source: &'static str,
+    pub symbol: String,
+    pub price: f64,
+    /// unix seconds
+    pub timestamp: u64,
+    /// Only for OHLC queries
+    pub ohlc: Option<Ohlc>,
+    /// Only for TWAP and OHLC queries: when the first point in the window was, in unix seconds
+    pub started_at: Option<u64>,
+    /// Whatever the source reports besides the price, only for spot queries
+    pub market: MarketData,
+}
+
+/// An upstream price API.
+/// Adding a new upstream means implementing this and registering it in [`all_sources`].
+pub trait PriceSource {
+    /// Unique name, used to select the source and reported in the published result
+    fn name(&self) -> &'static str;
+
+    /// Builds the HTTP request for `pair`, or `None` if this source doesn't list it
+    fn request(&self, pair: &PricePair) -> Result<Option<Request<Empty>>>;
+
+    /// Parses the response body of a request built by [`PriceSource::request`]
+    fn parse(&self, pair: &PricePair, body: &[u8]) -> Result<SourceQuote>;
+
+    /// Builds the HTTP request for price history covering `window`, or `None` if this source
+    /// has none for `pair`
+    fn range_request(&self, _pair: &PricePair, _window: &Window) -> Result<Option<Request<Empty>>> {
+        Ok(None)
+    }
+
+    /// Parses the response body of a request built by [`PriceSource::range_request`].
+    /// Candles outside the window are dropped later, so they don't need to be filtered here.
+    fn parse_range(
+        &self,
+        _pair: &PricePair,
+        _window: &Window,
+        _body: &[u8],
+    ) -> Result<Vec<Candle>> {
+        Err(anyhow!("{} has no price history", self.name()))
+    }
+}
+
+/// Every source, with endpoints and credentials read from the service config and host environment
+pub fn all_sources() -> Vec<Box<dyn PriceSource>> {
+    vec![
+        Box::new(CoinMarketCap::from_env()),
+        Box::new(CoinGecko::from_env()),
+        Box::new(Binance::from_env()),
+        Box::new(Kraken::from_env()),
+    ]
+}
+
+/// Picks sources by name, in the order given. An empty list selects all of them.
+pub fn select_sources(names: &[String]) -> Result<Vec<Box<dyn PriceSource>>> {
+    if names.is_empty() {
+        return Ok(all_sources());
+    }
+
+    let mut available = all_sources();
+    names
+        .iter()
+        .map(|name| {
+            let index = available
+                .iter()
+                .position(|source| source.name() == name)
+                .ok_or_else(|| anyhow!("Unknown or duplicate price source: {}", name))?;
+            Ok(available.remove(index))
+        })
+        .collect()
+}
+
+/// Sources configured via the service config `kv` as a comma-separated list under `key`
+pub fn configured_source_names(key: &str) -> Vec<String> {
+    std::env::var(key)
+        .map(|names| {
+            names
+                .split(',')
+                .map(|name| name.trim().to_string())
+                .filter(|name| !name.is_empty())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Queries every source that lists `pair` concurrently.
+/// Sources that don't list the pair (or have no history for it) are left out, failed sources
+/// are returned as errors.
+/// HTTP failures are [`crate::fetch::FetchError`]s, retried according to `policy`.
+pub async fn fetch_quotes(
+    sources: &[Box<dyn PriceSource>],
+    pair: &PricePair,
+    query: &Query,
+    policy: &RetryPolicy,
+) -> Vec<(&'static str, Result<SourceQuote>)> {
+    let request = |source: &dyn PriceSource| match query {
+        Query::Spot => source.request(pair),
+        Query::Range { window, .. } => source.range_request(pair, window),
+    };
+    let parse = |source: &dyn PriceSource, body: &[u8]| match query {
+        Query::Spot => source.parse(pair, body),
+        Query::Range { mode, window } => {
+            source.parse_range(pair, window, body).and_then(|candles| {
+                range::summarize(source.name(), &pair.base.symbol, candles, *mode, window)
+            })
+        }
+    };
+
+    let requests = sources.iter().filter_map(|source| match request(source.as_ref()) {
+        Ok(None) => None,
+        Ok(Some(req)) => Some((source, Ok(req))),
+        Err(e) => Some((source, Err(e))),
+    });
+
+    join_all(requests.map(|(source, req)| async move {
+        let res = match req {
+            Ok(req) => match fetch_with_retry(req, policy).await {
+                Ok(body) => parse(source.as_ref(), &body),
+                Err(e) => Err(e.into()),
+            },
+            Err(e) => Err(e),
+        };
+        (source.name(), res)
+    }))
+    .await
+}
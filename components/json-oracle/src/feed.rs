@@ -0,0 +1,156 @@
+//! What to fetch and publish, from the request and the service config
+
+use component_support::config::env_var;
+use shared::{
+    error::OracleError,
+    json_oracle::{JsonField, JsonRequest, JsonRequestError, ValueEncoding},
+};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+use wavs_wasi_chain::http::http_request_get;
+use wstd::{
+    http::{HeaderValue, Request, Uri},
+    io::Empty,
+};
+
+/// Read from the service config `kv`, see [`Feed::resolve`]
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FeedConfig {
+    pub url: Option<String>,
+    pub fields: Vec<JsonField>,
+    pub encoding: Option<ValueEncoding>,
+    /// Hosts a requested URL may point at, none if empty
+    pub allowed_hosts: Vec<String>,
+}
+
+impl FeedConfig {
+    /// An invalid `fields` config fails the run rather than let a request pick the fields
+    pub fn from_env() -> Result<Self, OracleError> {
+        Ok(Self {
+            url: env_var("url"),
+            fields: std::env::var("fields")
+                .ok()
+                .map(|json| serde_json::from_str::<Vec<JsonField>>(&json))
+                .transpose()
+                .map_err(|e| {
+                    OracleError::BadInput(format!("Invalid `fields` service config: {e}"))
+                })?
+                .unwrap_or_default(),
+            encoding: env_var("encoding"),
+            allowed_hosts: std::env::var("allowed_hosts")
+                .map(|hosts| {
+                    hosts
+                        .split(',')
+                        .map(|host| host.trim().to_ascii_lowercase())
+                        .filter(|host| !host.is_empty())
+                        .collect()
+                })
+                .unwrap_or_default(),
+        })
+    }
+}
+
+/// A request resolved against the service config
+#[derive(Debug, Clone, PartialEq)]
+pub struct Feed {
+    pub url: Uri,
+    pub fields: Vec<JsonField>,
+    pub encoding: ValueEncoding,
+}
+
+impl Feed {
+    /// Whatever the service config sets is fixed, a request can only fill in the rest.
+    /// A feed configured entirely in the service config takes an empty request.
+    pub fn resolve(req: JsonRequest, config: FeedConfig) -> Result<Self, OracleError> {
+        let requested = config.url.is_none();
+        let url = fixed("url", req.url, config.url)?.ok_or_else(|| {
+            OracleError::BadInput("No URL in the request or the service config".to_string())
+        })?;
+        let fields = fixed(
+            "fields",
+            Some(req.fields).filter(|fields| !fields.is_empty()),
+            Some(config.fields).filter(|fields| !fields.is_empty()),
+        )?
+        .ok_or(JsonRequestError::InvalidFieldCount(0))?;
+        let encoding = fixed("encoding", req.encoding, config.encoding)?.unwrap_or_default();
+
+        let url: Uri =
+            url.parse().map_err(|_| OracleError::BadInput(format!("Invalid URL: {}", url)))?;
+        if !matches!(url.scheme_str(), Some("http" | "https")) || url.host().is_none() {
+            return Err(OracleError::BadInput(format!("Invalid URL: {}", url)));
+        }
+
+        // the operator picked the service config's URL, a requested one could point anywhere the
+        // operator's node can reach
+        let host = url.host().unwrap_or_default().to_ascii_lowercase();
+        if requested && !config.allowed_hosts.contains(&host) {
+            return Err(OracleError::BadInput(format!("Host not allowed: {}", host)));
+        }
+        if is_internal(&host) {
+            return Err(OracleError::BadInput(format!("Internal address not allowed: {}", host)));
+        }
+
+        Ok(Self { url, fields, encoding })
+    }
+
+    pub fn host(&self) -> &str {
+        self.url.host().unwrap_or_default()
+    }
+
+    pub fn request(&self) -> Result<Request<Empty>, OracleError> {
+        let mut req = http_request_get(&self.url.to_string())
+            .map_err(|_| OracleError::BadInput(format!("Invalid URL: {}", self.url)))?;
+        req.headers_mut().insert("Accept", HeaderValue::from_static("application/json"));
+        Ok(req)
+    }
+}
+
+/// Loopback, private, link-local and other addresses that aren't on the public internet. Names
+/// aren't resolved here, so a name pointing at one is only kept out by the allowlist.
+fn is_internal(host: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if host == "localhost" || host.ends_with(".localhost") {
+        return true;
+    }
+    match host.parse::<IpAddr>() {
+        Ok(IpAddr::V4(ip)) => is_internal_v4(ip),
+        Ok(IpAddr::V6(ip)) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_internal_v4(ip),
+            None => is_internal_v6(ip),
+        },
+        Err(_) => false,
+    }
+}
+
+fn is_internal_v4(ip: Ipv4Addr) -> bool {
+    let [a, b, ..] = ip.octets();
+    ip.is_unspecified()
+        || ip.is_loopback()
+        || ip.is_private()
+        || ip.is_link_local()
+        || ip.is_broadcast()
+        // shared address space (RFC 6598)
+        || (a == 100 && (64..128).contains(&b))
+}
+
+fn is_internal_v6(ip: Ipv6Addr) -> bool {
+    let first = ip.segments()[0];
+    ip.is_unspecified()
+        || ip.is_loopback()
+        // unique local (fc00::/7) and link-local (fe80::/10)
+        || (first & 0xfe00) == 0xfc00
+        || (first & 0xffc0) == 0xfe80
+}
+
+/// The configured value if there is one, which the request may only repeat
+fn fixed<T: PartialEq>(
+    key: &str,
+    requested: Option<T>,
+    configured: Option<T>,
+) -> Result<Option<T>, OracleError> {
+    match (requested, configured) {
+        (Some(requested), Some(configured)) if requested != configured => {
+            Err(OracleError::BadInput(format!("`{}` is fixed by the service config", key)))
+        }
+        (requested, configured) => Ok(configured.or(requested)),
+    }
+}
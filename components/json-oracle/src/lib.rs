@@ -0,0 +1,60 @@
+mod feed;
+mod value;
+use component_support::{
+    bindings::{export, Guest, TriggerAction},
+    fetch::{fetch_with_retry, FetchError, RetryPolicy},
+    log::{info, warn},
+    trigger::TriggerEvent,
+    Oracle, Output,
+};
+use feed::{Feed, FeedConfig};
+use shared::{
+    error::OracleError,
+    json_oracle::{JsonRequest, ValueEncoding},
+};
+
+struct Component;
+export!(Component with_types_in component_support::bindings);
+
+impl Guest for Component {
+    fn run(action: TriggerAction) -> std::result::Result<Vec<u8>, String> {
+        component_support::run::<Component>(action)
+    }
+}
+
+impl Oracle for Component {
+    async fn get_output(event: &TriggerEvent) -> Result<Output, OracleError> {
+        let req: JsonRequest = event.request.decode()?;
+        info!("request: {:?}", req);
+
+        let feed = Feed::resolve(req, FeedConfig::from_env()?)?;
+        let host = feed.host();
+
+        // the query may hold an API key, so only the host goes into error messages
+        let body =
+            fetch_with_retry(feed.request()?, &RetryPolicy::from_env()).await.map_err(|e| {
+                warn!("{} failed: {}", host, e);
+                match e {
+                    FetchError::RateLimited { .. } => {
+                        OracleError::UpstreamUnavailable(format!("Rate limited by {}", host))
+                    }
+                    _ => OracleError::UpstreamUnavailable(format!("No response from {}", host)),
+                }
+            })?;
+        let json: serde_json::Value = serde_json::from_slice(&body)
+            .map_err(|_| OracleError::ParseFailure(format!("Unusable response from {}", host)))?;
+
+        let values = feed
+            .fields
+            .iter()
+            .map(|field| value::extract(&json, field))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        // the timestamp is signed with the values, so it can't be the operator's clock
+        let timestamp = match feed.encoding {
+            ValueEncoding::Json => event.block_time().await?,
+            ValueEncoding::Abi => 0,
+        };
+        Ok(value::encode(&feed, values, timestamp))
+    }
+}
@@ -0,0 +1,179 @@
+//! Picking values out of the response and encoding them for publishing
+
+use crate::feed::Feed;
+use alloy_dyn_abi::DynSolValue;
+use alloy_primitives::{Sign, I256, U256};
+use component_support::Output;
+use shared::{
+    abi::ResultEncoding,
+    error::OracleError,
+    json_oracle::{JsonField, JsonOracleOutput, JsonOracleValue, ValueEncoding, ValueType},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Uint(U256),
+    Int(I256),
+    Bool(bool),
+    String(String),
+}
+
+/// Reads the field's value from the response as its type
+pub fn extract(json: &serde_json::Value, field: &JsonField) -> Result<Value, OracleError> {
+    let path = &field.path;
+    let value = path
+        .select(json)
+        .ok_or_else(|| OracleError::ParseFailure(format!("Nothing at {}", path)))?;
+    let not_a = |kind: &str| OracleError::ParseFailure(format!("{} is not a {}", path, kind));
+
+    match field.ty {
+        ValueType::Uint256 | ValueType::Int256 => {
+            // APIs often send numbers as strings, so they survive JavaScript
+            let text = match value {
+                serde_json::Value::Number(number) => number.to_string(),
+                serde_json::Value::String(text) => text.trim().to_string(),
+                _ => return Err(not_a("number")),
+            };
+            let decimal = Decimal::parse(&text).ok_or_else(|| not_a("number"))?;
+            let magnitude = decimal.scale(field.decimals).ok_or_else(|| {
+                OracleError::OutOfRange(format!("{} doesn't fit in {}", path, type_name(field.ty)))
+            })?;
+
+            match field.ty {
+                ValueType::Uint256 if decimal.negative && !magnitude.is_zero() => {
+                    Err(OracleError::OutOfRange(format!("{} is negative", path)))
+                }
+                ValueType::Uint256 => Ok(Value::Uint(magnitude)),
+                _ => {
+                    let sign = match decimal.negative {
+                        true => Sign::Negative,
+                        false => Sign::Positive,
+                    };
+                    I256::checked_from_sign_and_abs(sign, magnitude).map(Value::Int).ok_or_else(
+                        || OracleError::OutOfRange(format!("{} doesn't fit in int256", path)),
+                    )
+                }
+            }
+        }
+        ValueType::Bool => value.as_bool().map(Value::Bool).ok_or_else(|| not_a("bool")),
+        ValueType::String => match value {
+            serde_json::Value::String(text) => Ok(Value::String(text.clone())),
+            serde_json::Value::Number(_) | serde_json::Value::Bool(_) => {
+                Ok(Value::String(value.to_string()))
+            }
+            _ => Err(not_a("string")),
+        },
+    }
+}
+
+fn type_name(ty: ValueType) -> &'static str {
+    match ty {
+        ValueType::Uint256 => "uint256",
+        ValueType::Int256 => "int256",
+        ValueType::Bool => "bool",
+        ValueType::String => "string",
+    }
+}
+
+/// A decimal number as written, `digits × 10^exponent`. Parsed from text rather than
+/// through `f64`, so e.g. `"0.1"` with 18 decimals is exactly `10^17`.
+struct Decimal {
+    negative: bool,
+    digits: String,
+    exponent: i64,
+}
+
+impl Decimal {
+    /// Plain or scientific notation, e.g. `-12.5` or `1.25e-7`
+    fn parse(text: &str) -> Option<Self> {
+        let (negative, text) = match text.strip_prefix('-') {
+            Some(text) => (true, text),
+            None => (false, text.strip_prefix('+').unwrap_or(text)),
+        };
+        let (mantissa, exponent) = match text.split_once(['e', 'E']) {
+            Some((mantissa, exponent)) => (mantissa, exponent.parse::<i64>().ok()?),
+            None => (text, 0),
+        };
+        let (int, frac) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+
+        let digits = format!("{}{}", int, frac);
+        if digits.is_empty() || !digits.bytes().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+
+        Some(Self { negative, digits, exponent: exponent.checked_sub(frac.len() as i64)? })
+    }
+
+    /// The magnitude times `10^decimals`, rounded half away from zero.
+    /// `None` if it doesn't fit in 256 bits.
+    fn scale(&self, decimals: u8) -> Option<U256> {
+        let shift = self.exponent.checked_add(decimals as i64)?;
+
+        let dropped = usize::try_from(shift.unsigned_abs()).unwrap_or(usize::MAX);
+        let (kept, rest) = match shift < 0 {
+            true => self.digits.split_at(self.digits.len().saturating_sub(dropped)),
+            false => (self.digits.as_str(), ""),
+        };
+        // the first dropped digit decides the rounding, unless the cut is before the first digit
+        let round_up = dropped <= self.digits.len() && rest.bytes().next() >= Some(b'5');
+
+        let mut value = U256::ZERO;
+        for digit in kept.bytes() {
+            value = value.checked_mul(U256::from(10))?.checked_add(U256::from(digit - b'0'))?;
+        }
+        if shift > 0 && !value.is_zero() {
+            value = value.checked_mul(U256::from(10).checked_pow(U256::from(shift))?)?;
+        }
+        if round_up {
+            value = value.checked_add(U256::from(1))?;
+        }
+        Some(value)
+    }
+}
+
+/// The result published for the feed, see [`ValueEncoding`]. Only JSON results carry the
+/// `timestamp`.
+pub fn encode(feed: &Feed, values: Vec<Value>, timestamp: u64) -> Output {
+    match feed.encoding {
+        ValueEncoding::Json => {
+            let output = JsonOracleOutput {
+                url: feed.url.to_string(),
+                timestamp,
+                values: feed
+                    .fields
+                    .iter()
+                    .zip(values)
+                    .map(|(field, value)| JsonOracleValue {
+                        path: field.path.to_string(),
+                        ty: field.ty,
+                        decimals: field.decimals,
+                        value: match value {
+                            Value::Uint(value) => value.to_string().into(),
+                            Value::Int(value) => value.to_string().into(),
+                            Value::Bool(value) => value.into(),
+                            Value::String(value) => value.into(),
+                        },
+                    })
+                    .collect(),
+            };
+            let data =
+                serde_json::to_vec(&output).expect("JsonOracleOutput is always serializable");
+            Output::new(data, ResultEncoding::Json)
+        }
+        ValueEncoding::Abi => {
+            let data = DynSolValue::Tuple(
+                values
+                    .into_iter()
+                    .map(|value| match value {
+                        Value::Uint(value) => DynSolValue::Uint(value, 256),
+                        Value::Int(value) => DynSolValue::Int(value, 256),
+                        Value::Bool(value) => DynSolValue::Bool(value),
+                        Value::String(value) => DynSolValue::String(value),
+                    })
+                    .collect(),
+            )
+            .abi_encode_params();
+            Output::new(data, ResultEncoding::AbiValues)
+        }
+    }
+}
@@ -1,19 +1,29 @@
 use std::sync::LazyLock;
 
-use alloy_provider::{fillers::{BlobGasFiller, ChainIdFiller, FillProvider, GasFiller, JoinFill, NonceFiller, WalletFiller}, network::EthereumWallet, DynProvider, Identity, Provider, RootProvider};
+use alloy_provider::{fillers::{BlobGasFiller, ChainIdFiller, FillProvider, GasFiller, JoinFill, NonceFiller, WalletFiller}, network::EthereumWallet, DynProvider, Identity, Provider, ProviderBuilder, RootProvider};
+use alloy_primitives::B256;
 use alloy_sol_types::SolValue;
-use shared::price_feed::PriceFeedData;
+use shared::{
+    abi::{self, from_fixed_point, request_hash, ErrorCode, PriceFeed, RelayMessage, RoundData, DEFAULT_DECIMALS},
+    codec::decode_abi,
+    error::OracleError,
+    price_feed::{MarketData, Ohlc, PriceFeedData},
+    relay::RelayRequest,
+    request::{OutputEncoding, PriceMode, PriceRequest, DEFAULT_WINDOW},
+};
 use trigger::{ITypes::TriggerInfo, WavsTrigger::NewTrigger};
-use anyhow::{Result, Context};
+use anyhow::{Result, Context, bail};
+use wavs_types::ChainName;
 
-use crate::config::CONFIG;
+use crate::config::{relay_chains, CONFIG};
 
 use super::client::{Client, CLIENT};
 
 // Will panic if called in flows where the client is not connected
 // but this is inherently gated via UI state
-pub static TRIGGER_CONTRACT: LazyLock<TriggerContract> = LazyLock::new(|| {
-    let (_, workflow) = CONFIG.unchecked_workflow();
+// None if no price oracle is deployed, see price_contracts
+pub static TRIGGER_CONTRACT: LazyLock<Option<TriggerContract>> = LazyLock::new(|| {
+    let (_, workflow) = CONFIG.price_workflow()?;
     let address = match workflow.trigger {
         wavs_types::Trigger::EthContractEvent { address, .. } => address,
         _ => unimplemented!()
@@ -21,16 +31,17 @@ pub static TRIGGER_CONTRACT: LazyLock<TriggerContract> = LazyLock::new(|| {
 
     let provider = CLIENT.get_cloned().unwrap().provider();
 
-    TriggerContract{
+    Some(TriggerContract{
         instance: trigger::WavsTrigger::new(address, provider.clone()),
         provider
-    }
+    })
 });
 
 // Will panic if called in flows where the client is not connected
 // but this is inherently gated via UI state
-pub static SUBMIT_CONTRACT: LazyLock<SubmitContract> = LazyLock::new(|| {
-    let (_, workflow) = CONFIG.unchecked_workflow();
+// None if no price oracle is deployed, see price_contracts
+pub static SUBMIT_CONTRACT: LazyLock<Option<SubmitContract>> = LazyLock::new(|| {
+    let (_, workflow) = CONFIG.price_workflow()?;
     let address = match workflow.submit {
         wavs_types::Submit::EthereumContract{ address, .. } => address,
         _ => unimplemented!()
@@ -38,12 +49,71 @@ pub static SUBMIT_CONTRACT: LazyLock<SubmitContract> = LazyLock::new(|| {
 
     let provider = CLIENT.get_cloned().unwrap().provider();
 
-    SubmitContract {
+    Some(SubmitContract {
         instance: submit::WavsSubmit::new(address, provider.clone()),
         provider
-    }
+    })
 });
 
+// The price oracle's trigger and submit contracts, an error if only a relay is deployed
+pub fn price_contracts() -> Result<(&'static TriggerContract, &'static SubmitContract)> {
+    match (TRIGGER_CONTRACT.as_ref(), SUBMIT_CONTRACT.as_ref()) {
+        (Some(trigger), Some(submit)) => Ok((trigger, submit)),
+        _ => bail!("No price oracle service is deployed"),
+    }
+}
+
+// The relay triggers on the wallet's chain, its results are read on the destination chain
+// which the wallet may not be connected to, so the submit contract gets its own provider
+pub struct RelayContracts {
+    pub source: ChainName,
+    pub destination: ChainName,
+    trigger: TriggerContract,
+    submit: SubmitContract,
+}
+
+impl RelayContracts {
+    pub async fn connect() -> Result<Self> {
+        let (_, workflow) = CONFIG.relay_workflow().context("No relay service is deployed")?;
+        let (source, destination) = relay_chains(&workflow).context("Not a relay workflow")?;
+        if source != CONFIG.chain_name {
+            bail!("The relay triggers on {}, but the wallet is on {}", source, CONFIG.chain_name);
+        }
+
+        let (trigger_address, submit_address) = match (workflow.trigger, workflow.submit) {
+            (wavs_types::Trigger::EthContractEvent { address: trigger, .. }, wavs_types::Submit::EthereumContract { address: submit, .. }) => (trigger, submit),
+            _ => unreachable!()
+        };
+
+        let provider = CLIENT.get_cloned().unwrap().provider();
+        let endpoint = CONFIG.unchecked_data().cli.chains.get_eth_chain(&destination)?.http_endpoint
+            .with_context(|| format!("No HTTP endpoint for {}", destination))?;
+        let destination_provider = DynProvider::new(ProviderBuilder::new().on_builtin(&endpoint).await?);
+
+        Ok(Self {
+            source,
+            trigger: TriggerContract {
+                instance: trigger::WavsTrigger::new(trigger_address, provider.clone()),
+                provider,
+            },
+            submit: SubmitContract {
+                instance: submit::WavsSubmit::new(submit_address, destination_provider.clone()),
+                provider: destination_provider,
+            },
+            destination,
+        })
+    }
+
+    pub async fn send(&self, payload: Vec<u8>) -> Result<TriggerInfo> {
+        let req = RelayRequest::new(payload).with_destination(&self.destination);
+        self.trigger.add_trigger(req.encode()).await
+    }
+
+    pub async fn get_relay_message(&self, trigger_id: u64) -> Result<Option<RelayMessage>> {
+        self.submit.get_relay_message(trigger_id, &self.trigger).await
+    }
+}
+
 pub struct TriggerContract {
     instance: trigger::WavsTrigger::WavsTriggerInstance<(), DynProvider>,
     provider: DynProvider,
@@ -71,6 +141,23 @@ impl TriggerContract {
 
         Ok(trigger_info)
     }
+
+    // What a result for the trigger must carry, from the contract's own record of the request
+    pub async fn request_hash(&self, trigger_id: u64) -> Result<B256> {
+        let trigger_info = self.instance
+            .getTrigger(trigger_id)
+            .call()
+            .await?
+            ._0;
+
+        let chain_id = self.provider.get_chain_id().await?;
+
+        Ok(request_hash(chain_id, *self.instance.address(), &abi::TriggerInfo {
+            triggerId: trigger_info.triggerId,
+            creator: trigger_info.creator,
+            data: trigger_info.data,
+        }))
+    }
 }
 
 pub struct SubmitContract {
@@ -79,7 +166,105 @@ pub struct SubmitContract {
 }
 
 impl SubmitContract {
-    pub async fn get_price_feed(&self, trigger_id: u64) -> Result<Option<PriceFeedData>> {
+    // Single requests are returned as a batch of one
+    // the request is needed to know how the result was encoded
+    pub async fn get_price_feed(&self, trigger_id: u64, req: &PriceRequest, trigger: &TriggerContract) -> Result<Option<Vec<PriceFeedData>>> {
+        let data = self.instance
+            .getData(trigger_id)
+            .call()
+            .await?
+            .data;
+
+        if data.is_empty() {
+            return Ok(None);
+        }
+
+        self.check_request(trigger_id, trigger).await?;
+
+        // a failed run publishes its error message in place of the result
+        let error = self.instance
+            .getError(trigger_id)
+            .call()
+            .await?;
+
+        if let Some(e) = OracleError::from_parts(ErrorCode::try_from(error.code)?, error.message) {
+            bail!(e);
+        }
+
+        // a result is only ever read the way it was written
+        let encoding = self.instance
+            .getEncoding(trigger_id)
+            .call()
+            .await?
+            .encoding;
+
+        if encoding != req.options.encoding.result_encoding(&req.assets) as u8 {
+            bail!("The result for trigger {} isn't in the requested encoding", trigger_id);
+        }
+
+        if let Some(codec) = req.options.encoding.feed_codec() {
+            return Ok(Some(codec.decode(&data)?.into_vec()));
+        }
+
+        match req.options.encoding {
+            OutputEncoding::Abi => {
+                let feeds = decode_abi::<PriceFeed>(&data, &req.assets)?;
+
+                // the ABI output has no symbols, so label the feeds with what was requested
+                Ok(Some(feeds.into_iter().zip(req.assets.as_slice()).map(|(feed, asset)| {
+                    let mode = PriceMode::from(feed.mode);
+                    PriceFeedData {
+                        symbol: asset.to_string(),
+                        timestamp: feed.timestamp,
+                        price: from_fixed_point(feed.price, feed.decimals),
+                        sources: Vec::new(),
+                        block_height: Some(feed.blockHeight).filter(|height| *height != 0),
+                        tx_hash: Some(feed.txHash).filter(|hash| !hash.is_zero()).map(|hash| hash.to_string()),
+                        mode,
+                        window: Some(feed.window).filter(|window| *window != 0),
+                        ohlc: (mode == PriceMode::Ohlc).then(|| Ohlc {
+                            open: from_fixed_point(feed.open, feed.decimals),
+                            high: from_fixed_point(feed.high, feed.decimals),
+                            low: from_fixed_point(feed.low, feed.decimals),
+                            close: from_fixed_point(feed.price, feed.decimals),
+                        }),
+                        // ABI results only carry the price
+                        market: MarketData::default(),
+                        reference: None,
+                        circuit_breaker: None,
+                    }
+                }).collect()))
+            }
+            // Chainlink, the only other encoding without a feed codec
+            _ => {
+                let rounds = decode_abi::<RoundData>(&data, &req.assets)?;
+
+                let decimals = req.options.decimals.unwrap_or(DEFAULT_DECIMALS);
+                // rounds start at the first point in the window, not where the window starts
+                let window = (!req.options.mode.is_spot()).then(|| req.options.window.unwrap_or(DEFAULT_WINDOW));
+                Ok(Some(rounds.into_iter().zip(req.assets.as_slice()).map(|(round, asset)| {
+                    let updated_at = round.updatedAt.to::<u64>();
+                    PriceFeedData {
+                        symbol: asset.to_string(),
+                        timestamp: updated_at,
+                        price: from_fixed_point(round.answer.into_raw(), decimals),
+                        sources: Vec::new(),
+                        block_height: None,
+                        tx_hash: None,
+                        mode: req.options.mode,
+                        window,
+                        ohlc: None,
+                        market: MarketData::default(),
+                        reference: None,
+                        circuit_breaker: None,
+                    }
+                }).collect()))
+            }
+        }
+    }
+
+    /// `None` until the relay has published, checked by the contract to be meant for this chain
+    pub async fn get_relay_message(&self, trigger_id: u64, trigger: &TriggerContract) -> Result<Option<RelayMessage>> {
         let data = self.instance
             .getData(trigger_id)
             .call()
@@ -87,10 +272,53 @@ impl SubmitContract {
             .data;
 
         if data.is_empty() {
-            Ok(None)
-        } else {
-            Ok(serde_json::from_slice(&data)?)
+            return Ok(None);
         }
+
+        self.check_request(trigger_id, trigger).await?;
+
+        let error = self.instance
+            .getError(trigger_id)
+            .call()
+            .await?;
+
+        if let Some(e) = OracleError::from_parts(ErrorCode::try_from(error.code)?, error.message) {
+            bail!(e);
+        }
+
+        let message = self.instance
+            .getRelayMessage(trigger_id)
+            .call()
+            .await?
+            .message;
+
+        Ok(Some(RelayMessage {
+            sourceChainId: message.sourceChainId,
+            sourceBlock: message.sourceBlock,
+            sourceBlockHash: message.sourceBlockHash,
+            logIndex: message.logIndex,
+            sourceTrigger: message.sourceTrigger,
+            sourceTxHash: message.sourceTxHash,
+            creator: message.creator,
+            destinationChainId: message.destinationChainId,
+            payload: message.payload,
+        }))
+    }
+
+    /// Fails unless the result published for `trigger_id` answers the request `trigger` has for it,
+    /// and not one made on another contract or chain
+    async fn check_request(&self, trigger_id: u64, trigger: &TriggerContract) -> Result<()> {
+        let published = self.instance
+            .getRequestHash(trigger_id)
+            .call()
+            .await?
+            .hash;
+
+        if published != trigger.request_hash(trigger_id).await? {
+            bail!("The result for trigger {} doesn't match its request", trigger_id);
+        }
+
+        Ok(())
     }
 }
 
@@ -8,7 +8,7 @@ use futures_signals::signal::Mutable;
 use serde::Deserialize;
 use wasm_bindgen::UnwrapThrowExt;
 use wasm_bindgen_futures::spawn_local;
-use wavs_types::{ChainName, Service, ServiceID, Workflow, WorkflowID};
+use wavs_types::{ChainName, Service, ServiceID, Submit, Trigger, Workflow, WorkflowID};
 
 #[derive(Debug)]
 pub struct Config {
@@ -29,8 +29,29 @@ impl Config {
         self.unchecked_data().deployments.services.first_key_value().map(|(x, y)| (x.clone(), y.clone())).unwrap()
     }
 
-    pub fn unchecked_workflow(&self) -> (WorkflowID, Workflow) {
-        self.unchecked_service().1.workflows.first_key_value().map(|(x, y)| (x.clone(), y.clone())).unwrap()
+    // The first workflow that submits on the chain it triggers on, if one is deployed
+    // relays are left to relay_workflow, and may be all that is deployed
+    pub fn price_workflow(&self) -> Option<(WorkflowID, Workflow)> {
+        self.workflows().into_iter().find(|(_, workflow)| relay_chains(workflow).is_none())
+    }
+
+    // A workflow that triggers on one chain and submits on another, if one is deployed
+    pub fn relay_workflow(&self) -> Option<(WorkflowID, Workflow)> {
+        self.workflows().into_iter().find(|(_, workflow)| relay_chains(workflow).is_some())
+    }
+
+    fn workflows(&self) -> Vec<(WorkflowID, Workflow)> {
+        self.unchecked_data().deployments.services.values().flat_map(|service| service.workflows.clone()).collect()
+    }
+}
+
+/// The source and destination chain of a workflow that relays between EVM chains
+pub fn relay_chains(workflow: &Workflow) -> Option<(ChainName, ChainName)> {
+    match (&workflow.trigger, &workflow.submit) {
+        (Trigger::EthContractEvent { chain_name: source, .. }, Submit::EthereumContract { chain_name: destination, .. }) if source != destination => {
+            Some((source.clone(), destination.clone()))
+        }
+        _ => None,
     }
 }
 
@@ -16,6 +16,7 @@ impl Header {
                 .style("align-items", "center")
                 .style("margin-top", "1rem")
                 .style("margin-left", "1rem")
+                .style("gap", "1rem")
             }
         });
         html!("div", {
@@ -26,6 +27,14 @@ impl Header {
                 .with_link(Route::Landing)
                 .render()
             )
+            .apply_if(CONFIG.relay_workflow().is_some(), |dom| {
+                dom.child(Button::new()
+                    .with_style(ButtonStyle::Outline)
+                    .with_text("Relay")
+                    .with_link(Route::Relay)
+                    .render()
+                )
+            })
         })
     }
 }
\ No newline at end of file
@@ -11,7 +11,7 @@ mod chain;
 mod header;
 
 use header::Header;
-use pages::{app::AppUi, landing::LandingUi, not_found::NotFoundUi, wallet_connect::WalletConnectUi};
+use pages::{app::AppUi, landing::LandingUi, not_found::NotFoundUi, relay::RelayUi, wallet_connect::WalletConnectUi};
 use prelude::*;
 
 pub fn main() {
@@ -36,7 +36,7 @@ async fn init() {
                     Route::Landing 
                     | Route::WalletConnect
                     | Route::NotFound => route.clone(),
-                    Route::App => if *client {
+                    Route::App | Route::Relay => if *client {
                         route.clone()
                     } else {
                         Route::WalletConnect
@@ -74,6 +74,7 @@ async fn init() {
                         Route::Landing => LandingUi::new().render(),
                         Route::WalletConnect => WalletConnectUi::new().render(),
                         Route::App => AppUi::new().render(),
+                        Route::Relay => RelayUi::new().render(),
                         Route::NotFound => NotFoundUi::new().render()
                     }
                 })
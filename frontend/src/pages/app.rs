@@ -3,14 +3,18 @@ use alloy_sol_types::SolType;
 use dominator_helpers::futures::AsyncLoader;
 use futures::{channel::mpsc::{self, Receiver}, Stream, StreamExt};
 use gloo_timers::future::{IntervalStream, TimeoutFuture};
-use shared::price_feed::PriceFeedData;
+use shared::{price_feed::PriceFeedData, request::{Asset, MarketField, OutputEncoding, PriceMode, PriceRequest, PriceRequestOptions}};
 use wasm_bindgen_futures::spawn_local;
-use crate::{chain::contract::{SUBMIT_CONTRACT, TRIGGER_CONTRACT}, prelude::*};
+use crate::{chain::contract::price_contracts, prelude::*};
 
 pub struct AppUi { 
     pub error: Mutable<Option<String>>,
+    pub assets: Mutable<Option<String>>,
+    pub encoding: Mutable<OutputEncoding>,
+    pub mode: Mutable<PriceMode>,
+    pub market_data: Mutable<bool>,
     pub trigger_id: Mutable<Option<u64>>,
-    pub price_feed: Mutable<Option<Arc<PriceFeedData>>>,
+    pub price_feeds: Mutable<Option<Arc<Vec<PriceFeedData>>>>,
     pub loader: AsyncLoader,
 }
 
@@ -18,8 +22,12 @@ impl AppUi {
     pub fn new() -> Arc<Self> {
         Arc::new(Self {
             error: Mutable::new(None),
+            assets: Mutable::new(Some("BTC".to_string())),
+            encoding: Mutable::new(OutputEncoding::Json),
+            mode: Mutable::new(PriceMode::Spot),
+            market_data: Mutable::new(false),
             trigger_id: Mutable::new(None),
-            price_feed: Mutable::new(None),
+            price_feeds: Mutable::new(None),
             loader: AsyncLoader::new()
         })
     }
@@ -43,18 +51,101 @@ impl AppUi {
                 .class([FontSize::H1.class(), &*TEXT_ALIGN_CENTER])
                 .text("App")
             }))
+            .child(TextInput::new()
+                .with_placeholder("Symbols or CoinMarketCap IDs, comma-separated")
+                .with_intial_value(state.assets.get_cloned().unwrap_or_default())
+                .with_on_input(clone!(state => move |assets| {
+                    state.assets.set(assets);
+                }))
+                .render()
+            )
+            .child(Label::new()
+                .with_text("Output encoding")
+                .render(Dropdown::new()
+                    .with_intial_selected(Some(state.encoding.get()))
+                    .with_options([
+                        ("JSON".to_string(), OutputEncoding::Json),
+                        ("ABI (fixed-point)".to_string(), OutputEncoding::Abi),
+                        ("Chainlink round".to_string(), OutputEncoding::Chainlink),
+                        ("CBOR".to_string(), OutputEncoding::Cbor),
+                        ("Bincode (compact)".to_string(), OutputEncoding::Bincode),
+                    ])
+                    .with_on_change(clone!(state => move |encoding| {
+                        state.encoding.set(*encoding);
+                    }))
+                    .render()
+                )
+            )
+            .child(Label::new()
+                .with_text("Price")
+                .render(Dropdown::new()
+                    .with_intial_selected(Some(state.mode.get()))
+                    .with_options([
+                        ("Spot".to_string(), PriceMode::Spot),
+                        ("TWAP (1 hour)".to_string(), PriceMode::Twap),
+                        ("OHLC (1 hour)".to_string(), PriceMode::Ohlc),
+                    ])
+                    .with_on_change(clone!(state => move |mode| {
+                        state.mode.set(*mode);
+                    }))
+                    .render()
+                )
+            )
+            .child(Label::new()
+                .with_text("Market data (not in ABI results)")
+                .render(Dropdown::new()
+                    .with_intial_selected(Some(state.market_data.get()))
+                    .with_options([
+                        ("None".to_string(), false),
+                        ("Market cap, volume, supply and rank".to_string(), true),
+                    ])
+                    .with_on_change(clone!(state => move |market_data| {
+                        state.market_data.set(*market_data);
+                    }))
+                    .render()
+                )
+            )
             .child(Button::new()
                 .with_text("Send Transaction")
                 .with_on_click(clone!(state => move || {
                     state.loader.load(clone!(state => async move {
                         state.error.set(None);
                         state.trigger_id.set(None);
-                        state.price_feed.set(None);
+                        state.price_feeds.set(None);
 
-                        match TRIGGER_CONTRACT.add_trigger(b"1".to_vec()).await {
+                        let assets = state.assets.get_cloned().unwrap_or_default()
+                            .split(',')
+                            .map(|asset| asset.parse::<Asset>())
+                            .collect::<std::result::Result<Vec<_>, _>>();
+
+                        let req = match assets {
+                            Ok(mut assets) if assets.len() == 1 => PriceRequest::new(assets.remove(0)),
+                            Ok(assets) => PriceRequest::batch(assets),
+                            Err(e) => {
+                                state.error.set(Some(e.to_string()));
+                                return;
+                            }
+                        };
+
+                        let req = req.with_options(PriceRequestOptions {
+                            encoding: state.encoding.get(),
+                            mode: state.mode.get(),
+                            fields: if state.market_data.get() { MarketField::ALL.to_vec() } else { Vec::new() },
+                            ..Default::default()
+                        });
+
+                        let (trigger_contract, _) = match price_contracts() {
+                            Ok(contracts) => contracts,
+                            Err(e) => {
+                                state.error.set(Some(e.to_string()));
+                                return;
+                            }
+                        };
+
+                        match trigger_contract.add_trigger(req.encode()).await {
                             Ok(trigger_info) => {
                                 state.trigger_id.set(Some(trigger_info.triggerId));
-                                state.wait_for_trigger(trigger_info.triggerId).await;
+                                state.wait_for_trigger(trigger_info.triggerId, &req).await;
                             },
                             Err(e) => {
                                 state.error.set(Some(e.to_string()));
@@ -82,30 +173,19 @@ impl AppUi {
                     })
                 })
             })))
-            .child_signal(state.price_feed.signal_cloned().map(clone!(state => move |price_feed| {
-                price_feed.map(|price_feed| {
-                    static CONTAINER: LazyLock<String> = LazyLock::new(|| {
+            .child_signal(state.price_feeds.signal_cloned().map(clone!(state => move |price_feeds| {
+                price_feeds.map(|price_feeds| {
+                    static LIST: LazyLock<String> = LazyLock::new(|| {
                         class! {
-                            .style("margin-top", "2rem")
                             .style("display", "flex")
-                            .style("flex-direction", "column")
-                            .style("align-items", "center")
-                            .style("gap", "1rem")
+                            .style("flex-wrap", "wrap")
+                            .style("justify-content", "center")
+                            .style("gap", "2rem")
                         }
                     });
                     html!("div", {
-                        .class([&*CONTAINER, FontSize::H3.class()])
-                        .children([
-                            html!("div", {
-                                .text(&format!("Symbol: {}", price_feed.symbol))
-                            }),
-                            html!("div", {
-                                .text(&format!("Timestamp: {}", price_feed.timestamp))
-                            }),
-                            html!("div", {
-                                .text(&format!("Price: {}", price_feed.price))
-                            }),
-                        ])
+                        .class(&*LIST)
+                        .children(price_feeds.iter().map(Self::render_price_feed))
                     })
                 })
             })))
@@ -123,8 +203,98 @@ impl AppUi {
         })
     }
 
-    async fn wait_for_trigger(self: &Arc<Self>, trigger_id: u64) {
+    fn render_price_feed(price_feed: &PriceFeedData) -> Dom {
+        static CONTAINER: LazyLock<String> = LazyLock::new(|| {
+            class! {
+                .style("margin-top", "2rem")
+                .style("display", "flex")
+                .style("flex-direction", "column")
+                .style("align-items", "center")
+                .style("gap", "1rem")
+            }
+        });
+        html!("div", {
+            .class([&*CONTAINER, FontSize::H3.class()])
+            .children([
+                html!("div", {
+                    .text(&format!("Symbol: {}", price_feed.symbol))
+                }),
+                html!("div", {
+                    .text(&format!("Timestamp: {}", String::from(js_sys::Date::new(&JsValue::from_f64(price_feed.timestamp as f64 * 1000.0)).to_iso_string())))
+                }),
+                html!("div", {
+                    .text(&match (price_feed.mode, price_feed.window) {
+                        (PriceMode::Twap, Some(window)) => format!("TWAP ({}s): {}", window, price_feed.price),
+                        _ => format!("Price: {}", price_feed.price),
+                    })
+                }),
+                html!("div", {
+                    .text(&format!("Sources: {}", price_feed.sources.join(", ")))
+                }),
+            ])
+            .apply_if(price_feed.ohlc.is_some(), |dom| {
+                let ohlc = price_feed.ohlc.unwrap();
+                dom.child(html!("div", {
+                    .text(&format!("Open: {} High: {} Low: {} Close: {} ({}s)", ohlc.open, ohlc.high, ohlc.low, ohlc.close, price_feed.window.unwrap_or_default()))
+                }))
+            })
+            .children(Self::market_data_lines(price_feed).into_iter().map(|line| {
+                html!("div", {
+                    .text(&line)
+                })
+            }))
+            .apply_if(price_feed.reference.is_some(), |dom| {
+                let reference = price_feed.reference.as_ref().unwrap();
+                dom.child(html!("div", {
+                    .apply_if(reference.flagged, |dom| dom.class(ColorText::Error.class()))
+                    .text(&format!("Reference ({}): {}, {} bps away{}", reference.source, reference.price, reference.deviation_bps, if reference.flagged { ", flagged" } else { "" }))
+                }))
+            })
+            .apply_if(price_feed.circuit_breaker.is_some(), |dom| {
+                let check = price_feed.circuit_breaker.as_ref().unwrap();
+                dom.child(html!("div", {
+                    .apply_if(check.flagged, |dom| dom.class(ColorText::Error.class()))
+                    .text(&format!("Previous (trigger {}): {}, {:+}%{}", check.trigger_id, check.price, check.change_percent, if check.flagged { ", flagged" } else { "" }))
+                }))
+            })
+            .apply_if(price_feed.block_height.is_some(), |dom| {
+                dom.child(html!("div", {
+                    .text(&format!("Block: {}", price_feed.block_height.unwrap_or_default()))
+                }))
+            })
+            .apply_if(price_feed.tx_hash.is_some(), |dom| {
+                dom.child(html!("div", {
+                    .text(&format!("Transaction: {}", price_feed.tx_hash.as_deref().unwrap_or_default()))
+                }))
+            })
+        })
+    }
+
+    /// One line per market data field the result has
+    fn market_data_lines(price_feed: &PriceFeedData) -> Vec<String> {
+        let market = &price_feed.market;
+        [
+            market.market_cap.map(|value| format!("Market cap: {}", value)),
+            market.volume_24h.map(|value| format!("24h volume: {}", value)),
+            market.percent_change_24h.map(|value| format!("24h change: {}%", value)),
+            market.circulating_supply.map(|value| format!("Circulating supply: {}", value)),
+            market.total_supply.map(|value| format!("Total supply: {}", value)),
+            market.rank.map(|value| format!("Rank: {}", value)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    async fn wait_for_trigger(self: &Arc<Self>, trigger_id: u64, req: &PriceRequest) {
         let state = self;
+        let (trigger_contract, submit_contract) = match price_contracts() {
+            Ok(contracts) => contracts,
+            Err(e) => {
+                state.error.set(Some(e.to_string()));
+                return;
+            }
+        };
         let performance = web_sys::window().unwrap().performance().unwrap();
         let timeout = performance.now() + 10_000.0;
         loop {
@@ -133,9 +303,9 @@ impl AppUi {
                 break;
             }
 
-            match SUBMIT_CONTRACT.get_price_feed(trigger_id).await {
-                Ok(Some(price_feed)) => {
-                    state.price_feed.set(Some(Arc::new(price_feed)));
+            match submit_contract.get_price_feed(trigger_id, req, trigger_contract).await {
+                Ok(Some(price_feeds)) => {
+                    state.price_feeds.set(Some(Arc::new(price_feeds)));
                     break;
                 },
                 Err(e) => {
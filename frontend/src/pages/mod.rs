@@ -1,4 +1,5 @@
 pub mod app;
 pub mod landing;
 pub mod not_found;
+pub mod relay;
 pub mod wallet_connect;
\ No newline at end of file
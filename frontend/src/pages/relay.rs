@@ -0,0 +1,180 @@
+use dominator_helpers::futures::AsyncLoader;
+use gloo_timers::future::TimeoutFuture;
+use shared::abi::RelayMessage;
+use crate::{chain::contract::RelayContracts, prelude::*};
+
+pub struct RelayUi {
+    pub error: Mutable<Option<String>>,
+    pub message: Mutable<Option<String>>,
+    pub route: Mutable<Option<String>>,
+    pub trigger_id: Mutable<Option<u64>>,
+    pub relayed: Mutable<Option<Arc<RelayMessage>>>,
+    pub loader: AsyncLoader,
+}
+
+impl RelayUi {
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            error: Mutable::new(None),
+            message: Mutable::new(Some("hello".to_string())),
+            route: Mutable::new(None),
+            trigger_id: Mutable::new(None),
+            relayed: Mutable::new(None),
+            loader: AsyncLoader::new()
+        })
+    }
+
+    pub fn render(self: &Arc<Self>) -> Dom {
+        static CONTAINER: LazyLock<String> = LazyLock::new(|| {
+            class! {
+                .style("margin-top", "2rem")
+                .style("display", "flex")
+                .style("flex-direction", "column")
+                .style("align-items", "center")
+                .style("gap", "1rem")
+            }
+        });
+
+        let state = self;
+
+        html!("div", {
+            .class(&*CONTAINER)
+            .child(html!("div", {
+                .class([FontSize::H1.class(), &*TEXT_ALIGN_CENTER])
+                .text("Relay")
+            }))
+            .child(TextInput::new()
+                .with_placeholder("Message to relay")
+                .with_intial_value(state.message.get_cloned().unwrap_or_default())
+                .with_on_input(clone!(state => move |message| {
+                    state.message.set(message);
+                }))
+                .render()
+            )
+            .child(Button::new()
+                .with_text("Send Transaction")
+                .with_on_click(clone!(state => move || {
+                    state.loader.load(clone!(state => async move {
+                        state.error.set(None);
+                        state.trigger_id.set(None);
+                        state.relayed.set(None);
+
+                        let relay = match RelayContracts::connect().await {
+                            Ok(relay) => relay,
+                            Err(e) => {
+                                state.error.set(Some(e.to_string()));
+                                return;
+                            }
+                        };
+                        state.route.set(Some(format!("{} to {}", relay.source, relay.destination)));
+
+                        let payload = state.message.get_cloned().unwrap_or_default().into_bytes();
+                        match relay.send(payload).await {
+                            Ok(trigger_info) => {
+                                state.trigger_id.set(Some(trigger_info.triggerId));
+                                state.wait_for_relay(&relay, trigger_info.triggerId).await;
+                            },
+                            Err(e) => {
+                                state.error.set(Some(e.to_string()));
+                            },
+                        }
+                    }))
+                }))
+                .render()
+            )
+            .child_signal(state.loader.is_loading().map(|is_loading| {
+                if is_loading {
+                    Some(html!("div", {
+                        .class([FontSize::H3.class(), &*TEXT_ALIGN_CENTER])
+                        .text("Loading...")
+                    }))
+                } else {
+                    None
+                }
+            }))
+            .child_signal(map_ref! {
+                let route = state.route.signal_cloned(),
+                let trigger_id = state.trigger_id.signal()
+                => (route.clone(), *trigger_id)
+            }.map(|(route, trigger_id)| {
+                trigger_id.map(|trigger_id| {
+                    html!("div", {
+                        .class([FontSize::H3.class(), &*TEXT_ALIGN_CENTER])
+                        .text(&format!("Trigger ID: {} ({})", trigger_id, route.unwrap_or_default()))
+                    })
+                })
+            }))
+            .child_signal(state.relayed.signal_cloned().map(|relayed| {
+                relayed.map(|relayed| Self::render_relay_message(&relayed))
+            }))
+            .child_signal(state.error.signal_cloned().map(clone!(state => move |error| {
+                error.map(|error| {
+                    html!("div", {
+                        .class([FontSize::H3.class(), ColorText::Error.class(), &*TEXT_ALIGN_CENTER])
+                        .text(&error)
+                    })
+                })
+            })))
+        })
+    }
+
+    fn render_relay_message(message: &RelayMessage) -> Dom {
+        static CONTAINER: LazyLock<String> = LazyLock::new(|| {
+            class! {
+                .style("margin-top", "2rem")
+                .style("display", "flex")
+                .style("flex-direction", "column")
+                .style("align-items", "center")
+                .style("gap", "1rem")
+            }
+        });
+        html!("div", {
+            .class([&*CONTAINER, FontSize::H3.class()])
+            .children([
+                html!("div", {
+                    .text(&format!("Message: {}", String::from_utf8_lossy(&message.payload)))
+                }),
+                html!("div", {
+                    .text(&format!("From chain {} to chain {}", message.sourceChainId, message.destinationChainId))
+                }),
+                html!("div", {
+                    .text(&format!("Block: {} ({}) Log index: {}", message.sourceBlock, message.sourceBlockHash, message.logIndex))
+                }),
+                html!("div", {
+                    .text(&format!("Transaction: {}", message.sourceTxHash))
+                }),
+                html!("div", {
+                    .text(&format!("Sender: {}", message.creator))
+                }),
+            ])
+        })
+    }
+
+    async fn wait_for_relay(self: &Arc<Self>, relay: &RelayContracts, trigger_id: u64) {
+        let state = self;
+        let performance = web_sys::window().unwrap().performance().unwrap();
+        // the result lands on another chain, which can take a few more blocks
+        let timeout = performance.now() + 30_000.0;
+        loop {
+            if performance.now() > timeout {
+                state.error.set(Some("Timeout!".to_string()));
+                break;
+            }
+
+            match relay.get_relay_message(trigger_id).await {
+                Ok(Some(message)) => {
+                    state.relayed.set(Some(Arc::new(message)));
+                    break;
+                },
+                Err(e) => {
+                    state.error.set(Some(e.to_string()));
+                    break;
+                },
+                Ok(None) => {
+                    // still waiting...
+                    TimeoutFuture::new(500).await;
+                }
+            }
+        }
+    }
+}
@@ -5,6 +5,7 @@ pub enum Route {
     Landing,
     WalletConnect,
     App,
+    Relay,
     NotFound,
 }
 
@@ -27,6 +28,7 @@ impl Route {
             [""] => Self::Landing,
             ["wallet-connect"] => Self::WalletConnect,
             ["app"] => Self::App,
+            ["relay"] => Self::Relay,
             _ => Self::NotFound,
         }
     }
@@ -60,6 +62,7 @@ impl std::fmt::Display for Route {
             Route::Landing => "/".to_string(), 
             Route::WalletConnect => "wallet-connect".to_string(), 
             Route::App => "app".to_string(),
+            Route::Relay => "relay".to_string(),
             Route::NotFound => "404".to_string(), 
         };
         write!(f, "{}", s)
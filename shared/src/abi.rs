@@ -0,0 +1,101 @@
+use crate::request::PriceMode;
+use alloy_primitives::{aliases::U80, keccak256, Address, B256, U256};
+use alloy_sol_types::{
+    sol_data::{self, Uint},
+    SolType, SolValue,
+};
+
+pub use solidity::{
+    AssetPrice, DataWithId, ErrorCode, LatestPrice, NewTrigger, PriceFeed, RelayMessage,
+    ResultEncoding, RoundData, TokenSnapshot, TriggerInfo,
+};
+
+/// Same precision as Chainlink's USD feeds
+pub const DEFAULT_DECIMALS: u8 = 8;
+
+/// Larger values would overflow the intermediate `u128` for realistic prices
+pub const MAX_DECIMALS: u8 = 18;
+
+/// The precision of `ITypes.AssetPrice`, whatever the result itself is published with
+pub const ASSET_PRICE_DECIMALS: u8 = MAX_DECIMALS;
+
+/// Converts `price` to an integer scaled by `10^decimals`, rounding to the nearest unit.
+/// `None` if the price is negative, not finite, or too large for the intermediate `u128`.
+pub fn to_fixed_point(price: f64, decimals: u8) -> Option<U256> {
+    let scaled = (price * 10f64.powi(decimals.min(MAX_DECIMALS) as i32)).round();
+    if !(0.0..u128::MAX as f64).contains(&scaled) {
+        return None;
+    }
+    Some(U256::from(scaled as u128))
+}
+
+pub fn from_fixed_point(price: U256, decimals: u8) -> f64 {
+    f64::from(price) / 10f64.powi(decimals as i32)
+}
+
+/// Chainlink proxies put the aggregator's phase in the upper 16 bits of a round ID.
+/// There's only ever one phase here.
+pub const ROUND_PHASE: u16 = 1;
+
+/// The Chainlink round ID of the result for `trigger_id`, increasing with trigger IDs
+pub fn round_id(trigger_id: u64) -> U80 {
+    (U80::from(ROUND_PHASE) << 64) | U80::from(trigger_id)
+}
+
+/// The trigger ID a [`round_id`] was made from
+pub fn round_trigger_id(round_id: U80) -> u64 {
+    round_id.as_limbs()[0]
+}
+
+/// `SimpleSubmit.assetKey`, what the latest price of an asset in a quote currency and mode is
+/// kept under
+pub fn asset_key(asset_id: u64, quote: &str, mode: PriceMode) -> B256 {
+    let mode = solidity::PriceMode::from(mode) as u8;
+    keccak256(<(Uint<64>, sol_data::String, Uint<8>)>::abi_encode_params(&(
+        asset_id,
+        quote.to_ascii_uppercase(),
+        mode,
+    )))
+}
+
+/// `SimpleSubmit.feedKey`, the Chainlink feed a round is kept in: an asset's price in a quote
+/// currency and mode, with `decimals` so that every round of a feed is scaled alike
+pub fn feed_key(asset_id: u64, quote: &str, mode: PriceMode, decimals: u8) -> B256 {
+    keccak256(<(sol_data::FixedBytes<32>, Uint<8>)>::abi_encode_params(&(
+        asset_key(asset_id, quote, mode),
+        decimals,
+    )))
+}
+
+/// `SimpleSubmit.requestHash`, published with every Ethereum result so it can be checked against
+/// the trigger contract's `getTrigger` record
+pub fn request_hash(chain_id: u64, trigger_contract: Address, trigger_info: &TriggerInfo) -> B256 {
+    keccak256((U256::from(chain_id), trigger_contract, trigger_info.clone()).abi_encode_params())
+}
+
+impl From<PriceMode> for solidity::PriceMode {
+    fn from(mode: PriceMode) -> Self {
+        match mode {
+            PriceMode::Spot => solidity::PriceMode::Spot,
+            PriceMode::Twap => solidity::PriceMode::Twap,
+            PriceMode::Ohlc => solidity::PriceMode::Ohlc,
+        }
+    }
+}
+
+impl From<solidity::PriceMode> for PriceMode {
+    fn from(mode: solidity::PriceMode) -> Self {
+        match mode {
+            solidity::PriceMode::Twap => PriceMode::Twap,
+            solidity::PriceMode::Ohlc => PriceMode::Ohlc,
+            _ => PriceMode::Spot,
+        }
+    }
+}
+
+mod solidity {
+    use alloy_sol_macro::sol;
+    pub use ITypes::*;
+
+    sol!("../src/interfaces/ITypes.sol");
+}
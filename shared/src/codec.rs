@@ -0,0 +1,265 @@
+//! How price feed results are written to `DataWithId.data` and read back. The component, the
+//! frontend and any CLI all go through here, so they agree on every byte.
+
+use crate::{
+    abi::ResultEncoding,
+    price_feed::{
+        CircuitBreakerCheck, MarketData, Ohlc, PriceFeedData, PriceFeedOutput, ReferenceCheck,
+    },
+    request::{AssetSelection, OutputEncoding, PriceMode},
+};
+use alloy_primitives::B256;
+use alloy_sol_types::{sol_data::Array, SolType, SolValue};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// An encoding that keeps every field of a [`PriceFeedOutput`]. ABI results are fixed-point
+/// instead and written with [`encode_abi`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedCodec {
+    Json,
+    /// RFC 8949, with field names but a binary layout
+    Cbor,
+    /// bincode with variable-length integers, no field names and nothing left out, so reading
+    /// it needs this exact layout (see `CompactOutput`)
+    Bincode,
+}
+
+impl FeedCodec {
+    pub const ALL: [FeedCodec; 3] = [FeedCodec::Json, FeedCodec::Cbor, FeedCodec::Bincode];
+
+    pub fn encode(self, output: &PriceFeedOutput) -> Result<Vec<u8>, CodecError> {
+        match self {
+            FeedCodec::Json => {
+                serde_json::to_vec(output).map_err(|e| CodecError::Json(e.to_string()))
+            }
+            FeedCodec::Cbor => {
+                let mut data = Vec::new();
+                ciborium::into_writer(output, &mut data)
+                    .map_err(|e| CodecError::Cbor(e.to_string()))?;
+                Ok(data)
+            }
+            FeedCodec::Bincode => bincode_options()
+                .serialize(&CompactOutput::try_from(output)?)
+                .map_err(|e| CodecError::Bincode(e.to_string())),
+        }
+    }
+
+    pub fn decode(self, data: &[u8]) -> Result<PriceFeedOutput, CodecError> {
+        match self {
+            FeedCodec::Json => {
+                serde_json::from_slice(data).map_err(|e| CodecError::Json(e.to_string()))
+            }
+            FeedCodec::Cbor => {
+                ciborium::from_reader(data).map_err(|e| CodecError::Cbor(e.to_string()))
+            }
+            FeedCodec::Bincode => bincode_options()
+                .deserialize::<CompactOutput>(data)
+                .map(PriceFeedOutput::from)
+                .map_err(|e| CodecError::Bincode(e.to_string())),
+        }
+    }
+}
+
+impl OutputEncoding {
+    /// `None` for the fixed-point ABI encodings
+    pub fn feed_codec(&self) -> Option<FeedCodec> {
+        match self {
+            OutputEncoding::Json => Some(FeedCodec::Json),
+            OutputEncoding::Cbor => Some(FeedCodec::Cbor),
+            OutputEncoding::Bincode => Some(FeedCodec::Bincode),
+            OutputEncoding::Abi | OutputEncoding::Chainlink => None,
+        }
+    }
+
+    /// What `SimpleSubmit` records for a price feed result in this encoding
+    pub fn result_encoding(&self, assets: &AssetSelection) -> ResultEncoding {
+        let single = matches!(assets, AssetSelection::Single(_));
+        match self {
+            OutputEncoding::Json => ResultEncoding::Json,
+            OutputEncoding::Cbor => ResultEncoding::Cbor,
+            OutputEncoding::Bincode => ResultEncoding::Bincode,
+            OutputEncoding::Abi if single => ResultEncoding::PriceFeed,
+            OutputEncoding::Abi => ResultEncoding::PriceFeeds,
+            OutputEncoding::Chainlink if single => ResultEncoding::RoundData,
+            OutputEncoding::Chainlink => ResultEncoding::RoundDatas,
+        }
+    }
+}
+
+/// Encodes one value per requested asset as Solidity ABI: the value itself for a single asset,
+/// an array for a batch. Used for `ITypes.PriceFeed` and `ITypes.RoundData`.
+pub fn encode_abi<T: SolValue>(mut values: Vec<T>, assets: &AssetSelection) -> Vec<u8> {
+    match assets {
+        AssetSelection::Single(_) => values.remove(0).abi_encode(),
+        AssetSelection::Batch(_) => values.abi_encode(),
+    }
+}
+
+/// The inverse of [`encode_abi`]
+pub fn decode_abi<T>(data: &[u8], assets: &AssetSelection) -> Result<Vec<T>, CodecError>
+where
+    T: SolValue + From<<T::SolType as SolType>::RustType>,
+{
+    let res = match assets {
+        AssetSelection::Single(_) => T::abi_decode(data, true).map(|value| vec![value]),
+        AssetSelection::Batch(_) => <Array<T::SolType>>::abi_decode(data, true)
+            .map(|values| values.into_iter().map(T::from).collect()),
+    };
+    res.map_err(|e| CodecError::Abi(e.to_string()))
+}
+
+/// The varint encoding keeps small numbers (timestamps, block heights) small
+fn bincode_options() -> impl Options {
+    bincode::DefaultOptions::new().reject_trailing_bytes()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum CodecError {
+    Json(String),
+    Cbor(String),
+    Bincode(String),
+    Abi(String),
+    /// A `tx_hash` that isn't 32 bytes of hex, which the compact encoding can't hold
+    InvalidTxHash(String),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CodecError::Json(e) => write!(f, "Invalid JSON result: {}", e),
+            CodecError::Cbor(e) => write!(f, "Invalid CBOR result: {}", e),
+            CodecError::Bincode(e) => write!(f, "Invalid bincode result: {}", e),
+            CodecError::Abi(e) => write!(f, "Invalid ABI result: {}", e),
+            CodecError::InvalidTxHash(hash) => write!(f, "Invalid transaction hash: {}", hash),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {}
+
+/// [`PriceFeedOutput`] as bincode can read it back: externally tagged, since bincode doesn't
+/// describe itself enough for untagged enums, flattened fields or fields left out
+#[derive(Serialize, Deserialize)]
+enum CompactOutput {
+    Single(Box<CompactFeed>),
+    Batch(Vec<CompactFeed>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct CompactFeed {
+    symbol: String,
+    timestamp: u64,
+    price: f64,
+    sources: Vec<String>,
+    block_height: Option<u64>,
+    tx_hash: Option<[u8; 32]>,
+    mode: PriceMode,
+    window: Option<u64>,
+    ohlc: Option<Ohlc>,
+    market: CompactMarket,
+    reference: Option<ReferenceCheck>,
+    circuit_breaker: Option<CircuitBreakerCheck>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CompactMarket {
+    market_cap: Option<f64>,
+    volume_24h: Option<f64>,
+    percent_change_24h: Option<f64>,
+    circulating_supply: Option<f64>,
+    total_supply: Option<f64>,
+    rank: Option<u32>,
+}
+
+impl TryFrom<&PriceFeedOutput> for CompactOutput {
+    type Error = CodecError;
+
+    fn try_from(output: &PriceFeedOutput) -> Result<Self, CodecError> {
+        Ok(match output {
+            PriceFeedOutput::Single(feed) => {
+                CompactOutput::Single(Box::new(CompactFeed::try_from(&**feed)?))
+            }
+            PriceFeedOutput::Batch(feeds) => CompactOutput::Batch(
+                feeds.iter().map(CompactFeed::try_from).collect::<Result<_, _>>()?,
+            ),
+        })
+    }
+}
+
+impl From<CompactOutput> for PriceFeedOutput {
+    fn from(output: CompactOutput) -> Self {
+        match output {
+            CompactOutput::Single(feed) => PriceFeedOutput::Single(Box::new((*feed).into())),
+            CompactOutput::Batch(feeds) => {
+                PriceFeedOutput::Batch(feeds.into_iter().map(PriceFeedData::from).collect())
+            }
+        }
+    }
+}
+
+impl TryFrom<&PriceFeedData> for CompactFeed {
+    type Error = CodecError;
+
+    fn try_from(feed: &PriceFeedData) -> Result<Self, CodecError> {
+        let tx_hash = feed
+            .tx_hash
+            .as_ref()
+            .map(|hash| {
+                hash.parse::<B256>()
+                    .map(|hash| hash.0)
+                    .map_err(|_| CodecError::InvalidTxHash(hash.clone()))
+            })
+            .transpose()?;
+        let market = feed.market.clone();
+        Ok(Self {
+            symbol: feed.symbol.clone(),
+            timestamp: feed.timestamp,
+            price: feed.price,
+            sources: feed.sources.clone(),
+            block_height: feed.block_height,
+            tx_hash,
+            mode: feed.mode,
+            window: feed.window,
+            ohlc: feed.ohlc,
+            market: CompactMarket {
+                market_cap: market.market_cap,
+                volume_24h: market.volume_24h,
+                percent_change_24h: market.percent_change_24h,
+                circulating_supply: market.circulating_supply,
+                total_supply: market.total_supply,
+                rank: market.rank,
+            },
+            reference: feed.reference.clone(),
+            circuit_breaker: feed.circuit_breaker.clone(),
+        })
+    }
+}
+
+impl From<CompactFeed> for PriceFeedData {
+    fn from(feed: CompactFeed) -> Self {
+        let market = feed.market;
+        Self {
+            symbol: feed.symbol,
+            timestamp: feed.timestamp,
+            price: feed.price,
+            sources: feed.sources,
+            block_height: feed.block_height,
+            tx_hash: feed.tx_hash.map(|hash| B256::from(hash).to_string()),
+            mode: feed.mode,
+            window: feed.window,
+            ohlc: feed.ohlc,
+            market: MarketData {
+                market_cap: market.market_cap,
+                volume_24h: market.volume_24h,
+                percent_change_24h: market.percent_change_24h,
+                circulating_supply: market.circulating_supply,
+                total_supply: market.total_supply,
+                rank: market.rank,
+            },
+            reference: feed.reference,
+            circuit_breaker: feed.circuit_breaker,
+        }
+    }
+}
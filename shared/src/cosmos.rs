@@ -0,0 +1,66 @@
+//! Price requests and results on Cosmos chains, where the trigger is a CosmWasm event
+//! instead of the `NewTrigger` log.
+
+use crate::error::OracleError;
+use alloy_primitives::Bytes;
+use serde::{Deserialize, Serialize};
+
+/// Type of the event a contract emits to request a price.
+/// CosmWasm prefixes the event type set by the contract (`new-trigger`) with `wasm-`.
+pub const NEW_TRIGGER_EVENT: &str = "wasm-new-trigger";
+
+/// Attribute of that event holding the contract's ID for the request
+pub const TRIGGER_ID_ATTRIBUTE: &str = "trigger_id";
+
+/// What the component returns for a Cosmos trigger, the counterpart of `ITypes.DataWithId`.
+///
+/// Serialized as JSON for a CosmWasm contract to store, e.g.
+/// `{"trigger_id":"7","data":"0x7b2273...","error":null}`. `trigger_id` is a string like
+/// CosmWasm's `Uint64`. On failure `error` is the [`OracleError::kind`] and `data` the message.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CosmosTriggerResult {
+    #[serde(with = "u64_string")]
+    pub trigger_id: u64,
+    pub data: Bytes,
+    pub error: Option<String>,
+}
+
+impl CosmosTriggerResult {
+    pub fn new(trigger_id: u64, result: Result<Vec<u8>, OracleError>) -> Self {
+        match result {
+            Ok(data) => Self { trigger_id, data: data.into(), error: None },
+            Err(e) => Self {
+                trigger_id,
+                data: e.message().as_bytes().to_vec().into(),
+                error: Some(e.kind().to_string()),
+            },
+        }
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("CosmosTriggerResult is always serializable")
+    }
+
+    /// The output of a successful run, or the error it published
+    pub fn into_result(self) -> Result<Vec<u8>, OracleError> {
+        let Some(kind) = self.error else {
+            return Ok(self.data.to_vec());
+        };
+        let message = String::from_utf8_lossy(&self.data).into_owned();
+        // a kind this build doesn't know yet is still a failure
+        Err(OracleError::from_kind(&kind, message.clone())
+            .unwrap_or_else(|| OracleError::BadInput(format!("{}: {}", kind, message))))
+    }
+}
+
+mod u64_string {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(value: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,224 @@
+//! Requests of the `erc20-snapshot` component, which publishes token balances read on-chain
+//! as an `ITypes.TokenSnapshot`
+
+use crate::{
+    error::OracleError,
+    request::{attribute, parse_attribute, RequestError, TriggerRequest},
+};
+use alloy_primitives::Address;
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// The only request version understood so far
+pub const SNAPSHOT_REQUEST_VERSION: u32 = 1;
+
+/// Upper bound on the holders in one snapshot, each is a separate `eth_call`
+pub const MAX_HOLDERS: usize = 64;
+
+/// Payload of a `NewTrigger` asking for a snapshot, e.g.
+/// `{"version":1,"token":"0x…","holders":["0x…","0x…"]}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotRequest {
+    pub version: u32,
+    /// Name of the chain in the WAVS config, the service config's `chain` if left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chain: Option<String>,
+    pub token: Address,
+    /// May be empty to only read the total supply
+    #[serde(default)]
+    pub holders: Vec<Address>,
+    /// The trigger's block if left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block: Option<BlockTag>,
+}
+
+/// The block a snapshot is read at. Operators only sign the same snapshot if they read the
+/// same block, which `latest`, `safe` and `finalized` don't guarantee.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "BlockTagRepr", into = "BlockTagRepr")]
+pub enum BlockTag {
+    Latest,
+    Safe,
+    Finalized,
+    Number(u64),
+}
+
+/// A number, or a name or hex number in a string
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum BlockTagRepr {
+    Number(u64),
+    Text(String),
+}
+
+impl FromStr for BlockTag {
+    type Err = SnapshotRequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim().to_ascii_lowercase();
+        match s.as_str() {
+            "latest" => Ok(BlockTag::Latest),
+            "safe" => Ok(BlockTag::Safe),
+            "finalized" => Ok(BlockTag::Finalized),
+            _ => match s.strip_prefix("0x") {
+                Some(hex) => u64::from_str_radix(hex, 16).ok(),
+                None => s.parse().ok(),
+            }
+            .map(BlockTag::Number)
+            .ok_or(SnapshotRequestError::InvalidBlock(s)),
+        }
+    }
+}
+
+impl TryFrom<BlockTagRepr> for BlockTag {
+    type Error = SnapshotRequestError;
+
+    fn try_from(repr: BlockTagRepr) -> Result<Self, Self::Error> {
+        match repr {
+            BlockTagRepr::Number(number) => Ok(BlockTag::Number(number)),
+            BlockTagRepr::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl From<BlockTag> for BlockTagRepr {
+    fn from(tag: BlockTag) -> Self {
+        match tag {
+            BlockTag::Number(number) => BlockTagRepr::Number(number),
+            tag => BlockTagRepr::Text(tag.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for BlockTag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BlockTag::Latest => write!(f, "latest"),
+            BlockTag::Safe => write!(f, "safe"),
+            BlockTag::Finalized => write!(f, "finalized"),
+            BlockTag::Number(number) => write!(f, "{}", number),
+        }
+    }
+}
+
+impl SnapshotRequest {
+    pub fn new(token: Address, holders: Vec<Address>) -> Self {
+        Self { version: SNAPSHOT_REQUEST_VERSION, chain: None, token, holders, block: None }
+    }
+
+    pub fn with_chain(mut self, chain: impl ToString) -> Self {
+        self.chain = Some(chain.to_string());
+        self
+    }
+
+    pub fn at_block(mut self, block: BlockTag) -> Self {
+        self.block = Some(block);
+        self
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("SnapshotRequest is always serializable")
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, SnapshotRequestError> {
+        // `cast format-bytes32-string` pads with zeroes
+        let input = std::str::from_utf8(bytes)
+            .map_err(|_| RequestError::InvalidUtf8)?
+            .trim_end_matches('\0')
+            .trim();
+
+        if input.is_empty() {
+            return Err(RequestError::Empty.into());
+        }
+
+        let req: Self =
+            serde_json::from_str(input).map_err(|e| RequestError::Json(e.to_string()))?;
+        req.validate()
+    }
+
+    /// A `request` attribute holds an encoded request like [`Self::decode`] takes. Otherwise the
+    /// request is made of `chain`, `token`, comma-separated `holders`, and `block`.
+    pub fn from_attributes<'a>(
+        attributes: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, SnapshotRequestError> {
+        let attributes: Vec<(&str, &str)> = attributes.into_iter().collect();
+        let get = |key: &str| attribute(&attributes, key);
+
+        if let Some(request) = get("request") {
+            return Self::decode(request.as_bytes());
+        }
+
+        Self {
+            version: SNAPSHOT_REQUEST_VERSION,
+            chain: get("chain").map(str::to_string),
+            token: parse_attribute(&attributes, "token")?.ok_or(RequestError::Empty)?,
+            holders: get("holders")
+                .filter(|holders| !holders.is_empty())
+                .map(|holders| holders.split(',').map(|holder| holder.trim().parse()).collect())
+                .transpose()
+                .map_err(|_| RequestError::InvalidAttribute("holders".to_string()))?
+                .unwrap_or_default(),
+            block: parse_attribute(&attributes, "block")?,
+        }
+        .validate()
+    }
+
+    fn validate(self) -> Result<Self, SnapshotRequestError> {
+        if self.version != SNAPSHOT_REQUEST_VERSION {
+            return Err(RequestError::UnsupportedVersion(self.version).into());
+        }
+        if self.holders.len() > MAX_HOLDERS {
+            return Err(SnapshotRequestError::InvalidHolderCount(self.holders.len()));
+        }
+        Ok(self)
+    }
+}
+
+impl TriggerRequest for SnapshotRequest {
+    type Error = SnapshotRequestError;
+
+    fn decode(bytes: &[u8]) -> Result<Self, SnapshotRequestError> {
+        SnapshotRequest::decode(bytes)
+    }
+
+    fn from_attributes<'a>(
+        attributes: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, SnapshotRequestError> {
+        SnapshotRequest::from_attributes(attributes)
+    }
+}
+
+/// Why a [`SnapshotRequest`] is invalid
+#[derive(Debug, Clone, PartialEq)]
+pub enum SnapshotRequestError {
+    Request(RequestError),
+    InvalidHolderCount(usize),
+    /// Neither a block number nor `latest`, `safe` or `finalized`
+    InvalidBlock(String),
+}
+
+impl From<RequestError> for SnapshotRequestError {
+    fn from(e: RequestError) -> Self {
+        SnapshotRequestError::Request(e)
+    }
+}
+
+impl From<SnapshotRequestError> for OracleError {
+    fn from(e: SnapshotRequestError) -> Self {
+        OracleError::BadInput(e.to_string())
+    }
+}
+
+impl fmt::Display for SnapshotRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotRequestError::Request(e) => e.fmt(f),
+            SnapshotRequestError::InvalidHolderCount(n) => {
+                write!(f, "Snapshot can hold at most {} holders, got {}", MAX_HOLDERS, n)
+            }
+            SnapshotRequestError::InvalidBlock(block) => write!(f, "Invalid block: {}", block),
+        }
+    }
+}
+
+impl std::error::Error for SnapshotRequestError {}
@@ -0,0 +1,119 @@
+use crate::{abi::ErrorCode, request::RequestError};
+use std::fmt;
+
+/// Why an oracle run failed.
+/// Published on-chain as `DataWithId.error` with the message as `DataWithId.data`, so every
+/// operator must produce the same message for the same failure: no raw upstream errors.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OracleError {
+    BadInput(String),
+    UpstreamUnavailable(String),
+    ParseFailure(String),
+    StaleData(String),
+    OutOfRange(String),
+    /// The request's `kind` isn't one the component answers
+    UnsupportedKind(String),
+}
+
+impl OracleError {
+    /// Rebuilds the error from what was published, `None` if the run succeeded
+    pub fn from_parts(code: ErrorCode, message: String) -> Option<Self> {
+        match code {
+            ErrorCode::BadInput => Some(OracleError::BadInput(message)),
+            ErrorCode::UpstreamUnavailable => Some(OracleError::UpstreamUnavailable(message)),
+            ErrorCode::ParseFailure => Some(OracleError::ParseFailure(message)),
+            ErrorCode::StaleData => Some(OracleError::StaleData(message)),
+            ErrorCode::OutOfRange => Some(OracleError::OutOfRange(message)),
+            ErrorCode::UnsupportedKind => Some(OracleError::UnsupportedKind(message)),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            OracleError::BadInput(_) => ErrorCode::BadInput,
+            OracleError::UpstreamUnavailable(_) => ErrorCode::UpstreamUnavailable,
+            OracleError::ParseFailure(_) => ErrorCode::ParseFailure,
+            OracleError::StaleData(_) => ErrorCode::StaleData,
+            OracleError::OutOfRange(_) => ErrorCode::OutOfRange,
+            OracleError::UnsupportedKind(_) => ErrorCode::UnsupportedKind,
+        }
+    }
+
+    /// Name of the error code, e.g. `bad_input`, for outputs that aren't Solidity ABI
+    pub fn kind(&self) -> &'static str {
+        match self {
+            OracleError::BadInput(_) => "bad_input",
+            OracleError::UpstreamUnavailable(_) => "upstream_unavailable",
+            OracleError::ParseFailure(_) => "parse_failure",
+            OracleError::StaleData(_) => "stale_data",
+            OracleError::OutOfRange(_) => "out_of_range",
+            OracleError::UnsupportedKind(_) => "unsupported_kind",
+        }
+    }
+
+    /// The inverse of [`Self::kind`], `None` for an unknown kind
+    pub fn from_kind(kind: &str, message: String) -> Option<Self> {
+        match kind {
+            "bad_input" => Some(OracleError::BadInput(message)),
+            "upstream_unavailable" => Some(OracleError::UpstreamUnavailable(message)),
+            "parse_failure" => Some(OracleError::ParseFailure(message)),
+            "stale_data" => Some(OracleError::StaleData(message)),
+            "out_of_range" => Some(OracleError::OutOfRange(message)),
+            "unsupported_kind" => Some(OracleError::UnsupportedKind(message)),
+            _ => None,
+        }
+    }
+
+    pub fn message(&self) -> &str {
+        match self {
+            OracleError::BadInput(message)
+            | OracleError::UpstreamUnavailable(message)
+            | OracleError::ParseFailure(message)
+            | OracleError::StaleData(message)
+            | OracleError::OutOfRange(message)
+            | OracleError::UnsupportedKind(message) => message,
+        }
+    }
+
+    /// Prefixes the message, e.g. with the asset it's about
+    pub fn context(self, context: impl fmt::Display) -> Self {
+        let prefix = |message: String| format!("{}: {}", context, message);
+        match self {
+            OracleError::BadInput(message) => OracleError::BadInput(prefix(message)),
+            OracleError::UpstreamUnavailable(message) => {
+                OracleError::UpstreamUnavailable(prefix(message))
+            }
+            OracleError::ParseFailure(message) => OracleError::ParseFailure(prefix(message)),
+            OracleError::StaleData(message) => OracleError::StaleData(prefix(message)),
+            OracleError::OutOfRange(message) => OracleError::OutOfRange(prefix(message)),
+            OracleError::UnsupportedKind(message) => OracleError::UnsupportedKind(prefix(message)),
+        }
+    }
+
+    /// Short description of the kind of failure, for showing to users
+    pub fn summary(&self) -> &'static str {
+        match self {
+            OracleError::BadInput(_) => "invalid request",
+            OracleError::UpstreamUnavailable(_) => "price source unavailable",
+            OracleError::ParseFailure(_) => "unexpected price source response",
+            OracleError::StaleData(_) => "price data is stale",
+            OracleError::OutOfRange(_) => "price out of range",
+            OracleError::UnsupportedKind(_) => "unsupported request kind",
+        }
+    }
+}
+
+impl From<RequestError> for OracleError {
+    fn from(e: RequestError) -> Self {
+        OracleError::BadInput(e.to_string())
+    }
+}
+
+impl fmt::Display for OracleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.summary(), self.message())
+    }
+}
+
+impl std::error::Error for OracleError {}
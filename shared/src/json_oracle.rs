@@ -0,0 +1,342 @@
+//! Requests and results of the `json-oracle` component, which publishes values picked out of
+//! a JSON API response by path
+
+use crate::{
+    abi::MAX_DECIMALS,
+    error::OracleError,
+    request::{attribute, parse_attribute, RequestError, TriggerRequest},
+};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// The only request version understood so far
+pub const JSON_REQUEST_VERSION: u32 = 1;
+
+/// Upper bound on the values picked from one response, to stay within the fuel limit
+pub const MAX_FIELDS: usize = 16;
+
+/// Payload of a `NewTrigger` asking the JSON oracle for values, e.g.
+/// `{"version":1,"url":"https://api.example.com/price","fields":[{"path":"$.data.price","type":"uint256","decimals":8}]}`.
+///
+/// Anything left out is taken from the service config, so an empty payload asks for a feed
+/// that is configured entirely there.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonRequest {
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<JsonField>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub encoding: Option<ValueEncoding>,
+}
+
+impl Default for JsonRequest {
+    fn default() -> Self {
+        Self { version: JSON_REQUEST_VERSION, url: None, fields: Vec::new(), encoding: None }
+    }
+}
+
+/// One value to publish
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonField {
+    pub path: JsonPath,
+    #[serde(rename = "type")]
+    pub ty: ValueType,
+    /// Decimal places numbers are scaled by, e.g. with 8 `2712.35` is published as `271235000000`
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub decimals: u8,
+}
+
+fn is_zero(decimals: &u8) -> bool {
+    *decimals == 0
+}
+
+/// The Solidity type a value is published as
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueType {
+    /// A number (or a string holding one), scaled by the field's decimals and rounded
+    Uint256,
+    /// Like `uint256`, but negative numbers are allowed
+    Int256,
+    Bool,
+    String,
+}
+
+/// How the values are encoded in `DataWithId.data`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueEncoding {
+    /// [`JsonOracleOutput`] as JSON
+    #[default]
+    Json,
+    /// The values in order as Solidity ABI parameters, for `abi.decode(data, (uint256, bool))`
+    Abi,
+}
+
+impl FromStr for ValueEncoding {
+    type Err = RequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(ValueEncoding::Json),
+            "abi" => Ok(ValueEncoding::Abi),
+            _ => Err(RequestError::InvalidAttribute("encoding".to_string())),
+        }
+    }
+}
+
+impl JsonRequest {
+    pub fn new(url: impl ToString, fields: Vec<JsonField>) -> Self {
+        Self { url: Some(url.to_string()), fields, ..Default::default() }
+    }
+
+    pub fn with_encoding(mut self, encoding: ValueEncoding) -> Self {
+        self.encoding = Some(encoding);
+        self
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("JsonRequest is always serializable")
+    }
+
+    /// An empty payload (after trimming `cast format-bytes32-string` padding) is a request with
+    /// nothing set
+    pub fn decode(bytes: &[u8]) -> Result<Self, JsonRequestError> {
+        let input = std::str::from_utf8(bytes)
+            .map_err(|_| RequestError::InvalidUtf8)?
+            .trim_end_matches('\0')
+            .trim();
+
+        if input.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let req: Self =
+            serde_json::from_str(input).map_err(|e| RequestError::Json(e.to_string()))?;
+        req.validate()
+    }
+
+    /// A `request` attribute holds an encoded request like [`Self::decode`] takes. Otherwise the
+    /// request is made of `url`, `encoding`, and `fields` as a JSON array.
+    pub fn from_attributes<'a>(
+        attributes: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, JsonRequestError> {
+        let attributes: Vec<(&str, &str)> = attributes.into_iter().collect();
+        let get = |key: &str| attribute(&attributes, key);
+
+        if let Some(request) = get("request") {
+            return Self::decode(request.as_bytes());
+        }
+
+        Self {
+            version: JSON_REQUEST_VERSION,
+            url: get("url").map(str::to_string),
+            fields: get("fields")
+                .map(serde_json::from_str)
+                .transpose()
+                .map_err(|_| RequestError::InvalidAttribute("fields".to_string()))?
+                .unwrap_or_default(),
+            encoding: parse_attribute(&attributes, "encoding")?,
+        }
+        .validate()
+    }
+
+    /// Fields may be missing here if the service config has them, so only the upper bound
+    /// is checked
+    fn validate(self) -> Result<Self, JsonRequestError> {
+        if self.version != JSON_REQUEST_VERSION {
+            return Err(RequestError::UnsupportedVersion(self.version).into());
+        }
+        if self.fields.len() > MAX_FIELDS {
+            return Err(JsonRequestError::InvalidFieldCount(self.fields.len()));
+        }
+        for field in &self.fields {
+            field.validate()?;
+        }
+        Ok(self)
+    }
+}
+
+impl TriggerRequest for JsonRequest {
+    type Error = JsonRequestError;
+
+    fn decode(bytes: &[u8]) -> Result<Self, JsonRequestError> {
+        JsonRequest::decode(bytes)
+    }
+
+    fn from_attributes<'a>(
+        attributes: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, JsonRequestError> {
+        JsonRequest::from_attributes(attributes)
+    }
+}
+
+impl JsonField {
+    pub fn new(path: &str, ty: ValueType) -> Result<Self, JsonRequestError> {
+        Ok(Self { path: path.parse()?, ty, decimals: 0 })
+    }
+
+    pub fn with_decimals(mut self, decimals: u8) -> Self {
+        self.decimals = decimals;
+        self
+    }
+
+    pub fn validate(&self) -> Result<(), JsonRequestError> {
+        if self.decimals > MAX_DECIMALS {
+            return Err(RequestError::InvalidDecimals(self.decimals).into());
+        }
+        Ok(())
+    }
+}
+
+/// A path into a JSON document, like `$.data[0].price` or `$["key.with.dots"]`.
+/// The leading `$.` may be left out, e.g. `data.price`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct JsonPath {
+    text: String,
+    segments: Vec<PathSegment>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum PathSegment {
+    Key(String),
+    Index(usize),
+}
+
+impl JsonPath {
+    /// The value at the path, `None` if a key or index along it is missing
+    pub fn select<'a>(&self, value: &'a serde_json::Value) -> Option<&'a serde_json::Value> {
+        self.segments.iter().try_fold(value, |value, segment| match segment {
+            PathSegment::Key(key) => value.get(key),
+            PathSegment::Index(index) => value.get(index),
+        })
+    }
+}
+
+impl FromStr for JsonPath {
+    type Err = JsonRequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let text = s.trim().to_string();
+        let invalid = || JsonRequestError::InvalidPath(text.clone());
+
+        let mut rest = match text.strip_prefix('$') {
+            Some(rest) => rest,
+            // a bare first key, as if it followed `$.`
+            None if !text.starts_with(['.', '[']) => &text,
+            None => return Err(invalid()),
+        };
+        let mut segments = Vec::new();
+        let mut needs_dot = text.starts_with('$');
+
+        while !rest.is_empty() {
+            if let Some(bracketed) = rest.strip_prefix('[') {
+                let (inner, after) = bracketed.split_once(']').ok_or_else(invalid)?;
+                let segment = match inner.chars().next() {
+                    Some(quote @ ('"' | '\'')) => {
+                        let key = inner[1..].strip_suffix(quote).ok_or_else(invalid)?;
+                        PathSegment::Key(key.to_string())
+                    }
+                    _ => PathSegment::Index(inner.parse().map_err(|_| invalid())?),
+                };
+                segments.push(segment);
+                rest = after;
+            } else {
+                let key = match rest.strip_prefix('.') {
+                    Some(key) => key,
+                    None if !needs_dot => rest,
+                    None => return Err(invalid()),
+                };
+                let end = key.find(['.', '[']).unwrap_or(key.len());
+                if end == 0 {
+                    return Err(invalid());
+                }
+                segments.push(PathSegment::Key(key[..end].to_string()));
+                rest = &key[end..];
+            }
+            needs_dot = true;
+        }
+
+        Ok(Self { text, segments })
+    }
+}
+
+impl TryFrom<String> for JsonPath {
+    type Error = JsonRequestError;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<JsonPath> for String {
+    fn from(path: JsonPath) -> Self {
+        path.text
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.text)
+    }
+}
+
+/// Result of a JSON oracle run with `"encoding": "json"`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonOracleOutput {
+    pub url: String,
+    /// When the trigger's block was made (when the response was fetched for a CLI run), in unix
+    /// seconds
+    pub timestamp: u64,
+    /// In the order of the request's fields
+    pub values: Vec<JsonOracleValue>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct JsonOracleValue {
+    pub path: String,
+    #[serde(rename = "type")]
+    pub ty: ValueType,
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub decimals: u8,
+    /// Numbers are scaled integers in a string, since they can exceed what JSON numbers hold
+    pub value: serde_json::Value,
+}
+
+/// Why a [`JsonRequest`] is invalid
+#[derive(Debug, Clone, PartialEq)]
+pub enum JsonRequestError {
+    Request(RequestError),
+    /// A JSON path the JSON oracle can't follow
+    InvalidPath(String),
+    InvalidFieldCount(usize),
+}
+
+impl From<RequestError> for JsonRequestError {
+    fn from(e: RequestError) -> Self {
+        JsonRequestError::Request(e)
+    }
+}
+
+impl From<JsonRequestError> for OracleError {
+    fn from(e: JsonRequestError) -> Self {
+        OracleError::BadInput(e.to_string())
+    }
+}
+
+impl fmt::Display for JsonRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonRequestError::Request(e) => e.fmt(f),
+            JsonRequestError::InvalidPath(path) => write!(f, "Invalid JSON path: {}", path),
+            JsonRequestError::InvalidFieldCount(n) => {
+                write!(f, "Request must pick 1 to {} fields, got {}", MAX_FIELDS, n)
+            }
+        }
+    }
+}
+
+impl std::error::Error for JsonRequestError {}
@@ -1 +1,9 @@
-pub mod price_feed;
\ No newline at end of file
+pub mod abi;
+pub mod codec;
+pub mod cosmos;
+pub mod erc20_snapshot;
+pub mod error;
+pub mod json_oracle;
+pub mod price_feed;
+pub mod relay;
+pub mod request;
@@ -1,8 +1,157 @@
+use crate::request::{MarketField, PriceMode};
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
 pub struct PriceFeedData {
     pub symbol: String,
-    pub timestamp: String,
+    /// When the price was last updated upstream, in unix seconds
+    pub timestamp: u64,
+    /// The spot price, the TWAP, or the close, depending on `mode`
+    pub price: f64,
+    /// Names of the upstream sources whose quotes were used for `price`
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// Block of the `NewTrigger` log this answers, if it came from one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_height: Option<u64>,
+    /// Transaction that emitted that log, as 0x-prefixed hex
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "PriceMode::is_spot")]
+    pub mode: PriceMode,
+    /// Seconds of history behind a `twap` or `ohlc` price, ending at `timestamp`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ohlc: Option<Ohlc>,
+    /// Only the fields the request asked for, and that a source reported
+    #[serde(flatten)]
+    pub market: MarketData,
+    /// Only if an on-chain reference is configured for the asset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference: Option<ReferenceCheck>,
+    /// Only if the circuit breaker is configured and found an earlier result for the asset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circuit_breaker: Option<CircuitBreakerCheck>,
+}
+
+/// How the price compares to an on-chain reference
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReferenceCheck {
+    /// `chainlink` or `uniswap_v3`
+    pub source: String,
+    pub price: f64,
+    /// Distance of the published price from `price`
+    pub deviation_bps: u32,
+    /// Further than the operator allows, published anyway because the check only flags
+    pub flagged: bool,
+}
+
+/// Market figures in the quote currency, the median of each across the sources that report it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MarketData {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub market_cap: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub volume_24h: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub percent_change_24h: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub circulating_supply: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_supply: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rank: Option<u32>,
+}
+
+impl MarketData {
+    /// Keeps only `fields`
+    pub fn select(self, fields: &[MarketField]) -> Self {
+        let keep = |field: MarketField| fields.contains(&field);
+        Self {
+            market_cap: self.market_cap.filter(|_| keep(MarketField::MarketCap)),
+            volume_24h: self.volume_24h.filter(|_| keep(MarketField::Volume24h)),
+            percent_change_24h: self
+                .percent_change_24h
+                .filter(|_| keep(MarketField::PercentChange24h)),
+            circulating_supply: self
+                .circulating_supply
+                .filter(|_| keep(MarketField::CirculatingSupply)),
+            total_supply: self.total_supply.filter(|_| keep(MarketField::TotalSupply)),
+            rank: self.rank.filter(|_| keep(MarketField::Rank)),
+        }
+    }
+}
+
+/// Open, high, low and close over a window, the median of each across sources
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Ohlc {
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+}
+
+/// How the price compares to the previous result for the same asset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CircuitBreakerCheck {
+    /// The trigger the previous result answered
+    pub trigger_id: u64,
     pub price: f64,
-}
\ No newline at end of file
+    /// Change from `price`, rounded to hundredths of a percent
+    pub change_percent: f64,
+    /// Larger than the operator allows, published anyway because the breaker only flags
+    pub flagged: bool,
+}
+
+/// What the oracle publishes: a single feed, or one feed per asset of a batch request (in request order)
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PriceFeedOutput {
+    Single(Box<PriceFeedData>),
+    Batch(Vec<PriceFeedData>),
+}
+
+impl PriceFeedOutput {
+    pub fn into_vec(self) -> Vec<PriceFeedData> {
+        match self {
+            PriceFeedOutput::Single(data) => vec![*data],
+            PriceFeedOutput::Batch(data) => data,
+        }
+    }
+}
+
+/// Market data without a price, what a `metadata` request publishes
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AssetMetadata {
+    pub symbol: String,
+    /// When the data was last updated upstream, in unix seconds
+    pub timestamp: u64,
+    /// Names of the upstream sources whose quotes were used
+    #[serde(default)]
+    pub sources: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_height: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tx_hash: Option<String>,
+    /// Every field a source reported, unless the request selected some
+    #[serde(flatten)]
+    pub market: MarketData,
+}
+
+/// What a `metadata` request publishes, shaped like [`PriceFeedOutput`]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MetadataOutput {
+    Single(Box<AssetMetadata>),
+    Batch(Vec<AssetMetadata>),
+}
+
+impl MetadataOutput {
+    pub fn into_vec(self) -> Vec<AssetMetadata> {
+        match self {
+            MetadataOutput::Single(data) => vec![*data],
+            MetadataOutput::Batch(data) => data,
+        }
+    }
+}
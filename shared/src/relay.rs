@@ -0,0 +1,141 @@
+//! Requests of the `cross-chain-relay` component, which publishes a `NewTrigger` payload from
+//! one chain as an `ITypes.RelayMessage` for a `SimpleSubmit` on another
+
+use crate::{
+    error::OracleError,
+    request::{attribute, RequestError, TriggerRequest},
+};
+use alloy_primitives::Bytes;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The only request version understood so far
+pub const RELAY_REQUEST_VERSION: u32 = 1;
+
+/// Upper bound on a relayed payload, it's stored on the destination chain
+pub const MAX_PAYLOAD_SIZE: usize = 4096;
+
+/// Payload of a `NewTrigger` to relay, e.g.
+/// `{"version":1,"destination":"local2","payload":"0x68656c6c6f"}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RelayRequest {
+    pub version: u32,
+    /// Name of the destination chain in the WAVS config, the service config's `destination`
+    /// if left out
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub destination: Option<String>,
+    /// Relayed as is
+    #[serde(default)]
+    pub payload: Bytes,
+}
+
+impl RelayRequest {
+    pub fn new(payload: impl Into<Bytes>) -> Self {
+        Self { version: RELAY_REQUEST_VERSION, destination: None, payload: payload.into() }
+    }
+
+    pub fn with_destination(mut self, destination: impl ToString) -> Self {
+        self.destination = Some(destination.to_string());
+        self
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("RelayRequest is always serializable")
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, RelayRequestError> {
+        // `cast format-bytes32-string` pads with zeroes
+        let input = std::str::from_utf8(bytes)
+            .map_err(|_| RequestError::InvalidUtf8)?
+            .trim_end_matches('\0')
+            .trim();
+
+        if input.is_empty() {
+            return Err(RequestError::Empty.into());
+        }
+
+        let req: Self =
+            serde_json::from_str(input).map_err(|e| RequestError::Json(e.to_string()))?;
+        req.validate()
+    }
+
+    /// A `request` attribute holds an encoded request like [`Self::decode`] takes. Otherwise the
+    /// request is made of `destination` and a hex `payload`.
+    pub fn from_attributes<'a>(
+        attributes: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, RelayRequestError> {
+        let attributes: Vec<(&str, &str)> = attributes.into_iter().collect();
+        let get = |key: &str| attribute(&attributes, key);
+
+        if let Some(request) = get("request") {
+            return Self::decode(request.as_bytes());
+        }
+
+        Self {
+            version: RELAY_REQUEST_VERSION,
+            destination: get("destination").map(str::to_string),
+            payload: get("payload")
+                .map(|payload| payload.parse())
+                .transpose()
+                .map_err(|_| RequestError::InvalidAttribute("payload".to_string()))?
+                .unwrap_or_default(),
+        }
+        .validate()
+    }
+
+    fn validate(self) -> Result<Self, RelayRequestError> {
+        if self.version != RELAY_REQUEST_VERSION {
+            return Err(RequestError::UnsupportedVersion(self.version).into());
+        }
+        if self.payload.len() > MAX_PAYLOAD_SIZE {
+            return Err(RelayRequestError::InvalidPayloadSize(self.payload.len()));
+        }
+        Ok(self)
+    }
+}
+
+impl TriggerRequest for RelayRequest {
+    type Error = RelayRequestError;
+
+    fn decode(bytes: &[u8]) -> Result<Self, RelayRequestError> {
+        RelayRequest::decode(bytes)
+    }
+
+    fn from_attributes<'a>(
+        attributes: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, RelayRequestError> {
+        RelayRequest::from_attributes(attributes)
+    }
+}
+
+/// Why a [`RelayRequest`] is invalid
+#[derive(Debug, Clone, PartialEq)]
+pub enum RelayRequestError {
+    Request(RequestError),
+    InvalidPayloadSize(usize),
+}
+
+impl From<RequestError> for RelayRequestError {
+    fn from(e: RequestError) -> Self {
+        RelayRequestError::Request(e)
+    }
+}
+
+impl From<RelayRequestError> for OracleError {
+    fn from(e: RelayRequestError) -> Self {
+        OracleError::BadInput(e.to_string())
+    }
+}
+
+impl fmt::Display for RelayRequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RelayRequestError::Request(e) => e.fmt(f),
+            RelayRequestError::InvalidPayloadSize(n) => {
+                write!(f, "Payload can be at most {} bytes, got {}", MAX_PAYLOAD_SIZE, n)
+            }
+        }
+    }
+}
+
+impl std::error::Error for RelayRequestError {}
@@ -0,0 +1,486 @@
+use crate::{abi::MAX_DECIMALS, error::OracleError};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// The only request version understood so far
+pub const PRICE_REQUEST_VERSION: u32 = 1;
+
+/// Upper bound on the number of assets in one batch request, to stay within the fuel limit
+pub const MAX_BATCH_SIZE: usize = 32;
+
+/// An `f64` can't hold more than this reliably
+pub const MAX_SIGNIFICANT_DIGITS: u32 = 15;
+
+/// Window of a `twap` or `ohlc` price if the request doesn't set one, in seconds
+pub const DEFAULT_WINDOW: u64 = 3600;
+
+/// Shorter windows hold too few upstream data points to average
+pub const MIN_WINDOW: u64 = 300;
+
+/// Longer windows need more history than the sources return in one response
+pub const MAX_WINDOW: u64 = 7 * 86_400;
+
+/// Kind of an untagged request: a price, with whatever the options ask for
+pub const DEFAULT_KIND: &str = "price";
+
+/// Payload of a `NewTrigger` asking the oracle for a price
+///
+/// Encoded as JSON, e.g. `{"version":1,"asset":{"symbol":"ETH"},"quote":"USD"}`,
+/// or `{"version":1,"assets":[{"symbol":"ETH"},{"id":1}]}` for a batch.
+/// For backwards compatibility a bare asset (`1027` or `ETH`) is also accepted.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceRequest {
+    pub version: u32,
+    /// What the oracle is asked for, e.g. `twap` or `metadata`, see [`DEFAULT_KIND`].
+    /// Left unchecked here, since which kinds exist is up to the component.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    #[serde(flatten)]
+    pub assets: AssetSelection,
+    #[serde(default = "default_quote")]
+    pub quote: String,
+    #[serde(default)]
+    pub options: PriceRequestOptions,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Asset {
+    /// CoinMarketCap ID, e.g. `1027` for ETH
+    Id(u64),
+    /// Ticker symbol, e.g. `ETH`
+    Symbol(String),
+}
+
+/// Whether a single price or a batch was requested, which also decides the output shape
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AssetSelection {
+    #[serde(rename = "asset")]
+    Single(Asset),
+    #[serde(rename = "assets")]
+    Batch(Vec<Asset>),
+}
+
+impl AssetSelection {
+    pub fn as_slice(&self) -> &[Asset] {
+        match self {
+            AssetSelection::Single(asset) => std::slice::from_ref(asset),
+            AssetSelection::Batch(assets) => assets,
+        }
+    }
+}
+
+/// Per-request overrides of the service config
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PriceRequestOptions {
+    /// Names of the price sources to query, all configured sources if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_deviation_bps: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_sources: Option<usize>,
+    /// Significant digits kept in the published price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub significant_digits: Option<u32>,
+    /// Window (in seconds) the published timestamp is rounded down to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_window: Option<u64>,
+    #[serde(default, skip_serializing_if = "OutputEncoding::is_default")]
+    pub encoding: OutputEncoding,
+    /// Decimals of the fixed-point price in ABI output, see [`crate::abi::DEFAULT_DECIMALS`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decimals: Option<u8>,
+    #[serde(default, skip_serializing_if = "PriceMode::is_spot")]
+    pub mode: PriceMode,
+    /// Seconds of history a `twap` or `ohlc` price covers, see [`DEFAULT_WINDOW`]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<u64>,
+    /// Market data published next to the price in JSON output, none if empty
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fields: Vec<MarketField>,
+}
+
+/// Which price is published
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceMode {
+    /// The latest price
+    #[default]
+    Spot,
+    /// Time-weighted average price over the window
+    Twap,
+    /// Open, high, low and close over the window, with the close as the price
+    Ohlc,
+}
+
+impl PriceMode {
+    pub fn is_spot(&self) -> bool {
+        *self == PriceMode::Spot
+    }
+}
+
+impl FromStr for PriceMode {
+    type Err = RequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "spot" => Ok(PriceMode::Spot),
+            "twap" => Ok(PriceMode::Twap),
+            "ohlc" => Ok(PriceMode::Ohlc),
+            _ => Err(RequestError::InvalidAttribute("mode".to_string())),
+        }
+    }
+}
+
+/// A field of [`crate::price_feed::MarketData`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarketField {
+    MarketCap,
+    Volume24h,
+    PercentChange24h,
+    CirculatingSupply,
+    TotalSupply,
+    Rank,
+}
+
+impl MarketField {
+    pub const ALL: [MarketField; 6] = [
+        MarketField::MarketCap,
+        MarketField::Volume24h,
+        MarketField::PercentChange24h,
+        MarketField::CirculatingSupply,
+        MarketField::TotalSupply,
+        MarketField::Rank,
+    ];
+}
+
+impl FromStr for MarketField {
+    type Err = RequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "market_cap" => Ok(MarketField::MarketCap),
+            "volume_24h" => Ok(MarketField::Volume24h),
+            "percent_change_24h" => Ok(MarketField::PercentChange24h),
+            "circulating_supply" => Ok(MarketField::CirculatingSupply),
+            "total_supply" => Ok(MarketField::TotalSupply),
+            "rank" => Ok(MarketField::Rank),
+            _ => Err(RequestError::InvalidAttribute("fields".to_string())),
+        }
+    }
+}
+
+/// How the result is encoded in `DataWithId.data`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputEncoding {
+    /// [`crate::price_feed::PriceFeedOutput`] as JSON
+    #[default]
+    Json,
+    /// `ITypes.PriceFeed` (or `ITypes.PriceFeed[]` for a batch) as Solidity ABI
+    Abi,
+    /// `ITypes.RoundData` (or `ITypes.RoundData[]` for a batch), Chainlink's round shape
+    Chainlink,
+    /// [`crate::price_feed::PriceFeedOutput`] as CBOR, the same fields as JSON in fewer bytes
+    Cbor,
+    /// [`crate::price_feed::PriceFeedOutput`] as bincode, without field names, see [`crate::codec`]
+    Bincode,
+}
+
+impl OutputEncoding {
+    fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl FromStr for OutputEncoding {
+    type Err = RequestError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "json" => Ok(OutputEncoding::Json),
+            "abi" => Ok(OutputEncoding::Abi),
+            "chainlink" => Ok(OutputEncoding::Chainlink),
+            "cbor" => Ok(OutputEncoding::Cbor),
+            "bincode" => Ok(OutputEncoding::Bincode),
+            _ => Err(RequestError::InvalidAttribute("encoding".to_string())),
+        }
+    }
+}
+
+fn default_quote() -> String {
+    "USD".to_string()
+}
+
+impl PriceRequest {
+    pub fn new(asset: Asset) -> Self {
+        Self::with_assets(AssetSelection::Single(asset))
+    }
+
+    pub fn batch(assets: Vec<Asset>) -> Self {
+        Self::with_assets(AssetSelection::Batch(assets))
+    }
+
+    fn with_assets(assets: AssetSelection) -> Self {
+        Self {
+            version: PRICE_REQUEST_VERSION,
+            kind: None,
+            assets,
+            quote: default_quote(),
+            options: PriceRequestOptions::default(),
+        }
+    }
+
+    pub fn with_kind(mut self, kind: impl ToString) -> Self {
+        self.kind = Some(kind.to_string());
+        self
+    }
+
+    /// The `kind` tag, lowercase, [`DEFAULT_KIND`] if there is none
+    pub fn kind(&self) -> String {
+        self.kind.as_deref().unwrap_or(DEFAULT_KIND).trim().to_ascii_lowercase()
+    }
+
+    pub fn with_quote(mut self, quote: impl ToString) -> Self {
+        self.quote = quote.to_string();
+        self
+    }
+
+    pub fn with_options(mut self, options: PriceRequestOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("PriceRequest is always serializable")
+    }
+
+    pub fn decode(bytes: &[u8]) -> Result<Self, RequestError> {
+        // `cast format-bytes32-string` pads with zeroes
+        let input = std::str::from_utf8(bytes)
+            .map_err(|_| RequestError::InvalidUtf8)?
+            .trim_end_matches('\0')
+            .trim();
+
+        if input.is_empty() {
+            return Err(RequestError::Empty);
+        }
+
+        if !input.starts_with('{') {
+            return input.parse().map(Self::new);
+        }
+
+        let req: Self =
+            serde_json::from_str(input).map_err(|e| RequestError::Json(e.to_string()))?;
+        req.validate()
+    }
+
+    /// Builds a request from the attributes of a Cosmos event, where every value is a string.
+    ///
+    /// A `request` attribute holds an encoded request like [`Self::decode`] takes. Otherwise the
+    /// request is made of `kind`, `asset` (or a comma-separated `assets` batch), `quote`, and the
+    /// [`PriceRequestOptions`] by name, with `sources` and `fields` comma-separated.
+    /// Other attributes (e.g. `_contract_address` or `trigger_id`) are ignored.
+    pub fn from_attributes<'a>(
+        attributes: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, RequestError> {
+        let attributes: Vec<(&str, &str)> = attributes.into_iter().collect();
+        let get = |key: &str| attribute(&attributes, key);
+
+        if let Some(request) = get("request") {
+            return Self::decode(request.as_bytes());
+        }
+
+        let assets = match (get("asset"), get("assets")) {
+            (Some(asset), None) => AssetSelection::Single(asset.parse()?),
+            (None, Some(assets)) => {
+                AssetSelection::Batch(assets.split(',').map(str::parse).collect::<Result<_, _>>()?)
+            }
+            (None, None) => return Err(RequestError::Empty),
+            (Some(_), Some(_)) => {
+                return Err(RequestError::InvalidAttribute("assets".to_string()));
+            }
+        };
+
+        let options = PriceRequestOptions {
+            sources: get("sources")
+                .map(|sources| sources.split(',').map(|s| s.trim().to_string()).collect())
+                .unwrap_or_default(),
+            max_deviation_bps: parse_attribute(&attributes, "max_deviation_bps")?,
+            min_sources: parse_attribute(&attributes, "min_sources")?,
+            significant_digits: parse_attribute(&attributes, "significant_digits")?,
+            timestamp_window: parse_attribute(&attributes, "timestamp_window")?,
+            encoding: parse_attribute(&attributes, "encoding")?.unwrap_or_default(),
+            decimals: parse_attribute(&attributes, "decimals")?,
+            mode: parse_attribute(&attributes, "mode")?.unwrap_or_default(),
+            window: parse_attribute(&attributes, "window")?,
+            fields: get("fields")
+                .map(|fields| fields.split(',').map(str::parse).collect::<Result<_, _>>())
+                .transpose()?
+                .unwrap_or_default(),
+        };
+
+        let req = Self::with_assets(assets)
+            .with_quote(get("quote").unwrap_or("USD"))
+            .with_options(options);
+        Self { kind: get("kind").map(str::to_string), ..req }.validate()
+    }
+
+    /// Checks what serde can't, and normalizes symbols and the quote currency
+    fn validate(mut self) -> Result<Self, RequestError> {
+        let req = &mut self;
+        if req.version != PRICE_REQUEST_VERSION {
+            return Err(RequestError::UnsupportedVersion(req.version));
+        }
+        match &mut req.assets {
+            AssetSelection::Single(asset) => asset.normalize()?,
+            AssetSelection::Batch(assets) => {
+                if assets.is_empty() || assets.len() > MAX_BATCH_SIZE {
+                    return Err(RequestError::InvalidBatchSize(assets.len()));
+                }
+                for asset in assets.iter_mut() {
+                    asset.normalize()?;
+                }
+            }
+        }
+        req.quote = req.quote.to_ascii_uppercase();
+
+        if let Some(digits) = req.options.significant_digits {
+            if !(1..=MAX_SIGNIFICANT_DIGITS).contains(&digits) {
+                return Err(RequestError::InvalidSignificantDigits(digits));
+            }
+        }
+        if let Some(decimals) = req.options.decimals {
+            if decimals > MAX_DECIMALS {
+                return Err(RequestError::InvalidDecimals(decimals));
+            }
+        }
+        if let Some(window) = req.options.window {
+            if !(MIN_WINDOW..=MAX_WINDOW).contains(&window) {
+                return Err(RequestError::InvalidWindow(window));
+            }
+        }
+
+        Ok(self)
+    }
+}
+
+/// A request the oracle components take from a trigger: bytes from a `NewTrigger` log or the CLI,
+/// or the attributes of a Cosmos event
+pub trait TriggerRequest: Sized {
+    /// Why a request is invalid, published as [`OracleError::BadInput`]
+    type Error: From<RequestError> + Into<OracleError>;
+
+    fn decode(bytes: &[u8]) -> Result<Self, Self::Error>;
+
+    fn from_attributes<'a>(
+        attributes: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, Self::Error>;
+}
+
+impl TriggerRequest for PriceRequest {
+    type Error = RequestError;
+
+    fn decode(bytes: &[u8]) -> Result<Self, RequestError> {
+        PriceRequest::decode(bytes)
+    }
+
+    fn from_attributes<'a>(
+        attributes: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) -> Result<Self, RequestError> {
+        PriceRequest::from_attributes(attributes)
+    }
+}
+
+pub(crate) fn attribute<'a>(attributes: &[(&str, &'a str)], key: &str) -> Option<&'a str> {
+    attributes.iter().find(|(k, _)| *k == key).map(|(_, v)| v.trim())
+}
+
+pub(crate) fn parse_attribute<T: FromStr>(
+    attributes: &[(&str, &str)],
+    key: &str,
+) -> Result<Option<T>, RequestError> {
+    attribute(attributes, key)
+        .map(|value| value.parse().map_err(|_| RequestError::InvalidAttribute(key.to_string())))
+        .transpose()
+}
+
+impl Asset {
+    fn normalize(&mut self) -> Result<(), RequestError> {
+        if let Asset::Symbol(symbol) = self {
+            *self = symbol.parse()?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Asset {
+    type Err = RequestError;
+
+    /// Numbers are CoinMarketCap IDs, anything else alphanumeric is a ticker symbol
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.is_empty() {
+            Err(RequestError::Empty)
+        } else if s.chars().all(|c| c.is_ascii_digit()) {
+            s.parse().map(Asset::Id).map_err(|_| RequestError::InvalidAsset(s.to_string()))
+        } else if s.chars().all(|c| c.is_ascii_alphanumeric()) {
+            Ok(Asset::Symbol(s.to_ascii_uppercase()))
+        } else {
+            Err(RequestError::InvalidAsset(s.to_string()))
+        }
+    }
+}
+
+impl fmt::Display for Asset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Asset::Id(id) => write!(f, "#{}", id),
+            Asset::Symbol(symbol) => write!(f, "{}", symbol),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RequestError {
+    Empty,
+    InvalidUtf8,
+    Json(String),
+    UnsupportedVersion(u32),
+    InvalidAsset(String),
+    InvalidBatchSize(usize),
+    InvalidDecimals(u8),
+    InvalidSignificantDigits(u32),
+    InvalidWindow(u64),
+    /// A Cosmos event attribute that doesn't hold a valid value for its key
+    InvalidAttribute(String),
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::Empty => write!(f, "Empty request"),
+            RequestError::InvalidUtf8 => write!(f, "Request is not valid UTF-8"),
+            RequestError::Json(e) => write!(f, "Invalid request JSON: {}", e),
+            RequestError::UnsupportedVersion(v) => write!(f, "Unsupported request version {}", v),
+            RequestError::InvalidAsset(s) => write!(f, "Invalid asset: {}", s),
+            RequestError::InvalidBatchSize(n) => {
+                write!(f, "Batch must contain 1 to {} assets, got {}", MAX_BATCH_SIZE, n)
+            }
+            RequestError::InvalidDecimals(d) => {
+                write!(f, "At most {} decimals are supported, got {}", MAX_DECIMALS, d)
+            }
+            RequestError::InvalidSignificantDigits(d) => {
+                write!(f, "Significant digits must be 1 to {}, got {}", MAX_SIGNIFICANT_DIGITS, d)
+            }
+            RequestError::InvalidWindow(w) => {
+                write!(f, "Window must be {} to {} seconds, got {}", MIN_WINDOW, MAX_WINDOW, w)
+            }
+            RequestError::InvalidAttribute(key) => write!(f, "Invalid `{}` attribute", key),
+        }
+    }
+}
+
+impl std::error::Error for RequestError {}
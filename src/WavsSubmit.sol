@@ -7,11 +7,20 @@ import {IWavsServiceHandler} from "@wavs/interfaces/IWavsServiceHandler.sol";
 import {ITypes} from "./interfaces/ITypes.sol";
 
 contract SimpleSubmit is IWavsServiceHandler {
+    /// @notice The phase in the upper 16 bits of every round ID, see `getRoundData`
+    uint80 public constant ROUND_PHASE = 1;
+
     IWavsServiceManager private _serviceManager;
 
     mapping(ITypes.TriggerId => bool) validTriggers;
     mapping(ITypes.TriggerId => bytes) datas;
     mapping(ITypes.TriggerId => bytes) signatures;
+    mapping(ITypes.TriggerId => ITypes.ErrorCode) errors;
+    mapping(ITypes.TriggerId => bytes32) requestHashes;
+    mapping(ITypes.TriggerId => ITypes.ResultEncoding) encodings;
+    mapping(bytes32 => ITypes.LatestPrice) latestPrices;
+    mapping(bytes32 => mapping(uint80 => ITypes.RoundData)) rounds;
+    mapping(bytes32 => uint80) latestRounds;
 
     constructor(IWavsServiceManager serviceManager) {
         _serviceManager = serviceManager;
@@ -24,7 +33,29 @@ contract SimpleSubmit is IWavsServiceHandler {
 
         signatures[dataWithId.triggerId] = signature;
         datas[dataWithId.triggerId] = dataWithId.data;
+        errors[dataWithId.triggerId] = dataWithId.error;
+        requestHashes[dataWithId.triggerId] = dataWithId.requestHash;
+        encodings[dataWithId.triggerId] = dataWithId.encoding;
         validTriggers[dataWithId.triggerId] = true;
+
+        // results can land out of order, so a price only replaces one for an earlier trigger
+        for (uint256 i = 0; i < dataWithId.prices.length; i++) {
+            ITypes.AssetPrice memory assetPrice = dataWithId.prices[i];
+            ITypes.LatestPrice storage latest = latestPrices[assetPrice.assetKey];
+            if (ITypes.TriggerId.unwrap(latest.triggerId) < ITypes.TriggerId.unwrap(dataWithId.triggerId)) {
+                latest.triggerId = dataWithId.triggerId;
+                latest.price = assetPrice.price;
+            }
+        }
+
+        if (dataWithId.encoding == ITypes.ResultEncoding.RoundData) {
+            _storeRound(abi.decode(dataWithId.data, (ITypes.RoundData)));
+        } else if (dataWithId.encoding == ITypes.ResultEncoding.RoundDatas) {
+            ITypes.RoundData[] memory batch = abi.decode(dataWithId.data, (ITypes.RoundData[]));
+            for (uint256 i = 0; i < batch.length; i++) {
+                _storeRound(batch[i]);
+            }
+        }
     }
 
     function isValidTriggerId(ITypes.TriggerId triggerId) external view returns (bool) {
@@ -38,4 +69,175 @@ contract SimpleSubmit is IWavsServiceHandler {
     function getData(ITypes.TriggerId triggerId) external view returns (bytes memory data) {
         data = datas[triggerId];
     }
+
+    /// @notice The hash of the request a result answers, compare with `requestHash` of the
+    /// trigger contract's `getTrigger` record to check the result belongs to it
+    function getRequestHash(ITypes.TriggerId triggerId) external view returns (bytes32 hash) {
+        hash = requestHashes[triggerId];
+    }
+
+    /// @notice How the result for a trigger is encoded, `ResultEncoding.None` for a failure or
+    /// no result yet
+    function getEncoding(ITypes.TriggerId triggerId) external view returns (ITypes.ResultEncoding encoding) {
+        encoding = encodings[triggerId];
+    }
+
+    /// @notice The newest price published for an asset, with a zero trigger ID if there is none.
+    /// The oracle's circuit breaker compares new prices with it.
+    function getLatestPrice(bytes32 key) external view returns (ITypes.LatestPrice memory) {
+        return latestPrices[key];
+    }
+
+    /// @notice What an asset's latest price is kept under: its CoinMarketCap ID, the quote
+    /// currency in upper case as requests normalize it, and the price mode
+    function assetKey(uint64 assetId, string memory quote, ITypes.PriceMode mode) public pure returns (bytes32) {
+        return keccak256(abi.encode(assetId, quote, mode));
+    }
+
+    /// @notice What the rounds of a Chainlink feed are kept under: the asset's key and the
+    /// decimals its answers are scaled with, so rounds requested with other decimals are another feed
+    function feedKey(uint64 assetId, string memory quote, ITypes.PriceMode mode, uint8 decimals)
+        public
+        pure
+        returns (bytes32)
+    {
+        return keccak256(abi.encode(assetKey(assetId, quote, mode), decimals));
+    }
+
+    /// @notice Binds a request to the chain and trigger contract it was made on, so a result
+    /// can't be passed off as the answer to another request
+    function requestHash(uint256 chainId, address triggerContract, ITypes.TriggerInfo memory triggerInfo)
+        public
+        pure
+        returns (bytes32)
+    {
+        return keccak256(abi.encode(chainId, triggerContract, triggerInfo));
+    }
+
+    /// @notice Why the run for a trigger failed, `ErrorCode.None` if it succeeded or has no result yet
+    function getError(ITypes.TriggerId triggerId)
+        external
+        view
+        returns (ITypes.ErrorCode code, string memory message)
+    {
+        code = errors[triggerId];
+        if (code != ITypes.ErrorCode.None) {
+            message = string(datas[triggerId]);
+        }
+    }
+
+    /// @notice Decodes the result of a single-asset request made with `"encoding": "abi"`
+    /// @dev Reverts if the trigger has no result yet or the result is in another encoding
+    function getPriceFeed(ITypes.TriggerId triggerId) external view returns (ITypes.PriceFeed memory) {
+        return abi.decode(_result(triggerId, ITypes.ResultEncoding.PriceFeed), (ITypes.PriceFeed));
+    }
+
+    /// @notice Decodes the result of a batch request made with `"encoding": "abi"`
+    function getPriceFeeds(ITypes.TriggerId triggerId) external view returns (ITypes.PriceFeed[] memory) {
+        return abi.decode(_result(triggerId, ITypes.ResultEncoding.PriceFeeds), (ITypes.PriceFeed[]));
+    }
+
+    /// @notice AggregatorV3Interface.getRoundData for the feed under `key`, see `feedKey`. Every
+    /// request made with `"encoding": "chainlink"` adds a round to the feed of each of its assets,
+    /// the round ID's lower 64 bits being the trigger ID.
+    /// @dev Reverts for a round ID of another phase, rather than reading it as one of phase 1
+    function getRoundData(bytes32 key, uint80 roundId)
+        public
+        view
+        returns (uint80 id, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+    {
+        require(roundId >> 64 == ROUND_PHASE, "Unknown phase");
+        ITypes.RoundData storage round = rounds[key][roundId];
+        require(round.roundId != 0, "No data present");
+        return (round.roundId, round.answer, round.startedAt, round.updatedAt, round.answeredInRound);
+    }
+
+    /// @notice AggregatorV3Interface.latestRoundData for the feed under `key`: the round of the
+    /// newest trigger, whatever order the results landed in
+    function latestRoundData(bytes32 key)
+        external
+        view
+        returns (uint80 id, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+    {
+        uint80 roundId = latestRounds[key];
+        require(roundId != 0, "No data present");
+        return getRoundData(key, roundId);
+    }
+
+    /// @notice Decodes the result of an erc20-snapshot request
+    function getTokenSnapshot(ITypes.TriggerId triggerId) external view returns (ITypes.TokenSnapshot memory) {
+        return abi.decode(_result(triggerId, ITypes.ResultEncoding.TokenSnapshot), (ITypes.TokenSnapshot));
+    }
+
+    /// @notice Decodes the result of a cross-chain-relay request
+    /// @dev Reverts if the message was relayed for another chain
+    function getRelayMessage(ITypes.TriggerId triggerId) external view returns (ITypes.RelayMessage memory message) {
+        message = abi.decode(_result(triggerId, ITypes.ResultEncoding.RelayMessage), (ITypes.RelayMessage));
+        require(message.destinationChainId == block.chainid, "Relayed for another chain");
+    }
+
+    /// @notice Identifies the source log of a relayed message, for consumers to reject replays
+    function relayId(ITypes.RelayMessage memory message) public pure returns (bytes32) {
+        return keccak256(
+            abi.encode(message.sourceChainId, message.sourceBlock, message.sourceBlockHash, message.logIndex)
+        );
+    }
+
+    /// @dev Results can land out of order, so a round only becomes the latest over an earlier one
+    function _storeRound(ITypes.RoundData memory round) internal {
+        rounds[round.feedKey][round.roundId] = round;
+        if (latestRounds[round.feedKey] < round.roundId) {
+            latestRounds[round.feedKey] = round.roundId;
+        }
+    }
+
+    /// @dev The result for a trigger, which must have succeeded and be in `encoding`
+    function _result(ITypes.TriggerId triggerId, ITypes.ResultEncoding encoding) internal view returns (bytes memory) {
+        require(validTriggers[triggerId], "No result for trigger");
+        require(errors[triggerId] == ITypes.ErrorCode.None, "Oracle run failed");
+        require(encodings[triggerId] == encoding, "Result in another encoding");
+        return datas[triggerId];
+    }
+}
+
+/// @notice One feed of a SimpleSubmit as Chainlink's AggregatorV3Interface, so contracts that read
+/// Chainlink feeds can read it unchanged. Its rounds are those of requests for the asset, quote
+/// and mode made with `"encoding": "chainlink"` and these `decimals`.
+contract WavsAggregator {
+    uint256 public constant version = 1;
+
+    SimpleSubmit public immutable submit;
+    bytes32 public immutable key;
+    uint8 public immutable decimals;
+    string public description;
+
+    constructor(
+        SimpleSubmit submit_,
+        uint64 assetId,
+        string memory quote,
+        ITypes.PriceMode mode,
+        uint8 decimals_,
+        string memory description_
+    ) {
+        submit = submit_;
+        key = submit_.feedKey(assetId, quote, mode, decimals_);
+        decimals = decimals_;
+        description = description_;
+    }
+
+    function getRoundData(uint80 roundId)
+        external
+        view
+        returns (uint80 id, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+    {
+        return submit.getRoundData(key, roundId);
+    }
+
+    function latestRoundData()
+        external
+        view
+        returns (uint80 id, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound)
+    {
+        return submit.latestRoundData(key);
+    }
 }
@@ -2,9 +2,73 @@
 pragma solidity ^0.8.22;
 
 interface ITypes {
+    /// @notice What the oracle publishes for a trigger
+    /// @dev On failure `error` is set and `data` holds a UTF-8 description instead of a result
     struct DataWithId {
         TriggerId triggerId;
         bytes data;
+        ErrorCode error;
+        // keccak256(abi.encode(chainId, triggerContract, triggerInfo)) of the request answered,
+        // see SimpleSubmit.requestHash
+        bytes32 requestHash;
+        // how `data` is encoded, `None` for a failure, see SimpleSubmit.getEncoding
+        ResultEncoding encoding;
+        // the price of each asset in a price feed result, empty for other results and failures
+        AssetPrice[] prices;
+    }
+
+    /// @notice One asset's published price, which SimpleSubmit keeps as the asset's latest
+    struct AssetPrice {
+        // see SimpleSubmit.assetKey
+        bytes32 assetKey;
+        // price * 10^18, after quantization
+        uint256 price;
+    }
+
+    /// @notice The newest price published for an asset, see SimpleSubmit.getLatestPrice
+    struct LatestPrice {
+        // zero if no price was published for the asset yet
+        TriggerId triggerId;
+        // price * 10^18
+        uint256 price;
+    }
+
+    /// @notice How a result is encoded, so it's only ever decoded the way it was written
+    enum ResultEncoding {
+        // a failure message, or no result yet
+        None,
+        // what a request's `json`, `cbor` or `bincode` encoding asks for; metadata and the
+        // JSON oracle's `json` encoding are JSON too
+        Json,
+        Cbor,
+        Bincode,
+        // a price feed request's `abi` encoding, for a single asset or a batch
+        PriceFeed,
+        PriceFeeds,
+        // a price feed request's `chainlink` encoding, for a single asset or a batch
+        RoundData,
+        RoundDatas,
+        TokenSnapshot,
+        RelayMessage,
+        // the JSON oracle's `abi` encoding, one value per requested field
+        AbiValues
+    }
+
+    /// @notice Why an oracle run failed, `None` if it succeeded
+    enum ErrorCode {
+        None,
+        // the request couldn't be decoded or names an unknown asset or source
+        BadInput,
+        // no upstream could be reached
+        UpstreamUnavailable,
+        // upstreams answered, but not with a usable price
+        ParseFailure,
+        // every quote was older than the configured maximum age
+        StaleData,
+        // the sources disagree too much, or the price doesn't fit the output encoding
+        OutOfRange,
+        // the request's kind isn't one the component answers
+        UnsupportedKind
     }
 
     struct TriggerInfo {
@@ -13,6 +77,81 @@ interface ITypes {
         bytes data;
     }
 
+    /// @notice Fixed-point price published when a request asks for ABI output
+    struct PriceFeed {
+        // CoinMarketCap ID of the asset
+        uint64 assetId;
+        // price * 10^decimals
+        uint256 price;
+        uint8 decimals;
+        // when the price was last updated upstream, in unix seconds
+        uint64 timestamp;
+        // block and transaction of the NewTrigger log this answers, zero if unknown
+        uint64 blockHeight;
+        bytes32 txHash;
+        // `price` is the spot price, the TWAP, or the close over the last `window` seconds
+        PriceMode mode;
+        uint64 window;
+        // scaled like `price`, zero unless `mode` is Ohlc
+        uint256 open;
+        uint256 high;
+        uint256 low;
+    }
+
+    /// @notice A price in the shape of Chainlink's AggregatorV3Interface.getRoundData,
+    /// published when a request asks for `"encoding": "chainlink"`
+    struct RoundData {
+        // phase 1 in the upper 16 bits, the trigger ID in the lower 64
+        uint80 roundId;
+        // price * 10^decimals
+        int256 answer;
+        // when the first point in the window was for TWAP and OHLC prices, `updatedAt` otherwise
+        uint256 startedAt;
+        // when the price was last updated upstream, in unix seconds
+        uint256 updatedAt;
+        uint80 answeredInRound;
+        // the feed the round belongs to, see SimpleSubmit.feedKey
+        bytes32 feedKey;
+    }
+
+    /// @notice ERC-20 balances at one block, published by the erc20-snapshot component
+    struct TokenSnapshot {
+        address token;
+        // the block the requested tag resolved to, every value was read at it
+        uint64 blockNumber;
+        bytes32 blockHash;
+        uint256 totalSupply;
+        // in the order of the request
+        address[] holders;
+        uint256[] balances;
+        // sum of `balances`
+        uint256 heldTotal;
+    }
+
+    /// @notice A NewTrigger payload from another chain, published by the cross-chain-relay component
+    struct RelayMessage {
+        // the NewTrigger log that was relayed, the first four identify it for replay protection
+        uint64 sourceChainId;
+        uint64 sourceBlock;
+        // tells the log apart from one at the same height on a reorged fork
+        bytes32 sourceBlockHash;
+        uint64 logIndex;
+        address sourceTrigger;
+        bytes32 sourceTxHash;
+        // who called addTrigger on the source chain
+        address creator;
+        // the chain the message is meant for
+        uint64 destinationChainId;
+        bytes payload;
+    }
+
+    /// @notice Which price a PriceFeed holds
+    enum PriceMode {
+        Spot,
+        Twap,
+        Ohlc
+    }
+
     event NewTrigger(bytes);
 
     type TriggerId is uint64;
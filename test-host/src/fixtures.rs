@@ -0,0 +1,190 @@
+//! Canned HTTP responses, in memory or recorded to fixture files.
+//!
+//! A fixture file is a JSON array of responses keyed by URL. Setting `RECORD_FIXTURES` to a
+//! comma-separated list of fixture names (or `all`) makes the tests that use those files send
+//! their requests to the real upstreams and overwrite the files with the responses.
+
+use bytes::Bytes;
+use http_body_util::{BodyExt, Full};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use wasmtime_wasi_http::{
+    bindings::http::types::ErrorCode,
+    body::{HyperIncomingBody, HyperOutgoingBody},
+    types::{
+        default_send_request_handler, HostFutureIncomingResponse, IncomingResponse,
+        OutgoingRequestConfig,
+    },
+};
+
+/// Responses captured while recording, in the order they arrived
+pub(crate) type Recorded = Arc<Mutex<Vec<(String, HttpFixture)>>>;
+
+/// Headers that are never written to fixture files
+const UNRECORDED_HEADERS: &[&str] = &["set-cookie", "date"];
+
+/// A canned response, served for every request whose URL starts with the registered prefix
+#[derive(Debug, Clone, PartialEq)]
+pub struct HttpFixture {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpFixture {
+    pub fn json(body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status: 200,
+            headers: vec![("content-type".to_string(), "application/json".to_string())],
+            body: body.into(),
+        }
+    }
+
+    pub fn status(status: u16) -> Self {
+        Self { status, headers: Vec::new(), body: Vec::new() }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    pub(crate) fn to_response(&self) -> Result<hyper::Response<HyperIncomingBody>, ErrorCode> {
+        let mut builder = hyper::Response::builder().status(self.status);
+        for (name, value) in &self.headers {
+            builder = builder.header(name, value);
+        }
+
+        let body =
+            Full::new(Bytes::from(self.body.clone())).map_err(|never| match never {}).boxed();
+        builder.body(body).map_err(|e| ErrorCode::InternalError(Some(e.to_string())))
+    }
+}
+
+/// `test-host/fixtures/<name>.json`
+pub fn fixture_file(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("fixtures").join(format!("{name}.json"))
+}
+
+/// Whether `RECORD_FIXTURES` asks for the fixture file at `path` to be re-recorded.
+/// Names match the end of the path without the extension, e.g. `eth_price_oracle/success`.
+pub(crate) fn should_record(path: &Path) -> bool {
+    let Ok(names) = std::env::var("RECORD_FIXTURES") else {
+        return false;
+    };
+    let path = path.with_extension("");
+    names.split(',').map(str::trim).any(|name| name == "all" || path.ends_with(name))
+}
+
+/// How a response is stored in a fixture file.
+/// JSON bodies are stored as JSON so fixtures can be read and edited by hand.
+#[derive(Debug, Serialize, Deserialize)]
+struct FixtureEntry {
+    url: String,
+    status: u16,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    json: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+}
+
+pub fn load_fixture_file(path: &Path) -> anyhow::Result<Vec<(String, HttpFixture)>> {
+    let entries: Vec<FixtureEntry> = serde_json::from_slice(&std::fs::read(path)?)?;
+
+    entries
+        .into_iter()
+        .map(|entry| {
+            let body = match (entry.json, entry.body) {
+                (Some(json), _) => serde_json::to_vec(&json)?,
+                (None, Some(body)) => body.into_bytes(),
+                (None, None) => Vec::new(),
+            };
+            let fixture = HttpFixture {
+                status: entry.status,
+                headers: entry.headers.into_iter().collect(),
+                body,
+            };
+            Ok((entry.url, fixture))
+        })
+        .collect()
+}
+
+pub fn save_fixture_file(path: &Path, fixtures: &[(String, HttpFixture)]) -> anyhow::Result<()> {
+    let entries: Vec<FixtureEntry> = fixtures
+        .iter()
+        .map(|(url, fixture)| {
+            let json = serde_json::from_slice(&fixture.body).ok();
+            FixtureEntry {
+                url: url.clone(),
+                status: fixture.status,
+                headers: fixture
+                    .headers
+                    .iter()
+                    .filter(|(name, _)| !UNRECORDED_HEADERS.contains(&name.as_str()))
+                    .cloned()
+                    .collect(),
+                body: match json {
+                    Some(_) => None,
+                    None => Some(String::from_utf8_lossy(&fixture.body).into_owned()),
+                },
+                json,
+            }
+        })
+        .collect();
+
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    let mut json = serde_json::to_vec_pretty(&entries)?;
+    json.push(b'\n');
+    std::fs::write(path, json)?;
+    Ok(())
+}
+
+/// Sends the request to the real upstream and keeps a copy of the response
+pub(crate) fn record(
+    url: String,
+    request: hyper::Request<HyperOutgoingBody>,
+    config: OutgoingRequestConfig,
+    recorded: Recorded,
+) -> HostFutureIncomingResponse {
+    let between_bytes_timeout = config.between_bytes_timeout;
+
+    HostFutureIncomingResponse::pending(wasmtime_wasi::runtime::spawn(async move {
+        let response = match default_send_request_handler(request, config).await {
+            Ok(response) => response,
+            Err(e) => return Ok(Err(e)),
+        };
+
+        let (parts, body) = response.resp.into_parts();
+        let body = match body.collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(e) => return Ok(Err(e)),
+        };
+
+        let fixture = HttpFixture {
+            status: parts.status.as_u16(),
+            headers: parts
+                .headers
+                .iter()
+                .filter_map(|(name, value)| {
+                    Some((name.to_string(), value.to_str().ok()?.to_string()))
+                })
+                .collect(),
+            body: body.to_vec(),
+        };
+        recorded.lock().unwrap().push((url, fixture.clone()));
+
+        Ok(fixture.to_response().map(|resp| IncomingResponse {
+            resp,
+            worker: None,
+            between_bytes_timeout,
+        }))
+    }))
+}
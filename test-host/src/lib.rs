@@ -0,0 +1,575 @@
+//! Runs WAVS components natively with wasmtime, standing in for the WAVS node (and its Docker
+//! image) in plain `cargo test`.
+//!
+//! The host implements the `wavs:worker/layer-trigger-world` imports plus WASI. Outgoing HTTP
+//! never reaches the network, it's answered from fixtures registered on the [`TestHost`],
+//! unless a fixture file is being re-recorded (see [`TestHost::with_fixture_file`]).
+
+mod bindings {
+    wasmtime::component::bindgen!({
+        path: "../component-support/wit",
+        world: "layer-trigger-world",
+        async: true,
+    });
+}
+
+mod fixtures;
+
+use alloy_primitives::{Address, LogData, B256};
+use alloy_sol_types::{SolEvent, SolValue};
+use anyhow::{Context, Result};
+use bindings::wavs::worker::layer_types;
+use http_body_util::BodyExt;
+use shared::abi::{NewTrigger, TriggerInfo};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::SystemTime,
+};
+use wasmtime::{
+    component::{Component, Linker, ResourceTable},
+    Config, Engine, Store,
+};
+use wasmtime_wasi::{pipe::MemoryOutputPipe, WasiCtx, WasiCtxBuilder, WasiView};
+use wasmtime_wasi_http::{
+    bindings::http::types::ErrorCode,
+    body::HyperOutgoingBody,
+    types::{HostFutureIncomingResponse, IncomingResponse, OutgoingRequestConfig},
+    HttpResult, WasiHttpCtx, WasiHttpView,
+};
+
+pub use fixtures::{fixture_file, load_fixture_file, save_fixture_file, HttpFixture};
+
+pub use bindings::wavs::worker::layer_types::{
+    CosmosAddress, CosmosChainConfig, CosmosEvent, EthAddress, EthChainConfig, EthEventLogData,
+    LogLevel, TriggerAction, TriggerConfig, TriggerData, TriggerDataCosmosContractEvent,
+    TriggerDataEthContractEvent, TriggerSource, TriggerSourceCosmosContractEvent,
+    TriggerSourceEthContractEvent,
+};
+
+/// Same as the `fuel_limit` in the Makefile's `SERVICE_CONFIG`
+pub const DEFAULT_FUEL_LIMIT: u64 = 100_000_000;
+
+/// The chain [`TriggerAction::eth_event`]s usually name, anvil's chain ID as in `wavs.toml` but
+/// without endpoints, so nothing is looked up unless a test configures them
+pub const LOCAL_CHAIN: &str = "local";
+pub const LOCAL_CHAIN_ID: u64 = 31337;
+
+/// The chain [`TestHost::with_mainnet_rpc`] configures, for tests that read Ethereum mainnet
+/// state from RPC fixtures
+pub const MAINNET: &str = "mainnet";
+
+/// Where [`TriggerAction::new_trigger`] logs come from
+pub const TRIGGER_CONTRACT: Address = Address::repeat_byte(1);
+pub const TRIGGER_BLOCK: u64 = 42;
+
+/// What `SimpleTrigger.addTrigger(data)` stores for a trigger, with a zero creator
+pub fn trigger_info(trigger_id: u64, data: impl Into<Vec<u8>>) -> TriggerInfo {
+    TriggerInfo { triggerId: trigger_id, creator: Address::ZERO, data: data.into().into() }
+}
+
+/// The log `SimpleTrigger.addTrigger` emits for a trigger
+pub fn new_trigger_log(trigger_info: &TriggerInfo) -> LogData {
+    NewTrigger { _0: trigger_info.abi_encode().into() }.encode_log_data()
+}
+
+/// An `eth_getBlockByNumber` result for a block made at `timestamp` (unix seconds)
+pub fn block_json(number: u64, hash: B256, timestamp: u64) -> serde_json::Value {
+    let zero = B256::ZERO.to_string();
+    serde_json::json!({
+        "hash": hash.to_string(),
+        "parentHash": zero,
+        "sha3Uncles": zero,
+        "miner": Address::ZERO.to_string(),
+        "stateRoot": zero,
+        "transactionsRoot": zero,
+        "receiptsRoot": zero,
+        "logsBloom": format!("0x{}", "0".repeat(512)),
+        "difficulty": "0x0",
+        "number": format!("{number:#x}"),
+        "gasLimit": "0x1c9c380",
+        "gasUsed": "0x0",
+        "timestamp": format!("{timestamp:#x}"),
+        "extraData": "0x",
+        "mixHash": zero,
+        "nonce": "0x0000000000000000",
+        "uncles": [],
+        "transactions": [],
+    })
+}
+
+/// Answers a JSON-RPC call from its method and params, see [`TestHost::with_json_rpc`]
+pub type RpcHandler =
+    Arc<dyn Fn(&str, &serde_json::Value) -> Result<serde_json::Value, String> + Send + Sync>;
+
+/// Largest amount of stdout/stderr kept from a run
+const OUTPUT_CAPACITY: usize = 1024 * 1024;
+
+/// `compiled/<name>.wasm` as built by `make wasi-build`, which has to run before `cargo test`.
+///
+/// A missing build fails the test rather than letting it pass without running. One older than its
+/// sources is only warned about, since `git checkout` or `touch` make sources look newer without
+/// changing them. `None` only if `SKIP_COMPONENT_TESTS` is set, for checkouts without
+/// `cargo component`.
+pub fn compiled_component(name: &str) -> Option<PathBuf> {
+    if std::env::var_os("SKIP_COMPONENT_TESTS").is_some() {
+        eprintln!("skipping: SKIP_COMPONENT_TESTS is set");
+        return None;
+    }
+
+    let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let path = root.join("compiled").join(format!("{name}.wasm"));
+    let Ok(built) = std::fs::metadata(&path).and_then(|meta| meta.modified()) else {
+        panic!(
+            "{} is missing: run `make wasi-build` before `cargo test`, or set \
+             SKIP_COMPONENT_TESTS=1 to skip the component tests",
+            path.display()
+        );
+    };
+
+    let sources =
+        ["shared", "component-support", &format!("components/{}", name.replace('_', "-"))];
+    if let Some(newer) = sources.iter().find_map(|dir| newer_file(&root.join(dir), built)) {
+        eprintln!(
+            "warning: {} is older than {}, run `make wasi-build` if the sources changed",
+            path.display(),
+            newer.display()
+        );
+    }
+    Some(path)
+}
+
+/// A file under `dir` modified after `time`, skipping build output
+fn newer_file(dir: &Path, time: SystemTime) -> Option<PathBuf> {
+    std::fs::read_dir(dir).ok()?.flatten().find_map(|entry| {
+        let path = entry.path();
+        let meta = entry.metadata().ok()?;
+        if meta.is_dir() {
+            (entry.file_name() != "target").then(|| newer_file(&path, time)).flatten()
+        } else {
+            (meta.modified().ok()? > time).then_some(path)
+        }
+    })
+}
+
+/// Everything observable about one `run` call
+#[derive(Debug)]
+pub struct RunOutput {
+    /// What the component's `run` returned
+    pub result: std::result::Result<Vec<u8>, String>,
+    /// Messages sent to the host `log` import
+    pub logs: Vec<(LogLevel, String)>,
+    /// URLs the component requested, in order, including ones without a fixture
+    pub requests: Vec<String>,
+    pub stdout: String,
+    pub stderr: String,
+    pub fuel_consumed: u64,
+}
+
+/// Loads a component once and runs it against synthetic triggers.
+/// Every [`TestHost::run`] gets a fresh instance, like on a WAVS node.
+/// Only [`LOCAL_CHAIN`] is configured to begin with.
+pub struct TestHost {
+    engine: Engine,
+    component: Component,
+    linker: Linker<HostState>,
+    env: Vec<(String, String)>,
+    fixtures: Vec<(String, HttpFixture)>,
+    rpc_handlers: Vec<(String, RpcHandler)>,
+    recording: Option<Recording>,
+    eth_chains: HashMap<String, EthChainConfig>,
+    cosmos_chains: HashMap<String, CosmosChainConfig>,
+    fuel_limit: u64,
+}
+
+/// A fixture file being re-recorded, the responses are shared by every run of the host
+struct Recording {
+    path: PathBuf,
+    responses: fixtures::Recorded,
+}
+
+impl TestHost {
+    /// The host for `compiled/<name>.wasm`, see [`compiled_component`]
+    pub fn compiled(name: &str) -> Option<Self> {
+        compiled_component(name).map(|path| Self::new(path).unwrap())
+    }
+
+    pub fn new(path: impl AsRef<Path>) -> Result<Self> {
+        let mut config = Config::new();
+        config.wasm_component_model(true).async_support(true).consume_fuel(true);
+        let engine = Engine::new(&config)?;
+
+        let component = Component::from_file(&engine, path.as_ref())
+            .with_context(|| format!("loading {}", path.as_ref().display()))?;
+
+        let mut linker = Linker::new(&engine);
+        wasmtime_wasi::add_to_linker_async(&mut linker)?;
+        wasmtime_wasi_http::add_only_http_to_linker_async(&mut linker)?;
+        bindings::LayerTriggerWorld::add_to_linker(&mut linker, |state: &mut HostState| state)?;
+
+        Ok(Self {
+            engine,
+            component,
+            linker,
+            env: Vec::new(),
+            fixtures: Vec::new(),
+            rpc_handlers: Vec::new(),
+            recording: None,
+            eth_chains: HashMap::from([(
+                LOCAL_CHAIN.to_string(),
+                EthChainConfig {
+                    chain_id: LOCAL_CHAIN_ID.to_string(),
+                    ws_endpoint: None,
+                    http_endpoint: None,
+                },
+            )]),
+            cosmos_chains: HashMap::new(),
+            fuel_limit: DEFAULT_FUEL_LIMIT,
+        })
+    }
+
+    /// Sets a service config `kv` entry (or a `host_envs` variable), both are environment variables.
+    /// Setting a key again replaces its value.
+    pub fn with_env(mut self, key: &str, value: &str) -> Self {
+        self.env.retain(|(k, _)| k != key);
+        self.env.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    /// Later fixtures don't override earlier ones with an overlapping prefix, the first match wins
+    pub fn with_fixture(mut self, url_prefix: &str, fixture: HttpFixture) -> Self {
+        self.fixtures.push((url_prefix.to_string(), fixture));
+        self
+    }
+
+    /// Serves every response in a fixture file, matched by URL prefix like [`Self::with_fixture`].
+    ///
+    /// If `RECORD_FIXTURES` names the file (by its stem, or `all`), requests go to the network
+    /// instead and the file is rewritten with the responses after every run.
+    pub fn with_fixture_file(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        if fixtures::should_record(path) {
+            let responses = fixtures::Recorded::default();
+            self.recording = Some(Recording { path: path.to_path_buf(), responses });
+            return Ok(self);
+        }
+
+        let fixtures = load_fixture_file(path)
+            .with_context(|| format!("loading fixtures from {}", path.display()))?;
+        self.fixtures.extend(fixtures);
+        Ok(self)
+    }
+
+    /// Answers JSON-RPC calls to URLs starting with `url_prefix` with `handler(method, params)`,
+    /// for components that make different calls to the same endpoint. An `Err` is sent as a
+    /// JSON-RPC error, like a reverted `eth_call`. Takes precedence over fixtures.
+    pub fn with_json_rpc(
+        mut self,
+        url_prefix: &str,
+        handler: impl Fn(&str, &serde_json::Value) -> Result<serde_json::Value, String>
+            + Send
+            + Sync
+            + 'static,
+    ) -> Self {
+        self.rpc_handlers.push((url_prefix.to_string(), Arc::new(handler)));
+        self
+    }
+
+    /// Replaces a chain of the same name, including the default [`LOCAL_CHAIN`]
+    pub fn with_eth_chain(mut self, name: &str, config: EthChainConfig) -> Self {
+        self.eth_chains.insert(name.to_string(), config);
+        self
+    }
+
+    /// [`MAINNET`] with chain ID 1 and `http_endpoint` as its only endpoint
+    pub fn with_mainnet_rpc(self, http_endpoint: &str) -> Self {
+        self.with_eth_chain(
+            MAINNET,
+            EthChainConfig {
+                chain_id: "1".to_string(),
+                ws_endpoint: None,
+                http_endpoint: Some(http_endpoint.to_string()),
+            },
+        )
+    }
+
+    pub fn with_cosmos_chain(mut self, name: &str, config: CosmosChainConfig) -> Self {
+        self.cosmos_chains.insert(name.to_string(), config);
+        self
+    }
+
+    pub fn with_fuel_limit(mut self, fuel_limit: u64) -> Self {
+        self.fuel_limit = fuel_limit;
+        self
+    }
+
+    /// Runs the component once. Only host failures (e.g. running out of fuel) are errors,
+    /// the component's own result is in [`RunOutput::result`].
+    pub async fn run(&self, action: TriggerAction) -> Result<RunOutput> {
+        let stdout = MemoryOutputPipe::new(OUTPUT_CAPACITY);
+        let stderr = MemoryOutputPipe::new(OUTPUT_CAPACITY);
+
+        let mut wasi = WasiCtxBuilder::new();
+        wasi.stdout(stdout.clone()).stderr(stderr.clone());
+        for (key, value) in &self.env {
+            wasi.env(key, value);
+        }
+
+        let state = HostState {
+            wasi: wasi.build(),
+            http: WasiHttpCtx::new(),
+            table: ResourceTable::new(),
+            fixtures: self.fixtures.clone(),
+            rpc_handlers: self.rpc_handlers.clone(),
+            recorded: self.recording.as_ref().map(|recording| recording.responses.clone()),
+            eth_chains: self.eth_chains.clone(),
+            cosmos_chains: self.cosmos_chains.clone(),
+            logs: Vec::new(),
+            requests: Vec::new(),
+        };
+
+        let mut store = Store::new(&self.engine, state);
+        store.set_fuel(self.fuel_limit)?;
+
+        let instance = bindings::LayerTriggerWorld::instantiate_async(
+            &mut store,
+            &self.component,
+            &self.linker,
+        )
+        .await?;
+        let result = instance.call_run(&mut store, &action).await?;
+
+        let fuel_consumed = self.fuel_limit - store.get_fuel()?;
+        let state = store.into_data();
+
+        if let Some(recording) = &self.recording {
+            save_fixture_file(&recording.path, &recording.responses.lock().unwrap())?;
+        }
+
+        Ok(RunOutput {
+            result,
+            logs: state.logs,
+            requests: state.requests,
+            stdout: String::from_utf8_lossy(&stdout.contents()).into_owned(),
+            stderr: String::from_utf8_lossy(&stderr.contents()).into_owned(),
+            fuel_consumed,
+        })
+    }
+}
+
+impl TriggerAction {
+    /// A trigger like `wavs-cli exec --input` sends
+    pub fn raw(data: impl Into<Vec<u8>>) -> Self {
+        Self {
+            config: TriggerConfig {
+                service_id: "test".to_string(),
+                workflow_id: "default".to_string(),
+                trigger_source: TriggerSource::Manual,
+            },
+            data: TriggerData::Raw(data.into()),
+        }
+    }
+
+    /// A `NewTrigger` log on [`LOCAL_CHAIN`] for a request, see [`Self::new_trigger_on`]
+    pub fn new_trigger(trigger_id: u64, data: impl Into<Vec<u8>>) -> Self {
+        Self::new_trigger_on(LOCAL_CHAIN, &trigger_info(trigger_id, data))
+    }
+
+    /// The `NewTrigger` log for `trigger_info`, from [`TRIGGER_CONTRACT`] on `chain_name` in
+    /// [`TRIGGER_BLOCK`]
+    pub fn new_trigger_on(chain_name: &str, trigger_info: &TriggerInfo) -> Self {
+        let log = new_trigger_log(trigger_info);
+        Self::eth_event(
+            chain_name,
+            TRIGGER_CONTRACT.into(),
+            log.topics().iter().map(|topic| topic.to_vec()).collect(),
+            log.data.to_vec(),
+            TRIGGER_BLOCK,
+        )
+    }
+
+    /// A trigger for an Ethereum log with the given topics (the event signature first) and data
+    pub fn eth_event(
+        chain_name: &str,
+        contract_address: [u8; 20],
+        topics: Vec<Vec<u8>>,
+        data: Vec<u8>,
+        block_height: u64,
+    ) -> Self {
+        let address = EthAddress { raw_bytes: contract_address.to_vec() };
+        Self {
+            config: TriggerConfig {
+                service_id: "test".to_string(),
+                workflow_id: "default".to_string(),
+                trigger_source: TriggerSource::EthContractEvent(TriggerSourceEthContractEvent {
+                    address: address.clone(),
+                    chain_name: chain_name.to_string(),
+                    event_hash: topics.first().cloned().unwrap_or_default(),
+                }),
+            },
+            data: TriggerData::EthContractEvent(TriggerDataEthContractEvent {
+                contract_address: address,
+                chain_name: chain_name.to_string(),
+                log: EthEventLogData { topics, data },
+                block_height,
+            }),
+        }
+    }
+
+    /// A trigger for a CosmWasm event, `contract_address` is bech32 with a `prefix` like `layer`
+    pub fn cosmos_event(
+        chain_name: &str,
+        contract_address: &str,
+        prefix: &str,
+        event_type: &str,
+        attributes: &[(&str, &str)],
+        block_height: u64,
+    ) -> Self {
+        let address = CosmosAddress {
+            bech32_addr: contract_address.to_string(),
+            prefix_len: prefix.len() as u32,
+        };
+        Self {
+            config: TriggerConfig {
+                service_id: "test".to_string(),
+                workflow_id: "default".to_string(),
+                trigger_source: TriggerSource::CosmosContractEvent(
+                    TriggerSourceCosmosContractEvent {
+                        address: address.clone(),
+                        chain_name: chain_name.to_string(),
+                        event_type: event_type.to_string(),
+                    },
+                ),
+            },
+            data: TriggerData::CosmosContractEvent(TriggerDataCosmosContractEvent {
+                contract_address: address,
+                chain_name: chain_name.to_string(),
+                event: CosmosEvent {
+                    ty: event_type.to_string(),
+                    attributes: attributes
+                        .iter()
+                        .map(|(key, value)| (key.to_string(), value.to_string()))
+                        .collect(),
+                },
+                block_height,
+            }),
+        }
+    }
+}
+
+struct HostState {
+    wasi: WasiCtx,
+    http: WasiHttpCtx,
+    table: ResourceTable,
+    fixtures: Vec<(String, HttpFixture)>,
+    rpc_handlers: Vec<(String, RpcHandler)>,
+    recorded: Option<fixtures::Recorded>,
+    eth_chains: HashMap<String, EthChainConfig>,
+    cosmos_chains: HashMap<String, CosmosChainConfig>,
+    logs: Vec<(LogLevel, String)>,
+    requests: Vec<String>,
+}
+
+impl WasiView for HostState {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.wasi
+    }
+}
+
+impl WasiHttpView for HostState {
+    fn ctx(&mut self) -> &mut WasiHttpCtx {
+        &mut self.http
+    }
+
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn send_request(
+        &mut self,
+        request: hyper::Request<HyperOutgoingBody>,
+        config: OutgoingRequestConfig,
+    ) -> HttpResult<HostFutureIncomingResponse> {
+        let url = request.uri().to_string();
+        self.requests.push(url.clone());
+
+        if let Some(recorded) = &self.recorded {
+            return Ok(fixtures::record(url, request, config, recorded.clone()));
+        }
+
+        let handler = self.rpc_handlers.iter().find(|(prefix, _)| url.starts_with(prefix.as_str()));
+        if let Some((_, handler)) = handler {
+            return Ok(json_rpc(request, config, handler.clone()));
+        }
+
+        let fixture = self.fixtures.iter().find(|(prefix, _)| url.starts_with(prefix.as_str()));
+        let Some((_, fixture)) = fixture else {
+            return Ok(HostFutureIncomingResponse::ready(Ok(Err(ErrorCode::DestinationNotFound))));
+        };
+
+        let fixture = fixture.clone();
+        let between_bytes_timeout = config.between_bytes_timeout;
+
+        // the guest writes a request body (e.g. a JSON-RPC call) after sending the request,
+        // which fails if the body is dropped before it's done
+        Ok(HostFutureIncomingResponse::pending(wasmtime_wasi::runtime::spawn(async move {
+            if let Err(e) = request.into_body().collect().await {
+                return Ok(Err(e));
+            }
+
+            Ok(fixture.to_response().map(|resp| IncomingResponse {
+                resp,
+                worker: None,
+                between_bytes_timeout,
+            }))
+        })))
+    }
+}
+
+/// Answers a JSON-RPC request body with the handler's result for it
+fn json_rpc(
+    request: hyper::Request<HyperOutgoingBody>,
+    config: OutgoingRequestConfig,
+    handler: RpcHandler,
+) -> HostFutureIncomingResponse {
+    HostFutureIncomingResponse::pending(wasmtime_wasi::runtime::spawn(async move {
+        let body = match request.into_body().collect().await {
+            Ok(body) => body.to_bytes(),
+            Err(e) => return Ok(Err(e)),
+        };
+        let call: serde_json::Value = serde_json::from_slice(&body).unwrap_or_default();
+
+        let mut response = serde_json::json!({"jsonrpc": "2.0", "id": call["id"]});
+        match handler(call["method"].as_str().unwrap_or_default(), &call["params"]) {
+            Ok(result) => response["result"] = result,
+            Err(message) => response["error"] = serde_json::json!({"code": 3, "message": message}),
+        }
+
+        Ok(HttpFixture::json(response.to_string()).to_response().map(|resp| IncomingResponse {
+            resp,
+            worker: None,
+            between_bytes_timeout: config.between_bytes_timeout,
+        }))
+    }))
+}
+
+impl layer_types::Host for HostState {}
+
+impl bindings::host::Host for HostState {
+    async fn get_eth_chain_config(&mut self, chain_name: String) -> Option<EthChainConfig> {
+        self.eth_chains.get(&chain_name).cloned()
+    }
+
+    async fn get_cosmos_chain_config(&mut self, chain_name: String) -> Option<CosmosChainConfig> {
+        self.cosmos_chains.get(&chain_name).cloned()
+    }
+
+    async fn log(&mut self, level: LogLevel, message: String) {
+        self.logs.push((level, message));
+    }
+}
@@ -0,0 +1,203 @@
+use alloy_primitives::{address, b256, Bytes, B256, I256, U256};
+use shared::{
+    abi::{feed_key, request_hash, round_id, PriceFeed, RoundData, TriggerInfo},
+    codec::{decode_abi, encode_abi, CodecError, FeedCodec},
+    price_feed::{
+        CircuitBreakerCheck, MarketData, Ohlc, PriceFeedData, PriceFeedOutput, ReferenceCheck,
+    },
+    request::{Asset, AssetSelection, PriceMode},
+};
+
+/// A feed with every optional field set, so a codec that drops one fails the round trip
+fn full_feed() -> PriceFeedData {
+    PriceFeedData {
+        symbol: "ETH".to_string(),
+        timestamp: 1738688100,
+        price: 2712.35,
+        sources: vec!["coingecko".to_string(), "kraken".to_string()],
+        block_height: Some(1234),
+        tx_hash: Some(
+            "0xcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcdcd".to_string(),
+        ),
+        mode: PriceMode::Ohlc,
+        window: Some(3600),
+        ohlc: Some(Ohlc { open: 2650.0, high: 2750.5, low: 2600.25, close: 2712.35 }),
+        market: MarketData {
+            market_cap: Some(327011000000.0),
+            volume_24h: Some(27182600000.0),
+            percent_change_24h: Some(-1.94),
+            circulating_supply: Some(120567000.0),
+            total_supply: Some(120567000.0),
+            rank: Some(2),
+        },
+        reference: Some(ReferenceCheck {
+            source: "chainlink".to_string(),
+            price: 2700.0,
+            deviation_bps: 46,
+            flagged: false,
+        }),
+        circuit_breaker: Some(CircuitBreakerCheck {
+            trigger_id: 6,
+            price: 2700.0,
+            change_percent: 0.46,
+            flagged: false,
+        }),
+    }
+}
+
+/// A feed with nothing optional, like a spot price from a raw trigger
+fn bare_feed() -> PriceFeedData {
+    PriceFeedData {
+        symbol: "BTC".to_string(),
+        timestamp: 1738688160,
+        price: 97241.0,
+        sources: Vec::new(),
+        block_height: None,
+        tx_hash: None,
+        mode: PriceMode::Spot,
+        window: None,
+        ohlc: None,
+        market: MarketData::default(),
+        reference: None,
+        circuit_breaker: None,
+    }
+}
+
+fn outputs() -> Vec<PriceFeedOutput> {
+    vec![
+        PriceFeedOutput::Single(Box::new(full_feed())),
+        PriceFeedOutput::Single(Box::new(bare_feed())),
+        PriceFeedOutput::Batch(vec![full_feed(), bare_feed()]),
+    ]
+}
+
+#[test]
+fn feed_codecs_round_trip() {
+    for codec in FeedCodec::ALL {
+        for output in outputs() {
+            let data = codec.encode(&output).unwrap();
+            assert_eq!(codec.decode(&data).unwrap(), output, "{codec:?}");
+        }
+    }
+}
+
+#[test]
+fn json_is_unchanged() {
+    let output = PriceFeedOutput::Single(Box::new(bare_feed()));
+
+    assert_eq!(
+        FeedCodec::Json.encode(&output).unwrap(),
+        br#"{"symbol":"BTC","timestamp":1738688160,"price":97241.0,"sources":[]}"#
+    );
+}
+
+#[test]
+fn binary_codecs_are_smaller() {
+    for output in outputs() {
+        let json = FeedCodec::Json.encode(&output).unwrap().len();
+        let cbor = FeedCodec::Cbor.encode(&output).unwrap().len();
+        let bincode = FeedCodec::Bincode.encode(&output).unwrap().len();
+        assert!(cbor < json, "cbor {cbor} json {json}");
+        assert!(bincode < cbor, "bincode {bincode} cbor {cbor}");
+    }
+}
+
+#[test]
+fn feed_codecs_reject_each_other() {
+    let output = PriceFeedOutput::Batch(vec![full_feed(), bare_feed()]);
+
+    for from in FeedCodec::ALL {
+        let data = from.encode(&output).unwrap();
+        for to in FeedCodec::ALL.into_iter().filter(|to| *to != from) {
+            assert!(to.decode(&data).is_err(), "{to:?} read {from:?}");
+        }
+    }
+}
+
+#[test]
+fn bincode_needs_a_valid_tx_hash() {
+    let mut feed = bare_feed();
+    feed.tx_hash = Some("0x1234".to_string());
+
+    assert_eq!(
+        FeedCodec::Bincode.encode(&PriceFeedOutput::Single(Box::new(feed))),
+        Err(CodecError::InvalidTxHash("0x1234".to_string()))
+    );
+}
+
+fn price_feed(asset_id: u64, price: u64) -> PriceFeed {
+    PriceFeed {
+        assetId: asset_id,
+        price: U256::from(price),
+        decimals: 8,
+        timestamp: 1738688100,
+        blockHeight: 1234,
+        txHash: Default::default(),
+        mode: PriceMode::Twap.into(),
+        window: 3600,
+        open: U256::ZERO,
+        high: U256::ZERO,
+        low: U256::ZERO,
+    }
+}
+
+#[test]
+fn abi_round_trips() {
+    let single = AssetSelection::Single(Asset::Id(1027));
+    let batch = AssetSelection::Batch(vec![Asset::Id(1027), Asset::Id(1)]);
+
+    let feeds = vec![price_feed(1027, 271235000000)];
+    let decoded = decode_abi::<PriceFeed>(&encode_abi(feeds, &single), &single).unwrap();
+    assert_eq!(decoded.len(), 1);
+    assert_eq!(decoded[0].assetId, 1027);
+    assert_eq!(decoded[0].price, U256::from(271235000000u64));
+    assert_eq!(PriceMode::from(decoded[0].mode), PriceMode::Twap);
+
+    let feeds = vec![price_feed(1027, 271235000000), price_feed(1, 9724100000000)];
+    let decoded = decode_abi::<PriceFeed>(&encode_abi(feeds, &batch), &batch).unwrap();
+    assert_eq!(decoded.iter().map(|feed| feed.assetId).collect::<Vec<_>>(), vec![1027, 1]);
+
+    let round = |answer: i64| RoundData {
+        roundId: round_id(9),
+        answer: I256::try_from(answer).unwrap(),
+        startedAt: U256::from(1738684500),
+        updatedAt: U256::from(1738688100),
+        answeredInRound: round_id(9),
+        feedKey: B256::ZERO,
+    };
+    let rounds = vec![round(271235000000), round(9724100000000)];
+    let decoded = decode_abi::<RoundData>(&encode_abi(rounds, &batch), &batch).unwrap();
+    assert_eq!(decoded.len(), 2);
+    assert_eq!(decoded[1].answer, I256::try_from(9724100000000i64).unwrap());
+    assert_eq!(decoded[0].roundId, round_id(9));
+
+    // a batch isn't a single feed
+    let data = encode_abi(vec![price_feed(1027, 1), price_feed(1, 1)], &batch);
+    assert!(decode_abi::<PriceFeed>(&data, &single).is_err());
+}
+
+/// The same vector as `testRequestHash` in `test/WavsSubmit.t.sol`, so the component and
+/// `SimpleSubmit.requestHash` can't drift apart
+#[test]
+fn request_hash_matches_solidity() {
+    let trigger_info = TriggerInfo {
+        triggerId: 1,
+        creator: address!("f39Fd6e51aad88F6F4ce6aB8827279cffFb92266"),
+        data: Bytes::from_static(b"data1"),
+    };
+
+    assert_eq!(
+        request_hash(31337, address!("5FbDB2315678afecb367f032d93F642f64180aa3"), &trigger_info),
+        b256!("f523763e58c9f9e915dd68c92761fa31e986cc467258226aa45c8b384618eff0")
+    );
+}
+
+/// The same vector as `testFeedKey` in `test/WavsSubmit.t.sol`, the key Chainlink rounds are
+/// published with and `SimpleSubmit` keeps them under
+#[test]
+fn feed_key_matches_solidity() {
+    assert_eq!(
+        feed_key(1027, "usd", PriceMode::Twap, 8),
+        b256!("09ea1b62f7f8d61f8305b6b7e8b445b04dddb0f8fa8182bb8704b369692b8147")
+    );
+}
@@ -0,0 +1,139 @@
+use alloy_primitives::{address, Address, Bytes, LogData, B256};
+use alloy_sol_types::SolValue;
+use serde_json::{json, Value};
+use shared::{
+    abi::{DataWithId, ErrorCode, RelayMessage, ResultEncoding, TriggerInfo},
+    relay::RelayRequest,
+};
+use test_host::{
+    new_trigger_log, EthChainConfig, TestHost, TriggerAction, LOCAL_CHAIN, TRIGGER_BLOCK,
+    TRIGGER_CONTRACT,
+};
+
+const SOURCE_RPC: &str = "http://localhost:8545";
+const CREATOR: Address = address!("f39fd6e51aad88f6f4ce6ab8827279cfffb92266");
+const TX_HASH: B256 = B256::repeat_byte(0xcd);
+
+/// The relay between two anvil chains, `local` (31337) and `local2` (31338), where block 42 on
+/// `local` has an unrelated trigger at log index 2 and then the `triggers`
+fn relay(triggers: &[(u64, &RelayRequest)]) -> Option<TestHost> {
+    let chain = |chain_id: &str, endpoint: &str| EthChainConfig {
+        chain_id: chain_id.to_string(),
+        ws_endpoint: None,
+        http_endpoint: Some(endpoint.to_string()),
+    };
+    let logs: Vec<Value> = [(6, &RelayRequest::new(b"other".to_vec()))]
+        .iter()
+        .chain(triggers)
+        .enumerate()
+        .map(|(i, (trigger_id, req))| {
+            log(&new_trigger_log(&trigger_info(*trigger_id, req)), i as u64 + 2)
+        })
+        .collect();
+    let host = TestHost::compiled("cross_chain_relay")?
+        .with_eth_chain("local", chain("31337", SOURCE_RPC))
+        .with_eth_chain("local2", chain("31338", "http://localhost:8645"))
+        .with_json_rpc(SOURCE_RPC, move |method, params| match method {
+            "eth_getLogs" if params[0]["fromBlock"] == "0x2a" => Ok(json!(&logs)),
+            "eth_getLogs" => Ok(json!([])),
+            _ => Err(format!("unexpected {method}")),
+        });
+    Some(host)
+}
+
+fn request() -> RelayRequest {
+    RelayRequest::new(b"hello".to_vec()).with_destination("local2")
+}
+
+fn trigger_info(trigger_id: u64, req: &RelayRequest) -> TriggerInfo {
+    TriggerInfo { creator: CREATOR, ..test_host::trigger_info(trigger_id, req.encode()) }
+}
+
+fn log(data: &LogData, log_index: u64) -> Value {
+    json!({
+        "address": TRIGGER_CONTRACT.to_string(),
+        "topics": data.topics(),
+        "data": Bytes::from(data.data.to_vec()),
+        "blockHash": B256::repeat_byte(0xab),
+        "blockNumber": format!("{TRIGGER_BLOCK:#x}"),
+        "transactionHash": TX_HASH,
+        "transactionIndex": "0x0",
+        "logIndex": format!("{log_index:#x}"),
+        "removed": false,
+    })
+}
+
+fn new_trigger(trigger_id: u64, req: &RelayRequest) -> TriggerAction {
+    TriggerAction::new_trigger_on(LOCAL_CHAIN, &trigger_info(trigger_id, req))
+}
+
+fn published(output: test_host::RunOutput) -> DataWithId {
+    DataWithId::abi_decode(&output.result.unwrap(), true).unwrap()
+}
+
+#[tokio::test]
+async fn relays_the_payload_with_its_source_log() {
+    let req = request();
+    let Some(host) = relay(&[(7, &req)]) else { return };
+
+    let data = published(host.run(new_trigger(7, &req)).await.unwrap());
+    assert_eq!(data.triggerId, 7);
+    assert!(matches!(data.error, ErrorCode::None));
+    assert!(matches!(data.encoding, ResultEncoding::RelayMessage));
+
+    let message = RelayMessage::abi_decode(&data.data, true).unwrap();
+    assert_eq!(message.sourceChainId, 31337);
+    assert_eq!(message.sourceBlock, TRIGGER_BLOCK);
+    assert_eq!(message.sourceBlockHash, B256::repeat_byte(0xab));
+    // told apart from the other trigger in the block
+    assert_eq!(message.logIndex, 3);
+    assert_eq!(message.sourceTrigger, TRIGGER_CONTRACT);
+    assert_eq!(message.sourceTxHash, TX_HASH);
+    assert_eq!(message.creator, CREATOR);
+    assert_eq!(message.destinationChainId, 31338);
+    assert_eq!(message.payload.as_ref(), b"hello");
+}
+
+#[tokio::test]
+async fn service_config_fixes_the_destination() {
+    let open = RelayRequest::new(b"hello".to_vec());
+    let elsewhere = RelayRequest::new(b"hello".to_vec()).with_destination("local");
+    let Some(host) = relay(&[(7, &open), (8, &elsewhere)]) else { return };
+    let host = host.with_env("destination", "local2");
+
+    let data = published(host.run(new_trigger(7, &open)).await.unwrap());
+    assert!(matches!(data.error, ErrorCode::None));
+    assert_eq!(RelayMessage::abi_decode(&data.data, true).unwrap().destinationChainId, 31338);
+
+    let output = host.run(new_trigger(8, &elsewhere)).await.unwrap();
+    assert!(output.requests.is_empty());
+    let data = published(output);
+    assert!(matches!(data.error, ErrorCode::BadInput));
+    assert_eq!(data.data.as_ref(), b"`destination` is fixed by the service config");
+}
+
+#[tokio::test]
+async fn publishes_failures() {
+    let back = RelayRequest::new(Vec::new()).with_destination("local");
+    let unknown = RelayRequest::new(Vec::new()).with_destination("goerli");
+    let Some(host) = relay(&[(7, &back)]) else { return };
+
+    let failure = |data: DataWithId| (data.error, String::from_utf8(data.data.to_vec()).unwrap());
+
+    let (error, message) = failure(published(host.run(new_trigger(7, &back)).await.unwrap()));
+    assert!(matches!(error, ErrorCode::BadInput));
+    assert_eq!(message, "local is the chain the trigger came from");
+
+    let (error, message) = failure(published(host.run(new_trigger(8, &unknown)).await.unwrap()));
+    assert!(matches!(error, ErrorCode::BadInput));
+    assert_eq!(message, "Unknown EVM chain goerli");
+
+    // the endpoint doesn't have the log (yet)
+    let (error, message) = failure(published(host.run(new_trigger(9, &request())).await.unwrap()));
+    assert!(matches!(error, ErrorCode::UpstreamUnavailable));
+    assert_eq!(message, "No response from local");
+
+    // without a log there's nothing to tell replays apart by
+    let output = host.run(TriggerAction::raw(request().encode())).await.unwrap();
+    assert_eq!(output.result.unwrap_err(), "invalid request: Only NewTrigger logs can be relayed");
+}
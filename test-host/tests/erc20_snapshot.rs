@@ -0,0 +1,136 @@
+use alloy_primitives::{address, Address, B256, U256};
+use alloy_sol_types::SolValue;
+use serde_json::{json, Value};
+use shared::{
+    abi::{DataWithId, ErrorCode, ResultEncoding, TokenSnapshot},
+    erc20_snapshot::{BlockTag, SnapshotRequest},
+};
+use test_host::{block_json, trigger_info, TestHost, TriggerAction, MAINNET, TRIGGER_BLOCK};
+
+const RPC: &str = "http://mainnet.rpc";
+const TOKEN: Address = address!("a0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+const RESERVE_A: Address = address!("0000000000000000000000000000000000000a0a");
+const RESERVE_B: Address = address!("0000000000000000000000000000000000000b0b");
+const BLOCK_HASH: B256 = B256::repeat_byte(0xab);
+
+/// The snapshot component on a `mainnet` chain with the trigger block and a finalized block 0x10,
+/// where `TOKEN` has a supply of 1000 and the reserves hold 300 and 200
+fn snapshot() -> Option<TestHost> {
+    let host = TestHost::compiled("erc20_snapshot")?.with_mainnet_rpc(RPC).with_json_rpc(
+        RPC,
+        |method, params| match method {
+            "eth_getBlockByNumber" => match params[0].as_str() {
+                Some("finalized" | "0x10") => Ok(block(0x10)),
+                Some("0x2a") => Ok(block(TRIGGER_BLOCK)),
+                _ => Ok(Value::Null),
+            },
+            "eth_call" => {
+                // every value must be read at a resolved block
+                if params[1] != "0x10" && params[1] != "0x2a" {
+                    return Err("wrong block".to_string());
+                }
+                let input = params[0]["input"].as_str().or(params[0]["data"].as_str()).unwrap();
+                let to: Address = params[0]["to"].as_str().unwrap().parse().unwrap();
+                match (to, &input[..10]) {
+                    (TOKEN, "0x18160ddd") => Ok(uint(1000)),
+                    (TOKEN, "0x70a08231") if input.ends_with("0a0a") => Ok(uint(300)),
+                    (TOKEN, "0x70a08231") if input.ends_with("0b0b") => Ok(uint(200)),
+                    (TOKEN, "0x70a08231") => Ok(uint(0)),
+                    _ => Err("execution reverted".to_string()),
+                }
+            }
+            _ => Err(format!("unexpected {method}")),
+        },
+    );
+    Some(host)
+}
+
+fn block(number: u64) -> Value {
+    block_json(number, BLOCK_HASH, 0x67a9c0e0)
+}
+
+fn uint(value: u64) -> Value {
+    json!(format!("0x{}", alloy_primitives::hex::encode(U256::from(value).abi_encode())))
+}
+
+fn published(output: test_host::RunOutput) -> DataWithId {
+    DataWithId::abi_decode(&output.result.unwrap(), true).unwrap()
+}
+
+/// A request from the `mainnet` trigger contract
+fn mainnet_trigger(trigger_id: u64, req: &SnapshotRequest) -> TriggerAction {
+    TriggerAction::new_trigger_on(MAINNET, &trigger_info(trigger_id, req.encode()))
+}
+
+#[tokio::test]
+async fn publishes_balances_at_the_trigger_block() {
+    let Some(host) = snapshot() else { return };
+
+    let req = SnapshotRequest::new(TOKEN, vec![RESERVE_A, RESERVE_B]).with_chain(MAINNET);
+    let data = published(host.run(mainnet_trigger(1, &req)).await.unwrap());
+    assert_eq!(data.triggerId, 1);
+    assert!(matches!(data.error, ErrorCode::None));
+    assert!(matches!(data.encoding, ResultEncoding::TokenSnapshot));
+
+    let snapshot = TokenSnapshot::abi_decode(&data.data, true).unwrap();
+    assert_eq!(snapshot.token, TOKEN);
+    assert_eq!(snapshot.blockNumber, TRIGGER_BLOCK);
+    assert_eq!(snapshot.blockHash, BLOCK_HASH);
+    assert_eq!(snapshot.totalSupply, U256::from(1000));
+    assert_eq!(snapshot.holders, vec![RESERVE_A, RESERVE_B]);
+    assert_eq!(snapshot.balances, vec![U256::from(300), U256::from(200)]);
+    assert_eq!(snapshot.heldTotal, U256::from(500));
+}
+
+#[tokio::test]
+async fn chain_can_come_from_the_service_config() {
+    let Some(host) = snapshot() else { return };
+
+    let req = SnapshotRequest::new(TOKEN, Vec::new()).at_block(BlockTag::Number(0x10));
+    let output = host.with_env("chain", MAINNET).run(TriggerAction::raw(req.encode())).await;
+
+    let snapshot = TokenSnapshot::abi_decode(&output.unwrap().result.unwrap(), true).unwrap();
+    assert_eq!(snapshot.totalSupply, U256::from(1000));
+    assert!(snapshot.balances.is_empty());
+    assert_eq!(snapshot.heldTotal, U256::ZERO);
+}
+
+#[tokio::test]
+async fn publishes_failures() {
+    let Some(host) = snapshot() else { return };
+
+    let failure = |data: DataWithId| (data.error, String::from_utf8(data.data.to_vec()).unwrap());
+
+    let not_a_token = SnapshotRequest::new(RESERVE_A, vec![RESERVE_B]).with_chain(MAINNET);
+    let (error, message) =
+        failure(published(host.run(mainnet_trigger(2, &not_a_token)).await.unwrap()));
+    assert!(matches!(error, ErrorCode::BadInput));
+    assert_eq!(message, format!("{} doesn't answer totalSupply() on mainnet", RESERVE_A));
+
+    let future = SnapshotRequest::new(TOKEN, Vec::new())
+        .with_chain(MAINNET)
+        .at_block(BlockTag::Number(0x20));
+    let (error, message) =
+        failure(published(host.run(TriggerAction::new_trigger(3, future.encode())).await.unwrap()));
+    assert!(matches!(error, ErrorCode::BadInput));
+    assert_eq!(message, "No block 32 on mainnet");
+
+    let unknown =
+        SnapshotRequest::new(TOKEN, Vec::new()).with_chain("goerli").at_block(BlockTag::Finalized);
+    let (error, message) = failure(published(
+        host.run(TriggerAction::new_trigger(4, unknown.encode())).await.unwrap(),
+    ));
+    assert!(matches!(error, ErrorCode::BadInput));
+    assert_eq!(message, "No RPC endpoint for chain goerli");
+
+    // no block of mainnet is the trigger's one
+    let other_chain = SnapshotRequest::new(TOKEN, Vec::new()).with_chain(MAINNET);
+    let (error, message) = failure(published(
+        host.run(TriggerAction::new_trigger(5, other_chain.encode())).await.unwrap(),
+    ));
+    assert!(matches!(error, ErrorCode::BadInput));
+    assert_eq!(
+        message,
+        "mainnet can only be read for triggers from it, to read it at the trigger block"
+    );
+}
@@ -0,0 +1,878 @@
+use alloy_primitives::{hex, Address, B256, I256, U256};
+use alloy_sol_types::SolValue;
+use shared::{
+    abi::{
+        asset_key, feed_key, request_hash, round_id, round_trigger_id, to_fixed_point, DataWithId,
+        ErrorCode, ResultEncoding, RoundData, ASSET_PRICE_DECIMALS,
+    },
+    cosmos::{CosmosTriggerResult, NEW_TRIGGER_EVENT},
+    error::OracleError,
+    price_feed::{
+        AssetMetadata, CircuitBreakerCheck, Ohlc, PriceFeedData, PriceFeedOutput, ReferenceCheck,
+    },
+    request::{OutputEncoding, PriceMode, PriceRequest, PriceRequestOptions},
+};
+use std::time::{SystemTime, UNIX_EPOCH};
+use test_host::{
+    block_json, trigger_info, CosmosChainConfig, EthChainConfig, HttpFixture, LogLevel, TestHost,
+    TriggerAction, LOCAL_CHAIN, LOCAL_CHAIN_ID, MAINNET, TRIGGER_BLOCK, TRIGGER_CONTRACT,
+};
+
+const COINGECKO: &str = "https://api.coingecko.com/api/v3/simple/price";
+const COINGECKO_MARKET_CHART: &str = "https://api.coingecko.com/api/v3/coins/ethereum/market_chart";
+const COINMARKETCAP_PRO: &str = "https://pro-api.coinmarketcap.com/v2/cryptocurrency/quotes/latest";
+
+const LOCAL_RPC: &str = "http://local.rpc";
+
+/// 2025-02-04T17:46:41Z, when a block long before the tests was made
+const BLOCK_TIME: u64 = 1738691201;
+
+/// The oracle, limited to CoinGecko so a single fixture answers every request. The quote is
+/// from `time`, and so is the trigger block of `NewTrigger` logs on the local chain.
+fn oracle_at(time: u64) -> Option<TestHost> {
+    let body = format!(
+        r#"{{"ethereum":{{"usd":2712.345678,"usd_market_cap":327011455337.1,"last_updated_at":{time}}}}}"#
+    );
+
+    Some(
+        TestHost::compiled("eth_price_oracle")?
+            .with_env("sources", "coingecko")
+            .with_fixture(COINGECKO, HttpFixture::json(body))
+            .with_eth_chain(
+                LOCAL_CHAIN,
+                EthChainConfig {
+                    chain_id: LOCAL_CHAIN_ID.to_string(),
+                    ws_endpoint: None,
+                    http_endpoint: Some(LOCAL_RPC.to_string()),
+                },
+            )
+            .with_json_rpc(LOCAL_RPC, move |method, params| trigger_block(method, params, time)),
+    )
+}
+
+/// The oracle with a quote from now
+fn oracle() -> Option<TestHost> {
+    oracle_at(SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs())
+}
+
+/// Answers the lookups for a `NewTrigger` log in [`TRIGGER_BLOCK`], made at `time`. The log
+/// isn't found, so its transaction is left out of the result.
+fn trigger_block(
+    method: &str,
+    params: &serde_json::Value,
+    time: u64,
+) -> Result<serde_json::Value, String> {
+    match method {
+        "eth_getBlockByNumber" if params[0] == format!("{TRIGGER_BLOCK:#x}") => {
+            Ok(block_json(TRIGGER_BLOCK, B256::ZERO, time))
+        }
+        "eth_getLogs" => Ok(serde_json::json!([])),
+        _ => Err(format!("unexpected {method} {params}")),
+    }
+}
+
+const COSMOS_RPC: &str = "http://layer.rpc";
+
+fn new_cosmos_trigger(attributes: &[(&str, &str)]) -> TriggerAction {
+    TriggerAction::cosmos_event(
+        "layer-local",
+        "layer1qg5ega6dykkxc307y25pecuufrjkxkaggkkxh7nad0vhyhtuhw3s5c8ndu",
+        "layer",
+        NEW_TRIGGER_EVENT,
+        attributes,
+        1234,
+    )
+}
+
+#[tokio::test]
+async fn raw_trigger_returns_json_price_feed() {
+    let Some(host) = oracle() else { return };
+
+    let output = host
+        .run(TriggerAction::raw(PriceRequest::new("ETH".parse().unwrap()).encode()))
+        .await
+        .unwrap();
+
+    let feed: PriceFeedData = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    assert_eq!(feed.symbol, "ETH");
+    assert_eq!(feed.price, 2712.35);
+    assert_eq!(feed.sources, vec!["coingecko"]);
+    assert_eq!(feed.timestamp % 60, 0);
+    assert_eq!(feed.block_height, None);
+    assert!(output.requests.iter().all(|url| url.starts_with(COINGECKO)));
+}
+
+#[tokio::test]
+async fn quote_without_a_timestamp_is_a_parse_failure() {
+    let Some(host) = TestHost::compiled("eth_price_oracle") else { return };
+
+    // a missing time isn't filled in with the operator's clock
+    let body = r#"{"ethereum":{"usd":2712.345678,"usd_market_cap":327011455337.1}}"#;
+    let output = host
+        .with_env("sources", "coingecko")
+        .with_fixture(COINGECKO, HttpFixture::json(body))
+        .run(TriggerAction::raw(PriceRequest::new("ETH".parse().unwrap()).encode()))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        output.result.unwrap_err(),
+        OracleError::ParseFailure("ETH: Unusable response from coingecko".to_string()).to_string()
+    );
+}
+
+#[tokio::test]
+async fn eth_trigger_publishes_data_with_id() {
+    let Some(host) = oracle() else { return };
+
+    let req = PriceRequest::new("ETH".parse().unwrap());
+    let output = host.run(TriggerAction::new_trigger(7, req.encode())).await.unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert_eq!(data.triggerId, 7);
+    assert!(matches!(data.error, ErrorCode::None));
+    assert!(matches!(data.encoding, ResultEncoding::Json));
+    // what `getTrigger(7)` on the trigger contract hashes to
+    assert_eq!(
+        data.requestHash,
+        request_hash(LOCAL_CHAIN_ID, TRIGGER_CONTRACT, &trigger_info(7, req.encode()))
+    );
+
+    // the trigger log isn't found, so neither is its transaction
+    let feed: PriceFeedData = serde_json::from_slice(&data.data).unwrap();
+    assert_eq!(feed.block_height, Some(42));
+    assert_eq!(feed.tx_hash, None);
+}
+
+#[tokio::test]
+async fn chainlink_encoding_publishes_a_round() {
+    let Some(host) = oracle() else { return };
+
+    let req = PriceRequest::new("ETH".parse().unwrap()).with_options(PriceRequestOptions {
+        encoding: OutputEncoding::Chainlink,
+        ..Default::default()
+    });
+    let output = host.run(TriggerAction::new_trigger(9, req.encode())).await.unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert!(matches!(data.encoding, ResultEncoding::RoundData));
+    let round = RoundData::abi_decode(&data.data, true).unwrap();
+    assert_eq!(round.roundId, round_id(9));
+    assert_eq!(round_trigger_id(round.roundId), 9);
+    assert_eq!(round.answeredInRound, round.roundId);
+    // 2712.35 with 8 decimals
+    assert_eq!(round.answer.to_string(), "271235000000");
+    assert_eq!(round.startedAt, round.updatedAt);
+    assert!(round_id(10) > round.roundId);
+    // other assets, quotes, modes and decimals are other feeds with rounds of the same ID
+    assert_eq!(round.feedKey, feed_key(1027, "USD", PriceMode::Spot, 8));
+}
+
+#[tokio::test]
+async fn compact_encodings_read_back_with_the_shared_codecs() {
+    let Some(host) = oracle() else { return };
+
+    for encoding in [OutputEncoding::Cbor, OutputEncoding::Bincode] {
+        let req = PriceRequest::new("ETH".parse().unwrap())
+            .with_options(PriceRequestOptions { encoding, ..Default::default() });
+        let output = host.run(TriggerAction::new_trigger(7, req.encode())).await.unwrap();
+
+        let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+        assert_eq!(data.encoding as u8, encoding.result_encoding(&req.assets) as u8);
+        let feeds = encoding.feed_codec().unwrap().decode(&data.data).unwrap().into_vec();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].symbol, "ETH");
+        assert_eq!(feeds[0].price, 2712.35);
+        assert_eq!(feeds[0].block_height, Some(42));
+    }
+}
+
+#[tokio::test]
+async fn eth_trigger_publishes_failures() {
+    let Some(host) = oracle() else { return };
+
+    let output = host
+        .run(TriggerAction::new_trigger(8, PriceRequest::new("NOPE".parse().unwrap()).encode()))
+        .await
+        .unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert_eq!(data.triggerId, 8);
+    assert!(matches!(data.error, ErrorCode::BadInput));
+    assert!(matches!(data.encoding, ResultEncoding::None));
+    assert_eq!(String::from_utf8(data.data.to_vec()).unwrap(), "Unknown asset symbol: NOPE");
+    assert!(!output.requests.iter().any(|url| url.starts_with(COINGECKO)));
+    // failures are bound to their request too
+    assert_eq!(
+        data.requestHash,
+        request_hash(
+            LOCAL_CHAIN_ID,
+            TRIGGER_CONTRACT,
+            &trigger_info(8, PriceRequest::new("NOPE".parse().unwrap()).encode())
+        )
+    );
+}
+
+#[tokio::test]
+async fn quote_age_is_measured_at_the_trigger_block() {
+    let Some(host) = oracle_at(BLOCK_TIME) else { return };
+    let req = PriceRequest::new("ETH".parse().unwrap());
+
+    // long ago, but as new as the block
+    let output = host.run(TriggerAction::new_trigger(7, req.encode())).await.unwrap();
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert!(matches!(data.error, ErrorCode::None));
+
+    // 700 seconds older than the block, however late the operator runs
+    let later_rpc = "http://later.rpc";
+    let output = oracle_at(BLOCK_TIME)
+        .unwrap()
+        .with_eth_chain(
+            LOCAL_CHAIN,
+            EthChainConfig {
+                chain_id: LOCAL_CHAIN_ID.to_string(),
+                ws_endpoint: None,
+                http_endpoint: Some(later_rpc.to_string()),
+            },
+        )
+        .with_json_rpc(later_rpc, |method, params| trigger_block(method, params, BLOCK_TIME + 700))
+        .run(TriggerAction::new_trigger(7, req.encode()))
+        .await
+        .unwrap();
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert_eq!(
+        OracleError::from_parts(data.error, String::from_utf8(data.data.to_vec()).unwrap()),
+        Some(OracleError::StaleData("ETH: Every quote is older than 600 seconds".to_string()))
+    );
+}
+
+#[tokio::test]
+async fn eth_trigger_without_chain_id_publishes_an_error() {
+    let Some(host) = oracle() else { return };
+
+    let output = host
+        .run(TriggerAction::new_trigger_on(
+            MAINNET,
+            &trigger_info(7, PriceRequest::new("ETH".parse().unwrap()).encode()),
+        ))
+        .await
+        .unwrap();
+
+    // the requester hears back, but nothing is bound to a request it can't be checked against
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert_eq!(data.triggerId, 7);
+    assert!(matches!(data.error, ErrorCode::BadInput));
+    assert_eq!(String::from_utf8_lossy(&data.data), "No chain ID for mainnet in the WAVS config");
+    assert_eq!(data.requestHash, B256::ZERO);
+    assert!(output.requests.is_empty());
+}
+
+#[tokio::test]
+async fn cosmos_trigger_decodes_request_attributes() {
+    let Some(host) = oracle_at(BLOCK_TIME) else { return };
+
+    let output = host
+        .with_cosmos_chain(
+            "layer-local",
+            CosmosChainConfig {
+                chain_id: "layer-local".to_string(),
+                rpc_endpoint: Some(COSMOS_RPC.to_string()),
+                grpc_endpoint: None,
+                grpc_web_endpoint: None,
+                gas_price: 0.025,
+                gas_denom: "ulayer".to_string(),
+                bech32_prefix: "layer".to_string(),
+            },
+        )
+        .with_fixture(
+            &format!("{COSMOS_RPC}/block?height=1234"),
+            HttpFixture::json(
+                r#"{"result":{"block":{"header":{"height":"1234","time":"2025-02-04T17:46:41.612Z"}}}}"#,
+            ),
+        )
+        .run(new_cosmos_trigger(&[
+            (
+                "_contract_address",
+                "layer1qg5ega6dykkxc307y25pecuufrjkxkaggkkxh7nad0vhyhtuhw3s5c8ndu",
+            ),
+            ("trigger_id", "9"),
+            ("asset", "eth"),
+            ("significant_digits", "4"),
+        ]))
+        .await
+        .unwrap();
+
+    let result: CosmosTriggerResult = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    assert_eq!(result.trigger_id, 9);
+    assert_eq!(result.error, None);
+
+    let feed: PriceFeedData = serde_json::from_slice(&result.into_result().unwrap()).unwrap();
+    assert_eq!(feed.symbol, "ETH");
+    assert_eq!(feed.price, 2712.0);
+    assert_eq!(feed.block_height, Some(1234));
+    assert_eq!(feed.tx_hash, None);
+}
+
+#[tokio::test]
+async fn cosmos_trigger_publishes_failures() {
+    let Some(host) = oracle() else { return };
+
+    let output = host
+        .run(new_cosmos_trigger(&[("trigger_id", "10"), ("asset", "ETH"), ("decimals", "many")]))
+        .await
+        .unwrap();
+
+    let result: CosmosTriggerResult = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    assert_eq!(result.trigger_id, 10);
+    assert_eq!(
+        result.into_result(),
+        Err(OracleError::BadInput("Invalid `decimals` attribute".to_string()))
+    );
+    assert!(output.requests.is_empty());
+}
+
+#[tokio::test]
+async fn cosmos_trigger_without_id_fails_the_run() {
+    let Some(host) = oracle() else { return };
+
+    let output = host.run(new_cosmos_trigger(&[("asset", "ETH")])).await.unwrap();
+
+    assert_eq!(output.result.unwrap_err(), "Missing trigger_id attribute");
+}
+
+#[tokio::test]
+async fn logs_through_the_host_without_secrets() {
+    let Some(host) = oracle() else { return };
+
+    // an upstream error that echoes the API key back
+    let body =
+        r#"{"status":{"error_code":1001,"error_message":"API key test-secret-key is invalid"}}"#;
+    let output = host
+        .with_env("sources", "coingecko,coinmarketcap")
+        .with_env("WAVS_ENV_CMC_API_KEY", "test-secret-key")
+        .with_fixture(COINMARKETCAP_PRO, HttpFixture::json(body))
+        .run(TriggerAction::raw(PriceRequest::new("ETH".parse().unwrap()).encode()))
+        .await
+        .unwrap();
+
+    assert!(output.result.is_ok());
+    assert!(output.stdout.is_empty());
+    assert!(output.logs.iter().all(|(_, message)| !message.contains("test-secret-key")));
+
+    let logged = |level: fn(&LogLevel) -> bool, text: &str| {
+        output.logs.iter().any(|(l, message)| level(l) && message.contains(text))
+    };
+    assert!(logged(|l| matches!(l, LogLevel::Info), "request: "));
+    assert!(logged(|l| matches!(l, LogLevel::Debug), "api.coingecko.com"));
+    assert!(logged(
+        |l| matches!(l, LogLevel::Warn),
+        "coinmarketcap returned an unusable response for ETH: API key [REDACTED] is invalid"
+    ));
+    assert!(logged(|l| matches!(l, LogLevel::Info), "publishing"));
+}
+
+/// A CoinGecko market chart with prices at the given offsets from now, in seconds
+fn market_chart(points: &[(i64, f64)]) -> HttpFixture {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() as i64;
+    let prices: Vec<String> = points
+        .iter()
+        .map(|(offset, price)| format!("[{},{}]", (now + offset) * 1000, price))
+        .collect();
+    HttpFixture::json(format!(r#"{{"prices":[{}]}}"#, prices.join(",")))
+}
+
+fn range_request(mode: PriceMode) -> TriggerAction {
+    let mut req = PriceRequest::new("ETH".parse().unwrap());
+    req.options.mode = mode;
+    TriggerAction::raw(req.encode())
+}
+
+#[tokio::test]
+async fn twap_weights_prices_by_time() {
+    let Some(host) = oracle() else { return };
+
+    // the first point is before the hour long window, so it holds until the second one, and the
+    // last one keeps the quote fresh
+    let chart = market_chart(&[(-7200, 1000.0), (-3000, 100.0), (-1800, 200.0), (-60, 200.0)]);
+    let output = host
+        .with_fixture(COINGECKO_MARKET_CHART, chart)
+        .run(range_request(PriceMode::Twap))
+        .await
+        .unwrap();
+
+    let feed: PriceFeedData = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    assert_eq!(feed.mode, PriceMode::Twap);
+    assert_eq!(feed.window, Some(3600));
+    assert_eq!(feed.ohlc, None);
+    // 1000 from the start of the window (about 600 seconds), 100 for 1200 seconds, then 200 until
+    // the end of the window (about 1800 seconds)
+    assert!((295.0..320.0).contains(&feed.price), "twap {}", feed.price);
+    assert_eq!(output.requests.len(), 1);
+}
+
+#[tokio::test]
+async fn range_window_ends_at_the_trigger_block() {
+    let Some(host) = oracle() else { return };
+
+    // the block is 20 minutes old, prices since then are left out
+    let block_time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs() - 1200;
+    let chart = market_chart(&[
+        (-7200, 100.0),
+        (-3000, 100.0),
+        (-2000, 100.0),
+        (-600, 1000.0),
+        (-60, 1000.0),
+    ]);
+    let mut req = PriceRequest::new("ETH".parse().unwrap());
+    req.options.mode = PriceMode::Twap;
+    let output = host
+        .with_env("max_quote_age", "0")
+        .with_fixture(COINGECKO_MARKET_CHART, chart)
+        .with_mainnet_rpc(RPC)
+        .with_json_rpc(RPC, move |method, params| trigger_block(method, params, block_time))
+        .run(TriggerAction::new_trigger_on(MAINNET, &trigger_info(7, req.encode())))
+        .await
+        .unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert!(matches!(data.error, ErrorCode::None));
+    let feed: PriceFeedData = serde_json::from_slice(&data.data).unwrap();
+    assert_eq!(feed.price, 100.0);
+}
+
+#[tokio::test]
+async fn chainlink_round_starts_at_the_first_point_in_the_window() {
+    let Some(host) = oracle() else { return };
+
+    let chart = market_chart(&[(-7200, 1000.0), (-3000, 100.0), (-1800, 200.0), (-60, 200.0)]);
+    let mut req = PriceRequest::new("ETH".parse().unwrap());
+    req.options.mode = PriceMode::Twap;
+    req.options.encoding = OutputEncoding::Chainlink;
+    req.options.timestamp_window = Some(0);
+    let output = host
+        .with_fixture(COINGECKO_MARKET_CHART, chart)
+        .run(TriggerAction::raw(req.encode()))
+        .await
+        .unwrap();
+
+    let round = RoundData::abi_decode(&output.result.unwrap(), true).unwrap();
+    // not an hour before the last point, where the window starts
+    assert_eq!(round.updatedAt - round.startedAt, U256::from(2940));
+    assert_eq!(round.feedKey, feed_key(1027, "USD", PriceMode::Twap, 8));
+}
+
+#[tokio::test]
+async fn ohlc_covers_the_window() {
+    let Some(host) = oracle() else { return };
+
+    let chart = market_chart(&[
+        (-7200, 1000.0),
+        (-3000, 100.0),
+        (-1800, 250.0),
+        (-1200, 50.0),
+        (-120, 180.0),
+    ]);
+    let output = host
+        .with_fixture(COINGECKO_MARKET_CHART, chart)
+        .run(range_request(PriceMode::Ohlc))
+        .await
+        .unwrap();
+
+    let feed: PriceFeedData = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    assert_eq!(feed.mode, PriceMode::Ohlc);
+    assert_eq!(feed.price, 180.0);
+    assert_eq!(feed.ohlc, Some(Ohlc { open: 100.0, high: 250.0, low: 50.0, close: 180.0 }));
+}
+
+#[tokio::test]
+async fn range_needs_two_points_in_the_window() {
+    let Some(host) = oracle() else { return };
+
+    let output = host
+        .with_fixture(COINGECKO_MARKET_CHART, market_chart(&[(-7200, 1000.0), (-600, 100.0)]))
+        .run(range_request(PriceMode::Twap))
+        .await
+        .unwrap();
+
+    assert_eq!(
+        output.result.unwrap_err(),
+        OracleError::ParseFailure("ETH: Unusable response from coingecko".to_string()).to_string()
+    );
+}
+
+#[tokio::test]
+async fn kind_picks_the_handler_and_output() {
+    let Some(host) = oracle() else { return };
+    let chart = market_chart(&[(-3000, 100.0), (-1800, 200.0), (-60, 200.0)]);
+    let host = host.with_fixture(COINGECKO_MARKET_CHART, chart);
+    let eth = || PriceRequest::new("ETH".parse().unwrap());
+
+    // a single asset, published as a batch of one
+    let output = host.run(TriggerAction::raw(eth().with_kind("batch").encode())).await.unwrap();
+    let feeds = match serde_json::from_slice(&output.result.unwrap()).unwrap() {
+        PriceFeedOutput::Batch(feeds) => feeds,
+        PriceFeedOutput::Single(_) => panic!("expected a batch"),
+    };
+    assert_eq!(feeds.len(), 1);
+    assert_eq!(feeds[0].price, 2712.35);
+
+    let output = host.run(TriggerAction::raw(eth().with_kind("twap").encode())).await.unwrap();
+    let feed: PriceFeedData = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    assert_eq!(feed.mode, PriceMode::Twap);
+    assert_eq!(feed.window, Some(3600));
+
+    let output = host.run(TriggerAction::raw(eth().with_kind("Metadata").encode())).await.unwrap();
+    let result = output.result.unwrap();
+    let metadata: AssetMetadata = serde_json::from_slice(&result).unwrap();
+    assert_eq!(metadata.symbol, "ETH");
+    // quantized like the price
+    assert_eq!(metadata.market.market_cap, Some(327011000000.0));
+    assert!(!String::from_utf8(result).unwrap().contains("price"));
+}
+
+#[tokio::test]
+async fn kind_checks_the_request() {
+    let Some(host) = oracle() else { return };
+
+    let batch = PriceRequest::batch(vec!["ETH".parse().unwrap(), "BTC".parse().unwrap()]);
+    let output = host.run(TriggerAction::raw(batch.with_kind("spot").encode())).await.unwrap();
+    assert_eq!(
+        output.result.unwrap_err(),
+        "invalid request: `spot` requests take a single `asset`"
+    );
+
+    let abi = PriceRequest::new("ETH".parse().unwrap())
+        .with_kind("metadata")
+        .with_options(PriceRequestOptions { encoding: OutputEncoding::Abi, ..Default::default() });
+    let output = host.run(TriggerAction::raw(abi.encode())).await.unwrap();
+    assert_eq!(
+        output.result.unwrap_err(),
+        "invalid request: `metadata` requests take the `json` encoding"
+    );
+    assert!(output.requests.is_empty());
+}
+
+#[tokio::test]
+async fn unknown_kind_publishes_unsupported_kind() {
+    let Some(host) = oracle() else { return };
+
+    let req = PriceRequest::new("ETH".parse().unwrap()).with_kind("volatility");
+    let output = host.run(TriggerAction::new_trigger(7, req.encode())).await.unwrap();
+
+    assert!(!output.requests.iter().any(|url| url.starts_with(COINGECKO)));
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert!(matches!(data.error, ErrorCode::UnsupportedKind));
+    assert_eq!(
+        OracleError::from_parts(data.error, String::from_utf8(data.data.to_vec()).unwrap()),
+        Some(OracleError::UnsupportedKind("Unknown request kind volatility".to_string()))
+    );
+
+    let output = host
+        .run(new_cosmos_trigger(&[("trigger_id", "8"), ("asset", "ETH"), ("kind", "volatility")]))
+        .await
+        .unwrap();
+    let result: CosmosTriggerResult = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    assert_eq!(result.error.as_deref(), Some("unsupported_kind"));
+}
+
+const RPC: &str = "http://mainnet.rpc";
+
+/// Registers the `mainnet` chain, whose RPC endpoint answers every call with `output`
+fn with_rpc(host: TestHost, output: &[u8]) -> TestHost {
+    let body = format!(
+        r#"{{"jsonrpc":"2.0","id":0,"result":"0x{}"}}"#,
+        alloy_primitives::hex::encode(output)
+    );
+
+    host.with_mainnet_rpc(RPC).with_fixture(RPC, HttpFixture::json(body))
+}
+
+const REFERENCES: &str = r#"[{"symbol":"ETH","chain":"mainnet","kind":"chainlink","address":"0x5f4ec3df9cbd43714fe2740f5e3616155c5b8419","decimals":8}]"#;
+
+/// `latestRoundData` of a Chainlink aggregator answering `answer` (8 decimals), updated
+/// `age` seconds ago
+fn reference_round(answer: i64, age: u64) -> Vec<u8> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    // (roundId, answer, startedAt, updatedAt, answeredInRound)
+    let updated_at = U256::from(now - age);
+    (U256::ZERO, I256::try_from(answer).unwrap(), updated_at, updated_at, U256::ZERO)
+        .abi_encode_params()
+}
+
+/// The oracle with a Chainlink ETH/USD reference on `mainnet` answering `answer`
+fn oracle_with_reference(answer: i64) -> Option<TestHost> {
+    Some(with_rpc(oracle()?.with_env("references", REFERENCES), &reference_round(answer, 0)))
+}
+
+const SUBMIT_CONTRACT: Address = Address::repeat_byte(2);
+
+/// The oracle with a circuit breaker, where `SimpleSubmit` on `mainnet` holds the latest ETH/USD
+/// spot price as of the trigger block: `previous` published for trigger 6, or none at all
+fn oracle_with_circuit_breaker(previous: Option<f64>) -> Option<TestHost> {
+    let key = asset_key(1027, "USD", PriceMode::Spot);
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let latest = match previous {
+        Some(price) => (6u64, to_fixed_point(price, ASSET_PRICE_DECIMALS).unwrap()),
+        None => (0, U256::ZERO),
+    };
+
+    let host = oracle()?
+        .with_env("circuit_breaker_contract", &SUBMIT_CONTRACT.to_string())
+        .with_mainnet_rpc(RPC)
+        .with_json_rpc(RPC, move |method, params| {
+            if method != "eth_call" {
+                return trigger_block(method, params, now);
+            }
+            // the latest price as of the trigger, not as of whenever the operator runs
+            if params[1] != format!("{:#x}", TRIGGER_BLOCK) {
+                return Err(format!("unexpected {method} {params}"));
+            }
+            let to: Address = params[0]["to"].as_str().unwrap().parse().unwrap();
+            let input = params[0]["input"].as_str().or(params[0]["data"].as_str()).unwrap();
+            match to == SUBMIT_CONTRACT && input.ends_with(&hex::encode(key)) {
+                true => Ok(format!("0x{}", hex::encode(latest.abi_encode_params())).into()),
+                false => Err("execution reverted".to_string()),
+            }
+        });
+    Some(host)
+}
+
+fn eth() -> TriggerAction {
+    TriggerAction::raw(PriceRequest::new("ETH".parse().unwrap()).encode())
+}
+
+#[tokio::test]
+async fn reference_within_threshold_is_published() {
+    let Some(host) = oracle_with_reference(2700_00000000) else { return };
+
+    let output = host.run(eth()).await.unwrap();
+
+    let feed: PriceFeedData = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    assert_eq!(
+        feed.reference,
+        Some(ReferenceCheck {
+            source: "chainlink".to_string(),
+            price: 2700.0,
+            deviation_bps: 46,
+            flagged: false
+        })
+    );
+    assert!(output.requests.iter().any(|url| url.starts_with(RPC)));
+}
+
+#[tokio::test]
+async fn reference_refuses_prices_too_far_from_it() {
+    let Some(host) = oracle_with_reference(2500_00000000) else { return };
+
+    let output = host.run(eth()).await.unwrap();
+
+    assert_eq!(
+        output.result.unwrap_err(),
+        OracleError::OutOfRange("ETH: 849 bps from the chainlink reference, max 300".to_string())
+            .to_string()
+    );
+}
+
+#[tokio::test]
+async fn reference_is_read_at_the_trigger_block() {
+    let Some(host) = oracle() else { return };
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let host = host.with_env("references", REFERENCES).with_mainnet_rpc(RPC).with_json_rpc(
+        RPC,
+        move |method, params| {
+            if method != "eth_call" {
+                return trigger_block(method, params, now);
+            }
+            if params[1] != format!("{:#x}", TRIGGER_BLOCK) {
+                return Err(format!("unexpected {method} {params}"));
+            }
+            Ok(format!("0x{}", hex::encode(reference_round(2700_00000000, 0))).into())
+        },
+    );
+
+    let output = host.run(eth_trigger(7)).await.unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    let feed: PriceFeedData = serde_json::from_slice(&data.data).unwrap();
+    assert_eq!(feed.reference.unwrap().price, 2700.0);
+}
+
+#[tokio::test]
+async fn reference_must_be_on_the_trigger_chain() {
+    let Some(host) = oracle_with_reference(2700_00000000) else { return };
+
+    // the reference is on mainnet, the trigger on the local chain
+    let req = PriceRequest::new("ETH".parse().unwrap());
+    let output = host.run(TriggerAction::new_trigger(7, req.encode())).await.unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert_eq!(
+        OracleError::from_parts(data.error, String::from_utf8(data.data.to_vec()).unwrap()),
+        Some(OracleError::BadInput(
+            "ETH: mainnet can only be read for triggers from it, to read it at the trigger block"
+                .to_string()
+        ))
+    );
+}
+
+#[tokio::test]
+async fn reference_refuses_a_stale_answer() {
+    let Some(host) = oracle() else { return };
+    let host =
+        with_rpc(host.with_env("references", REFERENCES), &reference_round(2700_00000000, 7200));
+
+    let output = host.run(eth()).await.unwrap();
+
+    let e = output.result.unwrap_err();
+    assert!(e.contains("ETH: the chainlink reference is 7"), "{e}");
+    assert!(e.ends_with("s older than the price, max 3600s"), "{e}");
+
+    // a stale answer can only be flagged, however close it is
+    let host =
+        oracle().unwrap().with_env("references", REFERENCES).with_env("reference_action", "flag");
+    let output = with_rpc(host, &reference_round(2700_00000000, 7200)).run(eth()).await.unwrap();
+    let feed: PriceFeedData = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    assert!(feed.reference.unwrap().flagged);
+}
+
+#[tokio::test]
+async fn invalid_references_fail_the_run() {
+    let Some(host) = oracle() else { return };
+
+    let output = host.with_env("references", r#"[{"symbol":"ETH"}]"#).run(eth()).await.unwrap();
+
+    let e = output.result.unwrap_err();
+    assert!(e.starts_with("invalid request: Invalid `references` service config"), "{e}");
+    assert!(output.requests.is_empty());
+}
+
+#[tokio::test]
+async fn invalid_assets_fail_the_run() {
+    let Some(host) = oracle() else { return };
+
+    // a single mapping instead of an array of them
+    let output =
+        host.with_env("assets", r#"{"cmc_id":5805,"symbol":"AVAX"}"#).run(eth()).await.unwrap();
+
+    let e = output.result.unwrap_err();
+    assert!(e.starts_with("invalid request: Invalid `assets` service config"), "{e}");
+    assert!(output.requests.is_empty());
+}
+
+#[tokio::test]
+async fn reference_can_only_flag() {
+    let Some(host) = oracle_with_reference(2500_00000000) else { return };
+
+    let output = host.with_env("reference_action", "flag").run(eth()).await.unwrap();
+
+    let feed: PriceFeedData = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    let reference = feed.reference.unwrap();
+    assert!(reference.flagged);
+    assert_eq!(reference.deviation_bps, 849);
+}
+
+/// An ETH price request from the `mainnet` trigger contract
+fn eth_trigger(trigger_id: u64) -> TriggerAction {
+    let req = PriceRequest::new("ETH".parse().unwrap());
+    TriggerAction::new_trigger_on(MAINNET, &trigger_info(trigger_id, req.encode()))
+}
+
+#[tokio::test]
+async fn circuit_breaker_compares_with_the_latest_price() {
+    let Some(host) = oracle_with_circuit_breaker(Some(2500.0)) else { return };
+
+    let output = host.run(eth_trigger(7)).await.unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    let feed: PriceFeedData = serde_json::from_slice(&data.data).unwrap();
+    assert_eq!(
+        feed.circuit_breaker,
+        Some(CircuitBreakerCheck {
+            trigger_id: 6,
+            price: 2500.0,
+            change_percent: 8.49,
+            flagged: false
+        })
+    );
+    // one call for the one asset, besides looking up the trigger block and transaction
+    assert_eq!(output.requests.iter().filter(|url| url.starts_with(RPC)).count(), 3);
+
+    // which SimpleSubmit keeps as the latest ETH/USD spot price
+    assert_eq!(data.prices.len(), 1);
+    assert_eq!(data.prices[0].assetKey, asset_key(1027, "usd", PriceMode::Spot));
+    assert_eq!(data.prices[0].price, to_fixed_point(2712.35, ASSET_PRICE_DECIMALS).unwrap());
+}
+
+#[tokio::test]
+async fn circuit_breaker_passes_an_asset_without_a_price() {
+    let Some(host) = oracle_with_circuit_breaker(None) else { return };
+
+    let output = host.run(eth_trigger(7)).await.unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert!(matches!(data.error, ErrorCode::None));
+    let feed: PriceFeedData = serde_json::from_slice(&data.data).unwrap();
+    assert_eq!(feed.circuit_breaker, None);
+    assert_eq!(data.prices.len(), 1);
+}
+
+#[tokio::test]
+async fn circuit_breaker_refuses_large_jumps() {
+    let Some(host) = oracle_with_circuit_breaker(Some(2400.0)) else { return };
+
+    let output = host.run(eth_trigger(7)).await.unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert!(matches!(data.error, ErrorCode::OutOfRange));
+    assert_eq!(
+        String::from_utf8(data.data.to_vec()).unwrap(),
+        "ETH: 13.01% from the result for trigger 6, max 10%"
+    );
+    // a refused price doesn't become the latest
+    assert!(data.prices.is_empty());
+}
+
+#[tokio::test]
+async fn circuit_breaker_can_only_flag() {
+    let Some(host) = oracle_with_circuit_breaker(Some(2400.0)) else { return };
+
+    let output = host.with_env("circuit_breaker_action", "flag").run(eth_trigger(7)).await.unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    let feed: PriceFeedData = serde_json::from_slice(&data.data).unwrap();
+    assert!(feed.circuit_breaker.unwrap().flagged);
+}
+
+#[tokio::test]
+async fn circuit_breaker_keeps_flagging_after_a_flagged_price() {
+    let Some(host) = oracle_with_circuit_breaker(Some(2400.0)) else { return };
+
+    let output = host.with_env("circuit_breaker_action", "flag").run(eth_trigger(7)).await.unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    let feed: PriceFeedData = serde_json::from_slice(&data.data).unwrap();
+    assert!(feed.circuit_breaker.unwrap().flagged);
+    // the flagged price doesn't become the latest
+    assert!(data.prices.is_empty());
+
+    // so the next trigger's jump is still compared with the price before it
+    let host = oracle_with_circuit_breaker(Some(2400.0)).unwrap();
+    let output = host.with_env("circuit_breaker_action", "flag").run(eth_trigger(8)).await.unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    let feed: PriceFeedData = serde_json::from_slice(&data.data).unwrap();
+    let check = feed.circuit_breaker.unwrap();
+    assert_eq!(check.trigger_id, 6);
+    assert!(check.flagged);
+}
+
+#[tokio::test]
+async fn circuit_breaker_skips_raw_triggers() {
+    let Some(host) = oracle_with_circuit_breaker(Some(2400.0)) else { return };
+
+    let output = host.run(eth()).await.unwrap();
+
+    let feed: PriceFeedData = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    assert_eq!(feed.circuit_breaker, None);
+    assert!(!output.requests.iter().any(|url| url.starts_with(RPC)));
+}
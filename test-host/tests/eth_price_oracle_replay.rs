@@ -0,0 +1,173 @@
+//! The oracle against recorded upstream responses, see `fixtures/eth_price_oracle/`.
+//! Re-record with `RECORD_FIXTURES=eth_price_oracle/success cargo test -p test-host`.
+
+use alloy_primitives::B256;
+use alloy_sol_types::SolValue;
+use shared::{
+    abi::DataWithId,
+    error::OracleError,
+    price_feed::{MarketData, PriceFeedData},
+    request::{MarketField, PriceRequest, PriceRequestOptions},
+};
+use test_host::{
+    block_json, fixture_file, EthChainConfig, RunOutput, TestHost, TriggerAction, LOCAL_CHAIN,
+    LOCAL_CHAIN_ID, TRIGGER_BLOCK,
+};
+
+const LOCAL_RPC: &str = "http://local.rpc";
+
+/// Just after the newest quote in `success`. Quotes age from the trigger's block time, so this
+/// moves with a re-recording.
+const RECORDED_AT: u64 = 1739197870;
+
+/// The oracle replaying `fixtures/eth_price_oracle/<name>.json`, where triggers are from a block
+/// made at [`RECORDED_AT`]
+fn oracle(name: &str) -> Option<TestHost> {
+    Some(
+        TestHost::compiled("eth_price_oracle")?
+            .with_env("http_backoff_ms", "10")
+            .with_eth_chain(
+                LOCAL_CHAIN,
+                EthChainConfig {
+                    chain_id: LOCAL_CHAIN_ID.to_string(),
+                    ws_endpoint: None,
+                    http_endpoint: Some(LOCAL_RPC.to_string()),
+                },
+            )
+            .with_json_rpc(LOCAL_RPC, |method, params| match method {
+                "eth_getBlockByNumber" if params[0] == format!("{TRIGGER_BLOCK:#x}") => {
+                    Ok(block_json(TRIGGER_BLOCK, B256::ZERO, RECORDED_AT))
+                }
+                "eth_getLogs" => Ok(serde_json::json!([])),
+                _ => Err(format!("unexpected {method} {params}")),
+            })
+            .with_fixture_file(fixture_file(&format!("eth_price_oracle/{name}")))
+            .unwrap(),
+    )
+}
+
+fn trigger(req: PriceRequest) -> TriggerAction {
+    TriggerAction::new_trigger(1, req.encode())
+}
+
+fn btc() -> TriggerAction {
+    trigger(PriceRequest::new("BTC".parse().unwrap()))
+}
+
+/// The published feed, or the error the run published instead
+fn published(output: &RunOutput) -> Result<PriceFeedData, OracleError> {
+    let data = DataWithId::abi_decode(output.result.as_ref().unwrap(), true).unwrap();
+    let message = String::from_utf8(data.data.to_vec()).unwrap();
+    match OracleError::from_parts(data.error, message) {
+        Some(e) => Err(e),
+        None => Ok(serde_json::from_slice(&data.data).unwrap()),
+    }
+}
+
+/// Requests to the price sources, leaving out the trigger block lookups
+fn upstream_requests(output: &RunOutput) -> usize {
+    output.requests.iter().filter(|url| !url.starts_with(LOCAL_RPC)).count()
+}
+
+#[tokio::test]
+async fn replays_every_source() {
+    let Some(host) = oracle("success") else { return };
+
+    let output = host.run(btc()).await.unwrap();
+
+    let feed = published(&output).unwrap();
+    assert_eq!(feed.symbol, "BTC");
+    assert_eq!(feed.sources, vec!["binance", "coingecko", "coinmarketcap", "kraken"]);
+    // the median of 97234.12 and 97236.40, quantized
+    assert_eq!(feed.price, 97235.3);
+    // market data is left out unless it's asked for
+    assert_eq!(feed.market, MarketData::default());
+    assert_eq!(upstream_requests(&output), 4);
+}
+
+#[tokio::test]
+async fn publishes_requested_market_data() {
+    let Some(host) = oracle("success") else { return };
+
+    let req = PriceRequest::new("BTC".parse().unwrap()).with_options(PriceRequestOptions {
+        fields: vec![
+            MarketField::MarketCap,
+            MarketField::PercentChange24h,
+            MarketField::TotalSupply,
+            MarketField::Rank,
+        ],
+        ..Default::default()
+    });
+    let output = host.run(trigger(req)).await.unwrap();
+
+    let feed = published(&output).unwrap();
+    // only CoinMarketCap reported market data when this was recorded, and no rank, quantized
+    assert_eq!(
+        feed.market,
+        MarketData {
+            market_cap: Some(1927350000000.0),
+            percent_change_24h: Some(1.2219),
+            total_supply: Some(19821500.0),
+            ..Default::default()
+        }
+    );
+}
+
+#[tokio::test]
+async fn malformed_body_is_a_parse_failure() {
+    let Some(host) = oracle("malformed") else { return };
+
+    let output = host.with_env("sources", "coinmarketcap").run(btc()).await.unwrap();
+
+    assert_eq!(
+        published(&output).unwrap_err(),
+        OracleError::ParseFailure("BTC: Unusable response from coinmarketcap".to_string())
+    );
+    assert_eq!(upstream_requests(&output), 1);
+}
+
+#[tokio::test]
+async fn http_error_is_retried_then_unavailable() {
+    let Some(host) = oracle("http_error") else { return };
+
+    let output = host.with_env("sources", "coinmarketcap").run(btc()).await.unwrap();
+
+    assert_eq!(
+        published(&output).unwrap_err(),
+        OracleError::UpstreamUnavailable("BTC: No response from coinmarketcap".to_string())
+    );
+    assert_eq!(upstream_requests(&output), 3);
+}
+
+#[tokio::test]
+async fn long_rate_limit_is_not_retried() {
+    let Some(host) = oracle("rate_limited") else { return };
+
+    let output = host.with_env("sources", "coinmarketcap").run(btc()).await.unwrap();
+
+    assert_eq!(
+        published(&output).unwrap_err(),
+        OracleError::UpstreamUnavailable("BTC: Rate limited by coinmarketcap".to_string())
+    );
+    assert_eq!(upstream_requests(&output), 1);
+}
+
+#[tokio::test]
+async fn retries_stop_at_the_deadline() {
+    let Some(host) = oracle("http_error") else { return };
+
+    // the first retry would start after the deadline
+    let output = host
+        .with_env("sources", "coinmarketcap")
+        .with_env("http_backoff_ms", "500")
+        .with_env("http_deadline_ms", "200")
+        .run(btc())
+        .await
+        .unwrap();
+
+    assert_eq!(
+        published(&output).unwrap_err(),
+        OracleError::UpstreamUnavailable("BTC: No response from coinmarketcap".to_string())
+    );
+    assert_eq!(upstream_requests(&output), 1);
+}
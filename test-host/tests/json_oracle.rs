@@ -0,0 +1,200 @@
+use alloy_primitives::{B256, I256, U256};
+use alloy_sol_types::SolValue;
+use serde_json::json;
+use shared::{
+    abi::{DataWithId, ErrorCode, ResultEncoding},
+    json_oracle::{JsonField, JsonOracleOutput, JsonRequest, ValueEncoding, ValueType},
+};
+use test_host::{
+    block_json, trigger_info, HttpFixture, TestHost, TriggerAction, MAINNET, TRIGGER_BLOCK,
+};
+
+const TICKER: &str = "https://api.example.com/ticker";
+const RPC: &str = "http://mainnet.rpc";
+
+/// The oracle, allowed to fetch `TICKER`
+fn oracle() -> Option<TestHost> {
+    let body = r#"{"data":{"price":"2712.345678","change":-1.5,"live":true,"name":"Ether","tags":["l1","pos"]}}"#;
+    Some(
+        TestHost::compiled("json_oracle")?
+            .with_env("allowed_hosts", "api.example.com")
+            .with_fixture(TICKER, HttpFixture::json(body)),
+    )
+}
+
+fn fields() -> Vec<JsonField> {
+    vec![
+        JsonField::new("$.data.price", ValueType::Uint256).unwrap().with_decimals(8),
+        JsonField::new("data.change", ValueType::Int256).unwrap().with_decimals(2),
+        JsonField::new("$.data.live", ValueType::Bool).unwrap(),
+        JsonField::new("$.data.tags[1]", ValueType::String).unwrap(),
+    ]
+}
+
+#[tokio::test]
+async fn publishes_values_picked_by_the_request() {
+    let Some(host) = oracle() else { return };
+
+    let output =
+        host.run(TriggerAction::raw(JsonRequest::new(TICKER, fields()).encode())).await.unwrap();
+
+    let output: JsonOracleOutput = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    assert_eq!(output.url, TICKER);
+    let values: Vec<_> = output.values.iter().map(|value| value.value.clone()).collect();
+    // scaled from the string as written, not through an f64
+    assert_eq!(values, vec![json!("271234567800"), json!("-150"), json!(true), json!("pos")]);
+    assert_eq!(output.values[0].path, "$.data.price");
+    assert_eq!(output.values[0].decimals, 8);
+}
+
+#[tokio::test]
+async fn abi_encoding_publishes_solidity_values() {
+    let Some(host) = oracle() else { return };
+
+    let req = JsonRequest::new(TICKER, fields()).with_encoding(ValueEncoding::Abi);
+    let output = host.run(TriggerAction::new_trigger(3, req.encode())).await.unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert_eq!(data.triggerId, 3);
+    assert!(matches!(data.error, ErrorCode::None));
+    assert!(matches!(data.encoding, ResultEncoding::AbiValues));
+
+    let (price, change, live, tag) =
+        <(U256, I256, bool, String)>::abi_decode_params(&data.data, true).unwrap();
+    assert_eq!(price, U256::from(271234567800u64));
+    assert_eq!(change, I256::try_from(-150).unwrap());
+    assert!(live);
+    assert_eq!(tag, "pos");
+}
+
+#[tokio::test]
+async fn service_config_fixes_the_feed() {
+    let Some(host) = oracle() else { return };
+    let host = host
+        .with_env("url", TICKER)
+        .with_env("fields", &serde_json::to_string(&fields()[..1]).unwrap());
+
+    // nothing left for the request to set
+    let output = host.run(TriggerAction::raw(Vec::new())).await.unwrap();
+    let output: JsonOracleOutput = serde_json::from_slice(&output.result.unwrap()).unwrap();
+    assert_eq!(output.values.len(), 1);
+    assert_eq!(output.values[0].value, "271234567800");
+
+    let req = JsonRequest::new("https://evil.example.com/", Vec::new());
+    let output = host.run(TriggerAction::new_trigger(4, req.encode())).await.unwrap();
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert!(matches!(data.error, ErrorCode::BadInput));
+    assert_eq!(
+        String::from_utf8(data.data.to_vec()).unwrap(),
+        "`url` is fixed by the service config"
+    );
+    assert!(output.requests.is_empty());
+}
+
+#[tokio::test]
+async fn invalid_fields_config_fails_the_run() {
+    let Some(host) = oracle() else { return };
+    let host = host.with_env("fields", r#"[{"path":"$..price","type":"uint256"}]"#);
+
+    // a request's fields don't stand in for the broken config
+    let output = host
+        .run(TriggerAction::new_trigger(8, JsonRequest::new(TICKER, fields()).encode()))
+        .await
+        .unwrap();
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    assert!(matches!(data.error, ErrorCode::BadInput));
+    assert!(String::from_utf8(data.data.to_vec())
+        .unwrap()
+        .starts_with("Invalid `fields` service config: Invalid JSON path: $..price"));
+    assert!(output.requests.is_empty());
+}
+
+#[tokio::test]
+async fn publishes_failures_to_pick_values() {
+    let Some(host) = oracle() else { return };
+
+    let failure = |output: test_host::RunOutput| {
+        let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+        (data.error, String::from_utf8(data.data.to_vec()).unwrap())
+    };
+
+    let missing = vec![JsonField::new("$.data.volume", ValueType::Uint256).unwrap()];
+    let output = host
+        .run(TriggerAction::new_trigger(5, JsonRequest::new(TICKER, missing).encode()))
+        .await
+        .unwrap();
+    let (error, message) = failure(output);
+    assert!(matches!(error, ErrorCode::ParseFailure));
+    assert_eq!(message, "Nothing at $.data.volume");
+
+    let negative = vec![JsonField::new("$.data.change", ValueType::Uint256).unwrap()];
+    let output = host
+        .run(TriggerAction::new_trigger(6, JsonRequest::new(TICKER, negative).encode()))
+        .await
+        .unwrap();
+    let (error, message) = failure(output);
+    assert!(matches!(error, ErrorCode::OutOfRange));
+    assert_eq!(message, "$.data.change is negative");
+
+    let output = host
+        .with_env("allowed_hosts", "api.other.com")
+        .run(TriggerAction::new_trigger(7, JsonRequest::new(TICKER, fields()).encode()))
+        .await
+        .unwrap();
+    let (error, message) = failure(output);
+    assert!(matches!(error, ErrorCode::BadInput));
+    assert_eq!(message, "Host not allowed: api.example.com");
+}
+
+#[tokio::test]
+async fn requested_urls_need_an_allowed_public_host() {
+    let Some(host) = oracle() else { return };
+
+    let run = |host: TestHost, url: &str| {
+        let req = JsonRequest::new(url, fields());
+        async move {
+            let output = host.run(TriggerAction::new_trigger(8, req.encode())).await.unwrap();
+            assert!(output.requests.is_empty());
+            let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+            assert!(matches!(data.error, ErrorCode::BadInput));
+            String::from_utf8(data.data.to_vec()).unwrap()
+        }
+    };
+
+    // nothing is allowed without an allowlist
+    let message = run(host.with_env("allowed_hosts", ""), TICKER).await;
+    assert_eq!(message, "Host not allowed: api.example.com");
+
+    // not even an internal address on it
+    for (url, host_name) in [
+        ("http://169.254.169.254/latest/meta-data", "169.254.169.254"),
+        ("http://127.0.0.1:8545", "127.0.0.1"),
+        ("http://10.0.0.1/", "10.0.0.1"),
+        ("http://[::1]/", "[::1]"),
+        ("http://localhost/", "localhost"),
+    ] {
+        let message = run(oracle().unwrap().with_env("allowed_hosts", host_name), url).await;
+        assert_eq!(message, format!("Internal address not allowed: {host_name}"));
+    }
+}
+
+#[tokio::test]
+async fn json_timestamp_is_the_trigger_block_time() {
+    let Some(host) = oracle() else { return };
+
+    let host = host.with_mainnet_rpc(RPC).with_json_rpc(RPC, |method, params| match method {
+        "eth_getBlockByNumber" if params[0] == format!("{TRIGGER_BLOCK:#x}") => {
+            Ok(block_json(TRIGGER_BLOCK, B256::ZERO, 1739198400))
+        }
+        _ => Err(format!("unexpected {method}")),
+    });
+    let req = JsonRequest::new(TICKER, fields());
+    let output = host
+        .run(TriggerAction::new_trigger_on(MAINNET, &trigger_info(9, req.encode())))
+        .await
+        .unwrap();
+
+    let data = DataWithId::abi_decode(&output.result.unwrap(), true).unwrap();
+    let output: JsonOracleOutput = serde_json::from_slice(&data.data).unwrap();
+    assert_eq!(output.timestamp, 1739198400);
+}
@@ -0,0 +1,282 @@
+pragma solidity ^0.8.0;
+
+import {Test} from "forge-std/Test.sol";
+import {IWavsServiceManager} from "@wavs/interfaces/IWavsServiceManager.sol";
+import {SimpleSubmit, WavsAggregator} from "../src/WavsSubmit.sol";
+import {ITypes} from "../src/interfaces/ITypes.sol";
+
+/// @dev Accepts every signature, so tests can publish results directly
+contract AcceptingServiceManager {
+    function validate(bytes calldata, bytes calldata) external pure {}
+}
+
+contract SubmitTest is Test {
+    SimpleSubmit simpleSubmit;
+
+    function setUp() public {
+        simpleSubmit = new SimpleSubmit(IWavsServiceManager(address(new AcceptingServiceManager())));
+    }
+
+    function _publish(uint64 triggerId, bytes memory data, ITypes.ErrorCode error, ITypes.ResultEncoding encoding)
+        internal
+    {
+        ITypes.DataWithId memory dataWithId = ITypes.DataWithId({
+            triggerId: ITypes.TriggerId.wrap(triggerId),
+            data: data,
+            error: error,
+            requestHash: keccak256("request"),
+            encoding: encoding,
+            prices: new ITypes.AssetPrice[](0)
+        });
+        simpleSubmit.handleSignedData(abi.encode(dataWithId), "");
+    }
+
+    function _priceFeed(uint64 assetId, uint256 price) internal pure returns (ITypes.PriceFeed memory) {
+        return ITypes.PriceFeed({
+            assetId: assetId,
+            price: price,
+            decimals: 8,
+            timestamp: 1738688100,
+            blockHeight: 42,
+            txHash: keccak256("tx"),
+            mode: ITypes.PriceMode.Spot,
+            window: 0,
+            open: 0,
+            high: 0,
+            low: 0
+        });
+    }
+
+    function _relayMessage(uint64 destinationChainId) internal pure returns (ITypes.RelayMessage memory) {
+        return ITypes.RelayMessage({
+            sourceChainId: 31337,
+            sourceBlock: 42,
+            sourceBlockHash: keccak256("block"),
+            logIndex: 3,
+            sourceTrigger: address(0x5FbDB2315678afecb367f032d93F642f64180aa3),
+            sourceTxHash: keccak256("tx"),
+            creator: address(0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266),
+            destinationChainId: destinationChainId,
+            payload: "hello"
+        });
+    }
+
+    function testGetPriceFeed() public {
+        _publish(1, abi.encode(_priceFeed(1027, 271235000000)), ITypes.ErrorCode.None, ITypes.ResultEncoding.PriceFeed);
+
+        ITypes.PriceFeed memory feed = simpleSubmit.getPriceFeed(ITypes.TriggerId.wrap(1));
+        assertEq(feed.assetId, 1027);
+        assertEq(feed.price, 271235000000);
+        assertEq(feed.blockHeight, 42);
+        assertEq(simpleSubmit.getRequestHash(ITypes.TriggerId.wrap(1)), keccak256("request"));
+
+        vm.expectRevert(bytes("Result in another encoding"));
+        simpleSubmit.getPriceFeeds(ITypes.TriggerId.wrap(1));
+
+        vm.expectRevert(bytes("No result for trigger"));
+        simpleSubmit.getPriceFeed(ITypes.TriggerId.wrap(2));
+    }
+
+    function testGetPriceFeeds() public {
+        ITypes.PriceFeed[] memory feeds = new ITypes.PriceFeed[](2);
+        feeds[0] = _priceFeed(1027, 271235000000);
+        feeds[1] = _priceFeed(1, 9724100000000);
+        _publish(1, abi.encode(feeds), ITypes.ErrorCode.None, ITypes.ResultEncoding.PriceFeeds);
+
+        ITypes.PriceFeed[] memory decoded = simpleSubmit.getPriceFeeds(ITypes.TriggerId.wrap(1));
+        assertEq(decoded.length, 2);
+        assertEq(decoded[0].assetId, 1027);
+        assertEq(decoded[1].price, 9724100000000);
+
+        vm.expectRevert(bytes("Result in another encoding"));
+        simpleSubmit.getPriceFeed(ITypes.TriggerId.wrap(1));
+    }
+
+    function testGetError() public {
+        _publish(1, "Unknown asset NOPE", ITypes.ErrorCode.BadInput, ITypes.ResultEncoding.None);
+
+        (ITypes.ErrorCode code, string memory message) = simpleSubmit.getError(ITypes.TriggerId.wrap(1));
+        assertEq(uint8(code), uint8(ITypes.ErrorCode.BadInput));
+        assertEq(message, "Unknown asset NOPE");
+
+        vm.expectRevert(bytes("Oracle run failed"));
+        simpleSubmit.getPriceFeed(ITypes.TriggerId.wrap(1));
+
+        // a success has no message
+        _publish(2, abi.encode(_priceFeed(1027, 1)), ITypes.ErrorCode.None, ITypes.ResultEncoding.PriceFeed);
+        (code, message) = simpleSubmit.getError(ITypes.TriggerId.wrap(2));
+        assertEq(uint8(code), uint8(ITypes.ErrorCode.None));
+        assertEq(message, "");
+    }
+
+    function _round(uint64 triggerId, bytes32 key, int256 answer) internal pure returns (ITypes.RoundData memory) {
+        uint80 roundId = (uint80(1) << 64) | triggerId;
+        return ITypes.RoundData({
+            roundId: roundId,
+            answer: answer,
+            startedAt: 1738684560,
+            updatedAt: 1738688100,
+            answeredInRound: roundId,
+            feedKey: key
+        });
+    }
+
+    function testGetRoundData() public {
+        bytes32 eth = simpleSubmit.feedKey(1027, "USD", ITypes.PriceMode.Spot, 8);
+        bytes32 btc = simpleSubmit.feedKey(1, "USD", ITypes.PriceMode.Spot, 8);
+        uint80 roundId = (uint80(1) << 64) | 9;
+        _publish(9, abi.encode(_round(9, eth, 271235000000)), ITypes.ErrorCode.None, ITypes.ResultEncoding.RoundData);
+
+        (uint80 id, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound) =
+            simpleSubmit.getRoundData(eth, roundId);
+        assertEq(id, roundId);
+        assertEq(answer, 271235000000);
+        assertEq(startedAt, 1738684560);
+        assertEq(updatedAt, 1738688100);
+        assertEq(answeredInRound, roundId);
+
+        // the same round of another feed
+        vm.expectRevert(bytes("No data present"));
+        simpleSubmit.getRoundData(btc, roundId);
+
+        // the same trigger in another phase
+        vm.expectRevert(bytes("Unknown phase"));
+        simpleSubmit.getRoundData(eth, (uint80(2) << 64) | 9);
+    }
+
+    function testBatchRoundsAreKeptPerFeed() public {
+        bytes32 eth = simpleSubmit.feedKey(1027, "USD", ITypes.PriceMode.Spot, 8);
+        bytes32 btc = simpleSubmit.feedKey(1, "USD", ITypes.PriceMode.Spot, 8);
+        ITypes.RoundData[] memory batch = new ITypes.RoundData[](2);
+        batch[0] = _round(9, eth, 271235000000);
+        batch[1] = _round(9, btc, 9724100000000);
+        _publish(9, abi.encode(batch), ITypes.ErrorCode.None, ITypes.ResultEncoding.RoundDatas);
+
+        (, int256 answer,,,) = simpleSubmit.getRoundData(eth, (uint80(1) << 64) | 9);
+        assertEq(answer, 271235000000);
+        (, answer,,,) = simpleSubmit.latestRoundData(btc);
+        assertEq(answer, 9724100000000);
+    }
+
+    function testLatestRoundData() public {
+        bytes32 eth = simpleSubmit.feedKey(1027, "USD", ITypes.PriceMode.Spot, 8);
+        vm.expectRevert(bytes("No data present"));
+        simpleSubmit.latestRoundData(eth);
+
+        // an earlier trigger's result landing later doesn't replace the latest round
+        _publish(9, abi.encode(_round(9, eth, 271235000000)), ITypes.ErrorCode.None, ITypes.ResultEncoding.RoundData);
+        _publish(8, abi.encode(_round(8, eth, 270000000000)), ITypes.ErrorCode.None, ITypes.ResultEncoding.RoundData);
+
+        (uint80 id, int256 answer,,,) = simpleSubmit.latestRoundData(eth);
+        assertEq(id, (uint80(1) << 64) | 9);
+        assertEq(answer, 271235000000);
+    }
+
+    function testWavsAggregator() public {
+        WavsAggregator aggregator =
+            new WavsAggregator(simpleSubmit, 1027, "USD", ITypes.PriceMode.Spot, 8, "ETH / USD");
+        assertEq(aggregator.decimals(), 8);
+        assertEq(aggregator.description(), "ETH / USD");
+        assertEq(aggregator.version(), 1);
+
+        bytes32 eth = simpleSubmit.feedKey(1027, "USD", ITypes.PriceMode.Spot, 8);
+        _publish(9, abi.encode(_round(9, eth, 271235000000)), ITypes.ErrorCode.None, ITypes.ResultEncoding.RoundData);
+        // rounds with other decimals are another feed
+        bytes32 scaled = simpleSubmit.feedKey(1027, "USD", ITypes.PriceMode.Spot, 18);
+        _publish(10, abi.encode(_round(10, scaled, 1)), ITypes.ErrorCode.None, ITypes.ResultEncoding.RoundData);
+
+        (uint80 id, int256 answer,,,) = aggregator.latestRoundData();
+        assertEq(id, (uint80(1) << 64) | 9);
+        assertEq(answer, 271235000000);
+        (, answer,,,) = aggregator.getRoundData((uint80(1) << 64) | 9);
+        assertEq(answer, 271235000000);
+    }
+
+    function testGetTokenSnapshot() public {
+        address[] memory holders = new address[](2);
+        holders[0] = address(0x1111);
+        holders[1] = address(0x2222);
+        uint256[] memory balances = new uint256[](2);
+        balances[0] = 100;
+        balances[1] = 250;
+        ITypes.TokenSnapshot memory snapshot = ITypes.TokenSnapshot({
+            token: address(0xA0b86991c6218b36c1d19D4a2e9Eb0cE3606eB48),
+            blockNumber: 42,
+            blockHash: keccak256("block"),
+            totalSupply: 1000,
+            holders: holders,
+            balances: balances,
+            heldTotal: 350
+        });
+        _publish(1, abi.encode(snapshot), ITypes.ErrorCode.None, ITypes.ResultEncoding.TokenSnapshot);
+
+        ITypes.TokenSnapshot memory decoded = simpleSubmit.getTokenSnapshot(ITypes.TriggerId.wrap(1));
+        assertEq(decoded.token, snapshot.token);
+        assertEq(decoded.blockNumber, 42);
+        assertEq(decoded.blockHash, keccak256("block"));
+        assertEq(decoded.holders[1], address(0x2222));
+        assertEq(decoded.balances[1], 250);
+        assertEq(decoded.heldTotal, 350);
+    }
+
+    function testGetRelayMessage() public {
+        _publish(
+            1,
+            abi.encode(_relayMessage(uint64(block.chainid))),
+            ITypes.ErrorCode.None,
+            ITypes.ResultEncoding.RelayMessage
+        );
+        _publish(
+            2,
+            abi.encode(_relayMessage(uint64(block.chainid) + 1)),
+            ITypes.ErrorCode.None,
+            ITypes.ResultEncoding.RelayMessage
+        );
+
+        ITypes.RelayMessage memory message = simpleSubmit.getRelayMessage(ITypes.TriggerId.wrap(1));
+        assertEq(message.sourceChainId, 31337);
+        assertEq(message.sourceBlockHash, keccak256("block"));
+        assertEq(message.logIndex, 3);
+        assertEq(message.payload, "hello");
+
+        vm.expectRevert(bytes("Relayed for another chain"));
+        simpleSubmit.getRelayMessage(ITypes.TriggerId.wrap(2));
+    }
+
+    function testRelayId() public view {
+        ITypes.RelayMessage memory message = _relayMessage(31338);
+        bytes32 id = simpleSubmit.relayId(message);
+        assertEq(id, keccak256(abi.encode(uint64(31337), uint64(42), keccak256("block"), uint64(3))));
+
+        // the same height and log index on a reorged fork is another log
+        message.sourceBlockHash = keccak256("fork");
+        assertTrue(simpleSubmit.relayId(message) != id);
+
+        // what isn't part of the source log's identity doesn't change it
+        message = _relayMessage(31338);
+        message.payload = "other";
+        assertEq(simpleSubmit.relayId(message), id);
+    }
+
+    /// @dev The same vector as `feed_key_matches_solidity` in test-host/tests/codec.rs
+    function testFeedKey() public view {
+        assertEq(
+            simpleSubmit.feedKey(1027, "USD", ITypes.PriceMode.Twap, 8),
+            0x09ea1b62f7f8d61f8305b6b7e8b445b04dddb0f8fa8182bb8704b369692b8147
+        );
+    }
+
+    /// @dev The same vector as `request_hash_matches_solidity` in test-host/tests/codec.rs
+    function testRequestHash() public view {
+        ITypes.TriggerInfo memory triggerInfo = ITypes.TriggerInfo({
+            triggerId: ITypes.TriggerId.wrap(1),
+            creator: address(0xf39Fd6e51aad88F6F4ce6aB8827279cffFb92266),
+            data: "data1"
+        });
+
+        assertEq(
+            simpleSubmit.requestHash(31337, address(0x5FbDB2315678afecb367f032d93F642f64180aa3), triggerInfo),
+            0xf523763e58c9f9e915dd68c92761fa31e986cc467258226aa45c8b384618eff0
+        );
+    }
+}